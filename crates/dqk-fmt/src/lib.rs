@@ -0,0 +1,373 @@
+#![forbid(unsafe_code)]
+
+//! Canonical formatting of dqk source files.
+//!
+//! The formatter works on the lossless [`SyntaxNode`] tree, so comments are
+//! kept. Everything else is laid out from scratch:
+//!
+//! - blocks and `where` bounds are indented with one tab per level, and so
+//!   are the lines a `( )`, `[ ]` or `< >` group is broken into,
+//! - operators like `:=` and `==` have a space on each side,
+//! - `where` and every bound get a line of their own, bounds end in a comma,
+//! - runs of blank lines are collapsed into one, and there are none at the
+//!   start and end of the file or of a block.
+
+use dqk_parser::{
+    Diagnostics, FileId, Interner, Keyword, NodeKind, Parser, Punct, SyntaxNode, SyntaxToken,
+    SyntaxTokenKind, TokenKind,
+};
+
+/// Parses and formats `src`.
+///
+/// Returns `None` if `src` has errors, which are emitted to `diagnostics`.
+pub fn format_source(src: &str, file: FileId, diagnostics: &mut Diagnostics) -> Option<String> {
+    let interner = Interner::new();
+    let mut file_diagnostics = diagnostics.without_diagnostics();
+    let mut parser = Parser::new(src, file, &interner, &mut file_diagnostics);
+
+    let formatted = match parser.parse_syntax() {
+        Ok((_, tree)) => Some(format(&tree)),
+        Err(error) => {
+            file_diagnostics.emit(error);
+            None
+        }
+    };
+
+    let has_errors = file_diagnostics.has_errors();
+    diagnostics.extend(file_diagnostics);
+
+    formatted.filter(|_| !has_errors)
+}
+
+/// Formats a syntax tree, which should be free of errors.
+pub fn format(tree: &SyntaxNode) -> String {
+    let mut formatter = Formatter::default();
+
+    for token in tree.tokens() {
+        formatter.token(&token);
+    }
+
+    formatter.finish()
+}
+
+#[derive(Default)]
+struct Formatter {
+    out: String,
+    indent: usize,
+    /// Text of the current line, without indentation.
+    line: String,
+    /// Indentation of the current line, the level when it was started.
+    line_indent: usize,
+    /// Whether a blank line should come before the next line.
+    blank: bool,
+    /// Whether the previous line was ended by a comment, so the line break in
+    /// the source that follows it is not a blank line.
+    after_comment: bool,
+    /// Whether the tokens are inside the bounds of a `where` clause.
+    in_where: bool,
+    /// Number of `( )`, `[ ]` and `< >` groups the tokens are in, where the
+    /// line breaks of the source are kept but blank lines are not.
+    groups: usize,
+    /// Whether the current line ends before the next token, unless that is a
+    /// comment on the same line.
+    pending: bool,
+    /// Kind of the last token written to the current line.
+    prev: Option<TokenKind>,
+}
+
+impl Formatter {
+    fn token(&mut self, token: &SyntaxToken) {
+        let parent = token.parent().kind();
+
+        let kind = match token.kind() {
+            SyntaxTokenKind::Whitespace => return,
+            SyntaxTokenKind::Comment => return self.comment(token.text()),
+            SyntaxTokenKind::Unknown => return self.push(token.text(), true),
+            SyntaxTokenKind::Token(kind) => kind,
+        };
+
+        if self.pending && kind != TokenKind::Eol {
+            self.newline();
+        }
+
+        match kind {
+            TokenKind::Eol if self.groups > 0 => self.newline(),
+            // line breaks in a listener header don't matter, it has its own layout
+            TokenKind::Eol if matches!(parent, NodeKind::LnStmt | NodeKind::WhereClause) => {
+                if self.pending {
+                    self.newline();
+                }
+            }
+            TokenKind::Eol => self.eol(),
+            TokenKind::Keyword(Keyword::Where) => {
+                self.newline();
+                self.push(token.text(), false);
+                self.pending = true;
+
+                self.indent += 1;
+                self.in_where = true;
+            }
+            TokenKind::Punct(Punct::Comma) if self.in_where => {
+                self.push(token.text(), false);
+                self.pending = true;
+            }
+            TokenKind::Punct(Punct::OpenBrace) => {
+                if self.in_where {
+                    if !self.line.is_empty() {
+                        self.push(",", false);
+                    }
+
+                    self.newline();
+
+                    self.indent -= 1;
+                    self.in_where = false;
+                }
+
+                self.push(token.text(), true);
+                self.pending = true;
+
+                self.indent += 1;
+            }
+            TokenKind::Punct(Punct::CloseBrace) => {
+                self.newline();
+
+                self.indent = self.indent.saturating_sub(1);
+                self.blank = false;
+
+                self.push(token.text(), false);
+            }
+            _ if is_open(kind, parent) => {
+                let space = self.space(kind, parent);
+                self.push(token.text(), space);
+                self.prev = Some(kind);
+
+                self.indent += 1;
+                self.groups += 1;
+            }
+            _ if is_close(kind, parent) => {
+                self.indent = self.indent.saturating_sub(1);
+                self.groups = self.groups.saturating_sub(1);
+
+                let space = self.space(kind, parent);
+                self.push(token.text(), space);
+                self.prev = Some(kind);
+            }
+            _ => {
+                let space = self.space(kind, parent);
+                self.push(token.text(), space);
+                self.prev = Some(kind);
+            }
+        }
+    }
+
+    /// Whether a token needs a space before it, after the previous one.
+    fn space(&self, kind: TokenKind, parent: NodeKind) -> bool {
+        let prev = match self.prev {
+            Some(prev) => prev,
+            None => return false,
+        };
+
+        let tight_after = matches!(
+            prev,
+            TokenKind::Punct(Punct::OpenParen | Punct::OpenBracket | Punct::Period | Punct::Lt)
+                | TokenKind::StringStart(_)
+                | TokenKind::StringMiddle(_)
+        );
+        let tight_before = match kind {
+            TokenKind::Punct(
+                Punct::Comma | Punct::CloseParen | Punct::CloseBracket | Punct::Period | Punct::Gt,
+            ) => true,
+            TokenKind::Punct(Punct::Lt) => true,
+            TokenKind::StringMiddle(_) | TokenKind::StringEnd(_) => true,
+            TokenKind::Punct(Punct::OpenParen) => {
+                matches!(parent, NodeKind::ListenerArguments | NodeKind::ClosureExpr)
+            }
+            _ => false,
+        };
+
+        !tight_after && !tight_before
+    }
+
+    fn push(&mut self, text: &str, space: bool) {
+        if self.line.is_empty() {
+            self.line_indent = self.indent;
+        } else if space {
+            self.line.push(' ');
+        }
+
+        self.line.push_str(text);
+        self.after_comment = false;
+    }
+
+    fn comment(&mut self, text: &str) {
+        let text = text.trim_end();
+
+        if self.line.is_empty() {
+            self.line_indent = self.indent;
+        } else {
+            self.line.push(' ');
+        }
+
+        self.line.push_str(text);
+
+        self.newline();
+        self.after_comment = true;
+    }
+
+    fn eol(&mut self) {
+        if !self.line.is_empty() {
+            self.newline();
+        } else if self.after_comment {
+            self.after_comment = false;
+        } else if !self.out.is_empty() && !self.out.ends_with("{\n") {
+            self.blank = true;
+        }
+    }
+
+    /// Ends the current line, if it has anything on it.
+    fn newline(&mut self) {
+        if self.line.is_empty() {
+            return;
+        }
+
+        if self.blank {
+            self.out.push('\n');
+            self.blank = false;
+        }
+
+        for _ in 0..self.line_indent {
+            self.out.push('\t');
+        }
+
+        self.out.push_str(&self.line);
+        self.out.push('\n');
+
+        self.line.clear();
+        self.pending = false;
+        self.prev = None;
+    }
+
+    fn finish(mut self) -> String {
+        self.blank = false;
+        self.newline();
+        self.out
+    }
+}
+
+/// Whether the token opens a `( )`, `[ ]` or `< >` group, a `<` is only a
+/// delimiter around the events of a listener.
+fn is_open(kind: TokenKind, parent: NodeKind) -> bool {
+    match kind {
+        TokenKind::Punct(Punct::OpenParen | Punct::OpenBracket) => true,
+        TokenKind::Punct(Punct::Lt) => parent == NodeKind::DefaultEvents,
+        _ => false,
+    }
+}
+
+/// Whether the token closes a group, see [`is_open`].
+fn is_close(kind: TokenKind, parent: NodeKind) -> bool {
+    match kind {
+        TokenKind::Punct(Punct::CloseParen | Punct::CloseBracket) => true,
+        TokenKind::Punct(Punct::Gt) => parent == NodeKind::DefaultEvents,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dqk_parser::SourceMap;
+
+    use super::*;
+
+    fn fmt(src: &str) -> String {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let mut diagnostics = Diagnostics::new();
+
+        let formatted = format_source(src, file, &mut diagnostics).unwrap();
+        let again = format_source(&formatted, file, &mut diagnostics).unwrap();
+
+        assert_eq!(again, formatted, "formatting is not idempotent");
+
+        formatted
+    }
+
+    #[test]
+    fn listener() {
+        let src = "ln log_message<message_sent>(Message) where contents == 'ping',\n     is_bot == 0 {\n  channel.send   'pong'\n}\n";
+
+        assert_eq!(
+            fmt(src),
+            "ln log_message<message_sent>(Message)\nwhere\n\tcontents == 'ping',\n\tis_bot == 0,\n{\n\tchannel.send 'pong'\n}\n"
+        );
+    }
+
+    #[test]
+    fn spacing() {
+        assert_eq!(
+            fmt("x:=(1+2)*3\ny=x.len\nsend x,y ,  (z)\n"),
+            "x := (1 + 2) * 3\ny = x.len\nsend x, y, (z)\n"
+        );
+    }
+
+    #[test]
+    fn lists_and_closures() {
+        assert_eq!(
+            fmt("xs := [ 1,2 ]\nf := fn ( a,b ) a+b\nf [ ], [3]\n"),
+            "xs := [1, 2]\nf := fn(a, b) a + b\nf [], [3]\n"
+        );
+    }
+
+    #[test]
+    fn line_breaks_in_groups() {
+        assert_eq!(
+            fmt("ln f(\nMessage\n) {\n}\n"),
+            "ln f(\n\tMessage\n) {\n}\n"
+        );
+        assert_eq!(fmt("xs := [1,\n 2]\n"), "xs := [1,\n\t2]\n");
+        assert_eq!(
+            fmt("ln f <\n  message_sent,\n\n  message_edited\n>(Message) {\n}\n"),
+            "ln f<\n\tmessage_sent,\n\tmessage_edited\n>(Message) {\n}\n"
+        );
+        assert_eq!(
+            fmt("ln f(M) {\nx := (1 + # one\n(2 *\n3))\n}\n"),
+            "ln f(M) {\n\tx := (1 + # one\n\t\t(2 *\n\t\t\t3))\n}\n"
+        );
+    }
+
+    #[test]
+    fn interpolated_strings() {
+        assert_eq!(
+            fmt("send 'hi { name }, {a+1}!'\n"),
+            "send 'hi {name}, {a + 1}!'\n"
+        );
+    }
+
+    #[test]
+    fn blank_lines_and_comments() {
+        let src = "\n\n# header\n\n\n\nx := 1   # one\n\n\n\nln f(M) {\n\n    # inside\n\n    y := 2\n\n}\n\n\n";
+
+        assert_eq!(
+            fmt(src),
+            "# header\n\nx := 1 # one\n\nln f(M) {\n\t# inside\n\n\ty := 2\n}\n"
+        );
+    }
+
+    #[test]
+    fn comments_in_where_clause() {
+        let src = "ln f(M)\nwhere # bounds\n\ta == 1, # first\n\t# second\n\tb == 2,\n{\n}\n";
+
+        assert_eq!(
+            fmt(src),
+            "ln f(M)\nwhere # bounds\n\ta == 1, # first\n\t# second\n\tb == 2,\n{\n}\n"
+        );
+    }
+
+    #[test]
+    fn idempotent_on_example() {
+        let src = include_str!("../../../tests/ping.dqk");
+
+        assert_eq!(fmt(&fmt(src)), fmt(src));
+    }
+}
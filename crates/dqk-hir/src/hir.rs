@@ -0,0 +1,161 @@
+use std::rc::Rc;
+
+use dqk_parser::{Span, Symbol};
+use dqk_resolve::DefId;
+use dqk_schema::{Event, Field, Method};
+use dqk_typeck::Ty;
+
+/// The lowered statements of a whole source file.
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+impl Program {
+    /// Every listener of the program, including the ones declared in blocks.
+    pub fn listeners(&self) -> impl Iterator<Item = &Listener> {
+        let mut listeners = Vec::new();
+        collect_listeners(&self.stmts, &mut listeners);
+
+        listeners.into_iter()
+    }
+}
+
+fn collect_listeners<'a>(stmts: &'a [Stmt], listeners: &mut Vec<&'a Listener>) {
+    for stmt in stmts {
+        if let StmtKind::Listener(listener) = &stmt.kind {
+            listeners.push(listener);
+            collect_listeners(&listener.body, listeners);
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum StmtKind {
+    Listener(Box<Listener>),
+    /// `name := expr`
+    Let {
+        def: DefId,
+        expr: Expr,
+    },
+    /// `name = expr`
+    Assign {
+        def: DefId,
+        expr: Expr,
+    },
+    Expr(Expr),
+}
+
+/// A listener declared with `ln`.
+#[derive(Clone, Debug)]
+pub struct Listener {
+    pub name: Symbol,
+    /// The default events, unknown ones are left out.
+    pub events: Vec<Event>,
+    pub params: Vec<Param>,
+    /// The bounds of the `where` clause joined with [`BinOp::And`], the
+    /// listener only runs if it is true.
+    pub guard: Option<Expr>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub kind: ParamKind,
+    pub ty: Ty,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum ParamKind {
+    /// `(Message)`, the fields of the entity are bound to their definitions.
+    Expanded(Vec<(&'static Field, DefId)>),
+    /// `(name; Type)`
+    Named(DefId),
+}
+
+#[derive(Clone, Debug)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub ty: Ty,
+    pub span: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum ExprKind {
+    Integer(i64),
+    Float(f64),
+    String(Symbol),
+    List(Vec<Expr>),
+    Closure {
+        params: Vec<DefId>,
+        /// Shared with the closure values created from it.
+        body: Rc<Expr>,
+    },
+    Variable(DefId),
+    /// `message.contents`
+    Field {
+        expr: Box<Expr>,
+        field: &'static Field,
+    },
+    /// A method that is not called, like the `channel.send` of
+    /// `reply := channel.send`, which is a function.
+    Method {
+        receiver: Box<Expr>,
+        method: &'static Method,
+    },
+    /// `channel.send 'pong'`, and `message.delete`, which takes no arguments
+    /// and is called without them.
+    MethodCall {
+        receiver: Box<Expr>,
+        method: &'static Method,
+        args: Vec<Expr>,
+    },
+    /// A call of anything but a method.
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+    },
+    BinOp {
+        op: BinOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// Converts a value to a `String`, for the interpolations of a string.
+    ToString(Box<Expr>),
+    /// An expression with an error, like an unresolved name.
+    Error,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    /// Both `Bool`s are true, the right one is only evaluated if the left one
+    /// is. Only the guards of listeners use it.
+    And,
+}
+
+impl BinOp {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Eq => "==",
+            Self::And => "and",
+        }
+    }
+}
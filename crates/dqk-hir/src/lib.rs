@@ -0,0 +1,19 @@
+#![forbid(unsafe_code)]
+
+//! The high-level intermediate representation of dqk programs.
+//!
+//! A type checked [`Program`](dqk_parser::Program) is [`lower`]ed into a tree
+//! that is easier to run or compile than the syntax it comes from. Names are
+//! replaced by the [`DefId`](dqk_resolve::DefId)s they resolve to and every
+//! expression carries its [`Ty`](dqk_typeck::Ty). Syntax that only exists for
+//! convenience is desugared: parentheses are dropped, members are split into
+//! fields and method calls, methods without parameters are called explicitly,
+//! interpolated strings become concatenations and the bounds of a `where`
+//! clause become a single guard. Every node keeps the [`Span`](dqk_parser::Span)
+//! of the syntax it was lowered from, for diagnostics.
+
+mod hir;
+mod lower;
+
+pub use hir::*;
+pub use lower::*;
@@ -0,0 +1,455 @@
+use std::rc::Rc;
+
+use dqk_parser::{self as ast, Interner, InterpolationNext, LiteralExpr, Span, Spanned, Symbol};
+use dqk_resolve::{DefKind, Resolution};
+use dqk_schema::Event;
+use dqk_typeck::{Ty, Types};
+
+use crate::{BinOp, Expr, ExprKind, Listener, Param, ParamKind, Program, Stmt, StmtKind};
+
+/// Lowers a type checked `program` to HIR.
+///
+/// The program is expected to have no errors. Names that `resolution` couldn't
+/// resolve are lowered to [`ExprKind::Error`], and declarations of them to
+/// expression statements.
+pub fn lower(
+    program: &ast::Program,
+    resolution: &Resolution,
+    types: &Types,
+    interner: &Interner,
+) -> Program {
+    let lower = Lower {
+        resolution,
+        types,
+        interner,
+    };
+
+    Program {
+        stmts: program.stmts.iter().map(|stmt| lower.stmt(stmt)).collect(),
+        span: program.span(),
+    }
+}
+
+struct Lower<'a> {
+    resolution: &'a Resolution,
+    types: &'a Types,
+    interner: &'a Interner,
+}
+
+impl Lower<'_> {
+    fn stmt(&self, stmt: &ast::Stmt) -> Stmt {
+        let kind = match stmt {
+            ast::Stmt::Ln(ln) => StmtKind::Listener(Box::new(self.listener(ln))),
+            ast::Stmt::AssignNew(stmt) => {
+                let expr = self.expr(&stmt.expr);
+
+                match self.resolution.resolve(&stmt.ident) {
+                    Some(def) => StmtKind::Let { def, expr },
+                    None => StmtKind::Expr(expr),
+                }
+            }
+            ast::Stmt::Assign(stmt) => {
+                let expr = self.expr(&stmt.expr);
+
+                match self.resolution.resolve(&stmt.ident) {
+                    Some(def) => StmtKind::Assign { def, expr },
+                    None => StmtKind::Expr(expr),
+                }
+            }
+            ast::Stmt::Expr(stmt) => StmtKind::Expr(self.expr(&stmt.expr)),
+        };
+
+        Stmt {
+            kind,
+            span: stmt.span(),
+        }
+    }
+
+    fn listener(&self, ln: &ast::LnStmt) -> Listener {
+        let events = (ln.default_events.iter())
+            .flat_map(|default_events| default_events.events.iter())
+            .filter_map(|default| Event::from_name(self.interner.resolve(default.ident.value())))
+            .collect();
+
+        let params = ln.args.args.iter().map(|arg| self.param(arg)).collect();
+
+        // `where a, b` runs the listener if `a and b`
+        let guard = (ln.where_clause.iter())
+            .flat_map(|where_clause| where_clause.bounds.iter())
+            .map(|bound| self.expr(bound))
+            .reduce(|lhs, rhs| Expr {
+                span: lhs.span | rhs.span,
+                ty: Ty::Bool,
+                kind: ExprKind::BinOp {
+                    op: BinOp::And,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            });
+
+        Listener {
+            name: ln.ident.value(),
+            events,
+            params,
+            guard,
+            body: ln.block.stmts.iter().map(|stmt| self.stmt(stmt)).collect(),
+            span: ln.span(),
+        }
+    }
+
+    fn param(&self, arg: &ast::ListenerArgument) -> Param {
+        let span = arg.span();
+
+        match arg {
+            ast::ListenerArgument::Expanded(ast::Type::Ident(ident)) => {
+                // the fields are defined at the span of the type
+                let fields = (self.resolution.definitions())
+                    .filter(|(_, def)| def.span == span)
+                    .filter_map(|(id, def)| match def.kind {
+                        DefKind::Field { entity, name } => Some((entity.field(name)?, id)),
+                        _ => None,
+                    })
+                    .collect();
+
+                Param {
+                    kind: ParamKind::Expanded(fields),
+                    ty: Ty::from_name(self.interner.resolve(ident.value())).unwrap_or(Ty::Error),
+                    span,
+                }
+            }
+            ast::ListenerArgument::Args(arg) => {
+                let def = self.resolution.resolve(&arg.ident);
+                let ty = def.and_then(|def| self.types.def(def)).cloned();
+
+                Param {
+                    kind: match def {
+                        Some(def) => ParamKind::Named(def),
+                        // a duplicate name, which binds nothing
+                        None => ParamKind::Expanded(Vec::new()),
+                    },
+                    ty: ty.unwrap_or(Ty::Error),
+                    span,
+                }
+            }
+        }
+    }
+
+    fn ty(&self, expr: &ast::Expr) -> Ty {
+        self.types.expr(expr).cloned().unwrap_or(Ty::Error)
+    }
+
+    fn expr(&self, expr: &ast::Expr) -> Expr {
+        let kind = match expr {
+            ast::Expr::Paren(paren) => return self.expr(&paren.expr),
+            ast::Expr::Literal(LiteralExpr::Integer(int)) => ExprKind::Integer(int.value().value()),
+            ast::Expr::Literal(LiteralExpr::Float(float)) => ExprKind::Float(float.value().value()),
+            ast::Expr::Literal(LiteralExpr::String(string)) => ExprKind::String(string.value()),
+            ast::Expr::Interpolated(interpolated) => return self.interpolated(interpolated),
+            ast::Expr::List(list) => {
+                ExprKind::List(list.items.iter().map(|item| self.expr(item)).collect())
+            }
+            ast::Expr::Closure(closure) => match closure
+                .params
+                .iter()
+                .map(|param| self.resolution.resolve(param))
+                .collect::<Option<_>>()
+            {
+                Some(params) => ExprKind::Closure {
+                    params,
+                    body: Rc::new(self.expr(&closure.body)),
+                },
+                None => ExprKind::Error,
+            },
+            ast::Expr::Variable(ident) => match self.resolution.resolve(ident) {
+                Some(def) => ExprKind::Variable(def),
+                None => ExprKind::Error,
+            },
+            ast::Expr::Member(member) => self.member(member),
+            ast::Expr::Call(call) => self.call(call),
+            ast::Expr::BinOp(bin_op) => ExprKind::BinOp {
+                op: match bin_op.op {
+                    ast::BinOp::Add(_) => BinOp::Add,
+                    ast::BinOp::Sub(_) => BinOp::Sub,
+                    ast::BinOp::Mul(_) => BinOp::Mul,
+                    ast::BinOp::Div(_) => BinOp::Div,
+                    ast::BinOp::Eq(_) => BinOp::Eq,
+                },
+                lhs: Box::new(self.expr(&bin_op.lhs)),
+                rhs: Box::new(self.expr(&bin_op.rhs)),
+            },
+        };
+
+        Expr {
+            kind,
+            ty: self.ty(expr),
+            span: expr.span(),
+        }
+    }
+
+    /// A field, or a method of a Discord type. Methods without parameters are
+    /// called, like the type checker does.
+    fn member(&self, member: &ast::MemberExpr) -> ExprKind {
+        let receiver = self.expr(&member.expr);
+        let name = self.interner.resolve(member.ident.value());
+
+        let entity = match receiver.ty {
+            Ty::Entity(entity) => entity,
+            _ => return ExprKind::Error,
+        };
+
+        if let Some(field) = entity.field(name) {
+            return ExprKind::Field {
+                expr: Box::new(receiver),
+                field,
+            };
+        }
+
+        match entity.method(name) {
+            Some(method) if method.params.is_empty() => ExprKind::MethodCall {
+                receiver: Box::new(receiver),
+                method,
+                args: Vec::new(),
+            },
+            Some(method) => ExprKind::Method {
+                receiver: Box::new(receiver),
+                method,
+            },
+            None => ExprKind::Error,
+        }
+    }
+
+    /// Calls of methods are merged with the method into a
+    /// [`ExprKind::MethodCall`].
+    fn call(&self, call: &ast::CallExpr) -> ExprKind {
+        let callee = self.expr(&call.expr);
+        let args = call.args.iter().map(|arg| self.expr(arg)).collect();
+
+        match callee.kind {
+            ExprKind::Method { receiver, method } => ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            },
+            kind => ExprKind::Call {
+                callee: Box::new(Expr { kind, ..callee }),
+                args,
+            },
+        }
+    }
+
+    /// `'a{x}b'` is lowered to `'a' + to_string(x) + 'b'`, leaving out empty
+    /// parts and conversions of values that already are strings.
+    fn interpolated(&self, interpolated: &ast::InterpolatedExpr) -> Expr {
+        let mut parts = Vec::new();
+
+        let text = |value: Symbol, span: Span, parts: &mut Vec<Expr>| {
+            if !self.interner.resolve(value).is_empty() {
+                parts.push(Expr {
+                    kind: ExprKind::String(value),
+                    ty: Ty::String,
+                    span,
+                });
+            }
+        };
+
+        text(
+            interpolated.start.value(),
+            interpolated.start.span(),
+            &mut parts,
+        );
+
+        for part in interpolated.parts.iter() {
+            let expr = self.expr(&part.expr);
+
+            parts.push(match expr.ty {
+                Ty::String => expr,
+                _ => Expr {
+                    span: expr.span,
+                    ty: Ty::String,
+                    kind: ExprKind::ToString(Box::new(expr)),
+                },
+            });
+
+            match &part.next {
+                InterpolationNext::Middle(middle) => {
+                    text(middle.value(), middle.span(), &mut parts)
+                }
+                InterpolationNext::End(end) => text(end.value(), end.span(), &mut parts),
+            }
+        }
+
+        let concat = parts.into_iter().reduce(|lhs, rhs| Expr {
+            span: lhs.span | rhs.span,
+            ty: Ty::String,
+            kind: ExprKind::BinOp {
+                op: BinOp::Add,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        });
+
+        Expr {
+            kind: concat
+                .expect("interpolated strings have an expression")
+                .kind,
+            ty: Ty::String,
+            span: interpolated.span(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dqk_parser::{Diagnostics, Parser, SourceMap};
+    use dqk_schema::Entity;
+
+    use super::*;
+
+    struct Lowered {
+        program: Program,
+        resolution: Resolution,
+        interner: Interner,
+    }
+
+    fn lower_source(src: &str) -> Lowered {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<ast::Program>().unwrap();
+        let resolution = dqk_resolve::resolve(&program, &interner, &mut diagnostics);
+        let types = dqk_typeck::check(&program, &resolution, &interner, &mut diagnostics);
+        assert!(diagnostics.is_empty());
+
+        Lowered {
+            program: lower(&program, &resolution, &types, &interner),
+            resolution,
+            interner,
+        }
+    }
+
+    impl Lowered {
+        /// Prints `expr` as an s-expression.
+        fn show(&self, expr: &Expr) -> String {
+            let list = |exprs: &[Expr]| {
+                let exprs: Vec<_> = exprs.iter().map(|expr| self.show(expr)).collect();
+                exprs.join(" ")
+            };
+
+            match &expr.kind {
+                ExprKind::Integer(value) => value.to_string(),
+                ExprKind::Float(value) => value.to_string(),
+                ExprKind::String(value) => format!("{:?}", self.interner.resolve(*value)),
+                ExprKind::List(items) => format!("[{}]", list(items)),
+                ExprKind::Closure { params, body } => {
+                    let params: Vec<_> = (params.iter()).map(|param| self.name(*param)).collect();
+                    format!("(fn ({}) {})", params.join(" "), self.show(body))
+                }
+                ExprKind::Variable(def) => self.name(*def).to_string(),
+                ExprKind::Field { expr, field } => format!("{}.{}", self.show(expr), field.name),
+                ExprKind::Method { receiver, method } => {
+                    format!("(method {} {})", self.show(receiver), method.name)
+                }
+                ExprKind::MethodCall {
+                    receiver,
+                    method,
+                    args,
+                } => format!("({}.{} {})", self.show(receiver), method.name, list(args)),
+                ExprKind::Call { callee, args } => {
+                    format!("({} {})", self.show(callee), list(args))
+                }
+                ExprKind::BinOp { op, lhs, rhs } => {
+                    format!("({} {} {})", op.as_str(), self.show(lhs), self.show(rhs))
+                }
+                ExprKind::ToString(expr) => format!("(to_string {})", self.show(expr)),
+                ExprKind::Error => "error".to_string(),
+            }
+        }
+
+        fn name(&self, def: dqk_resolve::DefId) -> &str {
+            self.interner.resolve(self.resolution.definition(def).name)
+        }
+
+        fn listener(&self) -> &Listener {
+            self.program.listeners().next().expect("a listener")
+        }
+
+        /// The value of the `index`th statement.
+        fn value(&self, stmts: &[Stmt], index: usize) -> String {
+            match &stmts[index].kind {
+                StmtKind::Let { expr, .. }
+                | StmtKind::Assign { expr, .. }
+                | StmtKind::Expr(expr) => self.show(expr),
+                StmtKind::Listener(_) => panic!("expected a statement with a value"),
+            }
+        }
+    }
+
+    #[test]
+    fn ping() {
+        let lowered = lower_source(include_str!("../../../tests/ping.dqk"));
+        let listener = lowered.listener();
+
+        assert_eq!(listener.events, [Event::MessageSent]);
+        assert_eq!(listener.params[0].ty, Ty::Entity(Entity::Message));
+
+        let fields: Vec<_> = match &listener.params[0].kind {
+            ParamKind::Expanded(fields) => fields.iter().map(|(field, _)| field.name).collect(),
+            kind => panic!("expected expanded argument, found {:?}", kind),
+        };
+        assert!(fields.contains(&"channel") && fields.contains(&"contents"));
+
+        let guard = listener.guard.as_ref().unwrap();
+        assert_eq!(lowered.show(guard), "(== contents \"ping\")");
+        assert_eq!(guard.ty, Ty::Bool);
+
+        assert_eq!(lowered.value(&listener.body, 0), "(channel.send \"pong\")");
+    }
+
+    #[test]
+    fn where_bounds_are_joined() {
+        let lowered = lower_source(
+            "ln f<message_sent>(Message)\nwhere\n\tcontents == 'a',\n\tid == 1,\n\tis_bot == is_bot,\n{\n}\n",
+        );
+
+        assert_eq!(
+            lowered.show(lowered.listener().guard.as_ref().unwrap()),
+            "(and (and (== contents \"a\") (== id 1)) (== is_bot is_bot))"
+        );
+    }
+
+    #[test]
+    fn calls_are_normalised() {
+        let lowered = lower_source(
+            "ln f<message_sent>(Message) {\n\treply := channel.send\n\treply 'hi'\n\t(channel.send) 'hi'\n}\n",
+        );
+        let body = &lowered.listener().body;
+
+        assert_eq!(lowered.value(body, 0), "(method channel send)");
+        assert_eq!(lowered.value(body, 1), "(reply \"hi\")");
+        assert_eq!(lowered.value(body, 2), "(channel.send \"hi\")");
+    }
+
+    #[test]
+    fn interpolations_are_concatenated() {
+        let lowered = lower_source("n := 2\ns := 'n'\nx := 'n is {n + 1}!'\ny := '{s}'\n");
+        let stmts = &lowered.program.stmts;
+
+        assert_eq!(
+            lowered.value(stmts, 2),
+            "(+ (+ \"n is \" (to_string (+ n 1))) \"!\")"
+        );
+        assert_eq!(lowered.value(stmts, 3), "s");
+
+        match &stmts[2].kind {
+            StmtKind::Let { expr, .. } => {
+                assert_eq!(expr.ty, Ty::String);
+                assert_eq!(expr.span.length(), "'n is {n + 1}!'".len());
+            }
+            kind => panic!("expected declaration, found {:?}", kind),
+        }
+    }
+}
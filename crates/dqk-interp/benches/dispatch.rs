@@ -0,0 +1,80 @@
+//! Compares the tree-walking interpreter to the VM by dispatching the same
+//! event to the same scripts many times.
+//!
+//! Run with `cargo bench -p dqk-interp`.
+
+use std::time::{Duration, Instant};
+
+use dqk_interp::{compile, EventData, Interpreter, Vm};
+use dqk_parser::{Diagnostics, Interner, Parser, Program, SourceMap};
+
+const EVENTS: usize = 100_000;
+
+const SCRIPTS: &[(&str, &str)] = &[
+    ("ping", include_str!("../../../tests/ping.dqk")),
+    ("count", include_str!("count.dqk")),
+];
+
+const EVENT: &str = include_str!("../../../tests/ping.json");
+
+fn main() {
+    let event = EventData::from_json(&serde_json::from_str(EVENT).unwrap()).unwrap();
+
+    println!("dispatching {} events", EVENTS);
+
+    for (name, src) in SCRIPTS {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add(format!("{}.dqk", name), *src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<Program>().unwrap();
+        let resolution = dqk_resolve::resolve(&program, &interner, &mut diagnostics);
+        let types = dqk_typeck::check(&program, &resolution, &interner, &mut diagnostics);
+        assert!(diagnostics.is_empty(), "{} has errors", name);
+
+        let program = dqk_hir::lower(&program, &resolution, &types, &interner);
+
+        let interp = time(|| {
+            let mut interpreter = Interpreter::new(&resolution, &interner);
+            interpreter.run(&program).unwrap();
+
+            for _ in 0..EVENTS {
+                interpreter.dispatch(&event).unwrap();
+            }
+        });
+
+        let module = compile(&program, &resolution, &interner).unwrap();
+
+        let vm = time(|| {
+            let mut vm = Vm::new(&module);
+            vm.run().unwrap();
+
+            for _ in 0..EVENTS {
+                vm.dispatch(&event).unwrap();
+            }
+        });
+
+        println!(
+            "{:<8} interpreter {:>8.2?}  vm {:>8.2?}  ({:.2}x)",
+            name,
+            interp,
+            vm,
+            interp.as_secs_f64() / vm.as_secs_f64(),
+        );
+    }
+}
+
+/// The fastest of a few runs of `f`.
+fn time(mut f: impl FnMut()) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
@@ -0,0 +1,248 @@
+use std::{fmt::Write, rc::Rc};
+
+use dqk_parser::{SourceMap, Span};
+use dqk_schema::{Entity, Event, Field, Method};
+
+use crate::Value;
+
+/// An instruction of the [`Vm`](crate::Vm).
+///
+/// Instructions work on a stack of values. The locals of a call are the first
+/// slots of its part of the stack, the arguments of a closure being the first
+/// locals. Variables that closures capture live in cells instead, which are
+/// shared with the closures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// Pushes a constant of the chunk.
+    Constant(u32),
+    Unit,
+    Pop,
+    GetGlobal(u32),
+    /// Pops a value into a global.
+    SetGlobal(u32),
+    GetLocal(u16),
+    SetLocal(u16),
+    GetCell(u16),
+    SetCell(u16),
+    /// Pushes the value of a cell captured from the enclosing function.
+    GetCapture(u16),
+    SetCapture(u16),
+    /// Replaces an entity with one of its fields.
+    GetField(u16),
+    /// Pops a value and an entity, and pushes a copy of the entity with the
+    /// field set to the value.
+    SetField(u16),
+    /// Replaces an entity with one of its methods, as a value.
+    Method(u16),
+    /// Pops the arguments and the entity and calls the method.
+    CallMethod(u16, u8),
+    /// Pops the arguments and the callee and calls it.
+    Call(u8),
+    /// Pops items into a list.
+    List(u16),
+    /// Pushes a closure of a function of the module, capturing its cells.
+    Closure(u32),
+    /// Declares a listener of the module, capturing its cells.
+    Listener(u32),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    ToString,
+    /// Jumps to the instruction if the value on top of the stack is `false`,
+    /// without popping it.
+    JumpIfFalse(u32),
+    /// Pops the guard of a listener, and returns `false` if it isn't `true`.
+    Guard,
+    Return,
+}
+
+/// Instructions and what they refer to.
+#[derive(Clone, Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    /// Span of the expression each instruction was compiled from.
+    pub spans: Vec<Span>,
+    pub constants: Vec<Value>,
+    pub fields: Vec<&'static Field>,
+    pub methods: Vec<&'static Method>,
+}
+
+impl Chunk {
+    pub(crate) fn push(&mut self, op: Op, span: Span) -> usize {
+        self.code.push(op);
+        self.spans.push(span);
+
+        self.code.len() - 1
+    }
+}
+
+/// Where a closure gets a captured cell from, in the frame it is created in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Capture {
+    Cell(u16),
+    Capture(u16),
+}
+
+/// The top level of a program, a listener or a closure.
+#[derive(Clone, Debug)]
+pub struct Function {
+    pub name: String,
+    pub arity: u16,
+    /// Slots of the stack reserved for locals, including the arguments.
+    pub locals: u16,
+    /// Cells created for every call.
+    pub cells: u16,
+    pub captures: Vec<Capture>,
+    pub chunk: Chunk,
+}
+
+/// Where the value of a variable is kept.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Place {
+    Global(u32),
+    Local(u16),
+    Cell(u16),
+    Capture(u16),
+}
+
+/// A listener and how to pass an event to it.
+#[derive(Clone, Debug)]
+pub struct ListenerInfo {
+    pub events: Vec<Event>,
+    /// The entity of every argument, if it is one, and the local or cell it is
+    /// passed in.
+    pub params: Vec<(Option<Entity>, Place)>,
+    pub function: u32,
+}
+
+/// A compiled program, see [`compile`](crate::compile).
+#[derive(Clone, Debug)]
+pub struct Module {
+    /// The functions, starting with the top level of the program.
+    pub functions: Vec<Rc<Function>>,
+    pub listeners: Vec<ListenerInfo>,
+    pub globals: u32,
+}
+
+impl Module {
+    /// Lists the instructions of every function, with the line and column
+    /// they were compiled from.
+    pub fn disassemble(&self, source_map: &SourceMap) -> String {
+        let mut out = String::new();
+
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 {
+                out.push('\n');
+            }
+
+            let _ = writeln!(
+                out,
+                "fn {} #{} (arity {}, locals {}, cells {}, captures {})",
+                function.name,
+                index,
+                function.arity,
+                function.locals,
+                function.cells,
+                function.captures.len(),
+            );
+
+            let listener = self.listeners.iter().find(|l| l.function == index as u32);
+
+            if let Some(listener) = listener.filter(|listener| !listener.events.is_empty()) {
+                let events: Vec<_> = listener.events.iter().map(Event::name).collect();
+                let _ = writeln!(out, "  events <{}>", events.join(", "));
+            }
+
+            let chunk = &function.chunk;
+            let mut line = None;
+
+            for (offset, (op, span)) in chunk.code.iter().zip(&chunk.spans).enumerate() {
+                let position = source_map.line_col(*span);
+
+                let location = if line == Some(position.line) {
+                    "|".to_string()
+                } else {
+                    position.to_string()
+                };
+                line = Some(position.line);
+
+                let comment = match *op {
+                    Op::Constant(index) => match &chunk.constants[index as usize] {
+                        Value::String(string) => format!("'{}'", string),
+                        value => value.to_string(),
+                    },
+                    Op::GetField(index) | Op::SetField(index) => {
+                        chunk.fields[index as usize].name.to_string()
+                    }
+                    Op::Method(index) | Op::CallMethod(index, _) => {
+                        chunk.methods[index as usize].name.to_string()
+                    }
+                    Op::Closure(index) => self.functions[index as usize].name.clone(),
+                    Op::Listener(index) => {
+                        let function = self.listeners[index as usize].function;
+                        self.functions[function as usize].name.clone()
+                    }
+                    _ => String::new(),
+                };
+
+                let op = format!("{:?}", op);
+                let text = format!("  {:04} {:>8}  {:<20} {}", offset, location, op, comment);
+
+                out.push_str(text.trim_end());
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dqk_parser::{Diagnostics, Interner, Parser};
+
+    use super::*;
+
+    #[test]
+    fn disassemble() {
+        let src = "greeting := 'hi'\nln f(Message) {\n\tchannel.send greeting\n}\n";
+
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<dqk_parser::Program>().unwrap();
+        let resolution = dqk_resolve::resolve(&program, &interner, &mut diagnostics);
+        let types = dqk_typeck::check(&program, &resolution, &interner, &mut diagnostics);
+        assert!(diagnostics.is_empty());
+
+        let program = dqk_hir::lower(&program, &resolution, &types, &interner);
+        let module = crate::compile(&program, &resolution, &interner).unwrap();
+
+        assert_eq!(
+            module.disassemble(&source_map),
+            "\
+fn <script> #0 (arity 0, locals 0, cells 0, captures 0)
+  0000     1:13  Constant(0)          'hi'
+  0001        |  SetGlobal(0)
+  0002      2:1  Listener(0)          f
+  0003      1:1  Unit
+  0004        |  Return
+
+fn f #1 (arity 0, locals 1, cells 0, captures 0)
+  0000      3:2  GetLocal(0)
+  0001        |  GetField(0)          channel
+  0002        |  GetGlobal(0)
+  0003        |  CallMethod(0, 1)     send
+  0004        |  Pop
+  0005      2:1  Constant(0)          true
+  0006        |  Return
+"
+        );
+    }
+}
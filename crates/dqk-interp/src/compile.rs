@@ -0,0 +1,592 @@
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
+use dqk_hir::{BinOp, Expr, ExprKind, Listener, ParamKind, Program, Stmt, StmtKind};
+use dqk_parser::{Error, ErrorCode, Interner, Result, Span};
+use dqk_resolve::{DefId, Resolution};
+use dqk_schema::{Field, Method};
+use dqk_typeck::Ty;
+
+use crate::{Capture, Chunk, Function, ListenerInfo, Module, Op, Place, Value};
+
+/// Compiles a lowered program to bytecode for the [`Vm`](crate::Vm).
+///
+/// Like for the [`Interpreter`](crate::Interpreter), the program should have
+/// no errors, the expressions with errors fail to compile.
+pub fn compile(program: &Program, resolution: &Resolution, interner: &Interner) -> Result<Module> {
+    let mut captures = Captures::default();
+    captures.stmts(&program.stmts, &mut Scope::default());
+
+    let mut compiler = Compiler {
+        resolution,
+        interner,
+        fields: captures.fields,
+        captured: captures.captured,
+        functions: Vec::new(),
+        states: Vec::new(),
+        listeners: Vec::new(),
+        globals: 0,
+    };
+
+    compiler.begin("<script>".to_string(), 0);
+    compiler.stmts(&program.stmts)?;
+    compiler.emit(Op::Unit, program.span);
+    compiler.emit(Op::Return, program.span);
+    compiler.end();
+
+    Ok(Module {
+        functions: (compiler.functions.into_iter())
+            .map(|function| Rc::new(function.expect("every function is compiled")))
+            .collect(),
+        listeners: compiler.listeners,
+        globals: compiler.globals,
+    })
+}
+
+/// A variable, or an expanded listener argument, whose fields are variables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Def(DefId),
+    Param(Span),
+}
+
+/// The variables used and defined in a function.
+#[derive(Default)]
+struct Scope {
+    uses: HashSet<Key>,
+    defs: HashSet<Key>,
+}
+
+/// Finds the variables that closures and listeners capture from the functions
+/// around them, which are kept in cells.
+#[derive(Default)]
+struct Captures {
+    /// The argument and field every field variable of an expanded argument is.
+    fields: HashMap<DefId, (Key, &'static Field)>,
+    captured: HashSet<Key>,
+}
+
+impl Captures {
+    fn key(&self, def: DefId) -> Key {
+        self.fields.get(&def).map_or(Key::Def(def), |(key, _)| *key)
+    }
+
+    /// Runs `f` for a function in `outer`, which captures the variables it
+    /// uses but doesn't define.
+    fn function(&mut self, outer: &mut Scope, f: impl FnOnce(&mut Self, &mut Scope)) {
+        let mut inner = Scope::default();
+        f(self, &mut inner);
+
+        for key in inner.uses.difference(&inner.defs) {
+            self.captured.insert(*key);
+            outer.uses.insert(*key);
+        }
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt], scope: &mut Scope) {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Listener(listener) => {
+                    self.function(scope, |this, inner| this.listener(listener, inner))
+                }
+                StmtKind::Let { def, expr } => {
+                    self.expr(expr, scope);
+                    scope.defs.insert(Key::Def(*def));
+                }
+                StmtKind::Assign { def, expr } => {
+                    self.expr(expr, scope);
+                    scope.uses.insert(self.key(*def));
+                }
+                StmtKind::Expr(expr) => self.expr(expr, scope),
+            }
+        }
+    }
+
+    fn listener(&mut self, listener: &Listener, scope: &mut Scope) {
+        for param in &listener.params {
+            match &param.kind {
+                ParamKind::Expanded(fields) => {
+                    for (field, def) in fields {
+                        self.fields.insert(*def, (Key::Param(param.span), *field));
+                    }
+
+                    scope.defs.insert(Key::Param(param.span));
+                }
+                ParamKind::Named(def) => {
+                    scope.defs.insert(Key::Def(*def));
+                }
+            }
+        }
+
+        if let Some(guard) = &listener.guard {
+            self.expr(guard, scope);
+        }
+
+        self.stmts(&listener.body, scope);
+    }
+
+    fn expr(&mut self, expr: &Expr, scope: &mut Scope) {
+        match &expr.kind {
+            ExprKind::Integer(_) | ExprKind::Float(_) | ExprKind::String(_) | ExprKind::Error => {}
+            ExprKind::List(items) => {
+                for item in items {
+                    self.expr(item, scope);
+                }
+            }
+            ExprKind::Closure { params, body } => self.function(scope, |this, inner| {
+                inner
+                    .defs
+                    .extend(params.iter().map(|param| Key::Def(*param)));
+                this.expr(body, inner);
+            }),
+            ExprKind::Variable(def) => {
+                scope.uses.insert(self.key(*def));
+            }
+            ExprKind::Field { expr, .. }
+            | ExprKind::Method { receiver: expr, .. }
+            | ExprKind::ToString(expr) => self.expr(expr, scope),
+            ExprKind::MethodCall { receiver, args, .. } => {
+                self.expr(receiver, scope);
+
+                for arg in args {
+                    self.expr(arg, scope);
+                }
+            }
+            ExprKind::Call { callee, args } => {
+                self.expr(callee, scope);
+
+                for arg in args {
+                    self.expr(arg, scope);
+                }
+            }
+            ExprKind::BinOp { lhs, rhs, .. } => {
+                self.expr(lhs, scope);
+                self.expr(rhs, scope);
+            }
+        }
+    }
+}
+
+/// A function that is being compiled.
+struct State {
+    index: usize,
+    function: Function,
+    places: HashMap<Key, Place>,
+    /// The variables of the captured cells, by index.
+    captures: Vec<Key>,
+}
+
+struct Compiler<'a> {
+    resolution: &'a Resolution,
+    interner: &'a Interner,
+    fields: HashMap<DefId, (Key, &'static Field)>,
+    captured: HashSet<Key>,
+    /// The compiled functions, `None` while they are compiled.
+    functions: Vec<Option<Function>>,
+    /// The function that is compiled and the ones it is in, innermost last.
+    states: Vec<State>,
+    listeners: Vec<ListenerInfo>,
+    globals: u32,
+}
+
+impl Compiler<'_> {
+    fn state(&mut self) -> &mut State {
+        self.states.last_mut().unwrap()
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().function.chunk
+    }
+
+    fn emit(&mut self, op: Op, span: Span) -> usize {
+        self.chunk().push(op, span)
+    }
+
+    /// Starts compiling a function, returning its index.
+    fn begin(&mut self, name: String, arity: u16) -> u32 {
+        let index = self.functions.len();
+        self.functions.push(None);
+
+        self.states.push(State {
+            index,
+            function: Function {
+                name,
+                arity,
+                locals: 0,
+                cells: 0,
+                captures: Vec::new(),
+                chunk: Chunk::default(),
+            },
+            places: HashMap::new(),
+            captures: Vec::new(),
+        });
+
+        index as u32
+    }
+
+    fn end(&mut self) {
+        let state = self.states.pop().unwrap();
+        self.functions[state.index] = Some(state.function);
+    }
+
+    /// Makes room for a variable defined in the function that is compiled.
+    fn declare(&mut self, key: Key) -> Place {
+        let captured = self.captured.contains(&key);
+        let top_level = self.states.len() == 1;
+
+        let place = if top_level {
+            self.globals += 1;
+            Place::Global(self.globals - 1)
+        } else if captured {
+            self.state().function.cells += 1;
+            Place::Cell(self.state().function.cells - 1)
+        } else {
+            self.state().function.locals += 1;
+            Place::Local(self.state().function.locals - 1)
+        };
+
+        self.state().places.insert(key, place);
+
+        place
+    }
+
+    /// Finds a variable in the function at `depth` or around it, capturing it
+    /// from the functions around it. `span` is where it is used.
+    fn resolve(&mut self, key: Key, depth: usize, span: Span) -> Result<Place> {
+        let state = &self.states[depth];
+
+        if let Some(place) = state.places.get(&key) {
+            return Ok(*place);
+        }
+
+        if let Some(index) = state.captures.iter().position(|capture| *capture == key) {
+            return Ok(Place::Capture(index as u16));
+        }
+
+        if depth == 0 {
+            return Err(self.undefined(key, span));
+        }
+
+        let capture = match self.resolve(key, depth - 1, span)? {
+            Place::Global(global) => return Ok(Place::Global(global)),
+            Place::Cell(cell) => Capture::Cell(cell),
+            Place::Capture(capture) => Capture::Capture(capture),
+            Place::Local(_) => unreachable!("captured variables are kept in cells"),
+        };
+
+        let state = &mut self.states[depth];
+        state.captures.push(key);
+        state.function.captures.push(capture);
+
+        Ok(Place::Capture(state.captures.len() as u16 - 1))
+    }
+
+    /// A variable used before it is defined, like a listener, which only a
+    /// program with errors does.
+    fn undefined(&self, key: Key, span: Span) -> Error {
+        let (name, def) = match key {
+            Key::Def(def) => {
+                let def = self.resolution.definition(def);
+                (self.interner.resolve(def.name), def.span)
+            }
+            Key::Param(param) => ("argument", param),
+        };
+
+        Error::new(
+            ErrorCode::UndefinedName,
+            format!("'{}' is used before it is defined", name),
+        )
+        .with_hint("this has no value yet", span)
+        .with_hint("it is defined here", def)
+    }
+
+    fn get(&mut self, key: Key, span: Span) -> Result<()> {
+        let op = match self.resolve(key, self.states.len() - 1, span)? {
+            Place::Global(global) => Op::GetGlobal(global),
+            Place::Local(local) => Op::GetLocal(local),
+            Place::Cell(cell) => Op::GetCell(cell),
+            Place::Capture(capture) => Op::GetCapture(capture),
+        };
+
+        self.emit(op, span);
+
+        Ok(())
+    }
+
+    fn set(&mut self, place: Place, span: Span) {
+        let op = match place {
+            Place::Global(global) => Op::SetGlobal(global),
+            Place::Local(local) => Op::SetLocal(local),
+            Place::Cell(cell) => Op::SetCell(cell),
+            Place::Capture(capture) => Op::SetCapture(capture),
+        };
+
+        self.emit(op, span);
+    }
+
+    fn constant(&mut self, value: Value, span: Span) {
+        let constants = &mut self.chunk().constants;
+        constants.push(value);
+
+        let index = constants.len() as u32 - 1;
+        self.emit(Op::Constant(index), span);
+    }
+
+    fn field(&mut self, field: &'static Field) -> u16 {
+        let fields = &mut self.chunk().fields;
+
+        match fields.iter().position(|other| std::ptr::eq(*other, field)) {
+            Some(index) => index as u16,
+            None => {
+                fields.push(field);
+                fields.len() as u16 - 1
+            }
+        }
+    }
+
+    fn method(&mut self, method: &'static Method) -> u16 {
+        let methods = &mut self.chunk().methods;
+
+        match methods
+            .iter()
+            .position(|other| std::ptr::eq(*other, method))
+        {
+            Some(index) => index as u16,
+            None => {
+                methods.push(method);
+                methods.len() as u16 - 1
+            }
+        }
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt]) -> Result<()> {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Listener(listener) => self.listener(listener)?,
+                StmtKind::Let { def, expr } => {
+                    let name = self.interner.resolve(self.resolution.definition(*def).name);
+
+                    match &expr.kind {
+                        ExprKind::Closure { params, body } => {
+                            self.closure(name.to_string(), params, body, expr.span)?
+                        }
+                        _ => self.expr(expr)?,
+                    }
+
+                    let place = self.declare(Key::Def(*def));
+                    self.set(place, stmt.span);
+                }
+                // a field of an expanded argument is set on a copy of the argument
+                StmtKind::Assign { def, expr } => match self.fields.get(def).copied() {
+                    Some((key, field)) => {
+                        self.get(key, stmt.span)?;
+                        self.expr(expr)?;
+
+                        let field = self.field(field);
+                        self.emit(Op::SetField(field), stmt.span);
+
+                        let place = self.resolve(key, self.states.len() - 1, stmt.span)?;
+                        self.set(place, stmt.span);
+                    }
+                    None => {
+                        self.expr(expr)?;
+
+                        let place =
+                            self.resolve(Key::Def(*def), self.states.len() - 1, stmt.span)?;
+                        self.set(place, stmt.span);
+                    }
+                },
+                StmtKind::Expr(expr) => {
+                    self.expr(expr)?;
+                    self.emit(Op::Pop, stmt.span);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles a listener to a function returning whether it ran.
+    fn listener(&mut self, listener: &Listener) -> Result<()> {
+        let name = self.interner.resolve(listener.name).to_string();
+        let function = self.begin(name, 0);
+
+        let params = (listener.params.iter())
+            .map(|param| {
+                let entity = match param.ty {
+                    Ty::Entity(entity) => Some(entity),
+                    _ => None,
+                };
+
+                let key = match param.kind {
+                    ParamKind::Expanded(_) => Key::Param(param.span),
+                    ParamKind::Named(def) => Key::Def(def),
+                };
+
+                (entity, self.declare(key))
+            })
+            .collect();
+
+        if let Some(guard) = &listener.guard {
+            self.expr(guard)?;
+            self.emit(Op::Guard, guard.span);
+        }
+
+        self.stmts(&listener.body)?;
+        self.constant(Value::Bool(true), listener.span);
+        self.emit(Op::Return, listener.span);
+        self.end();
+
+        self.listeners.push(ListenerInfo {
+            events: listener.events.clone(),
+            params,
+            function,
+        });
+
+        let index = self.listeners.len() as u32 - 1;
+        self.emit(Op::Listener(index), listener.span);
+
+        Ok(())
+    }
+
+    fn closure(&mut self, name: String, params: &[DefId], body: &Expr, span: Span) -> Result<()> {
+        let function = self.begin(name, params.len() as u16);
+        self.state().function.locals = params.len() as u16;
+
+        // arguments are passed in locals, captured ones are moved to cells
+        for (local, param) in params.iter().enumerate() {
+            let key = Key::Def(*param);
+
+            let place = if self.captured.contains(&key) {
+                self.emit(Op::GetLocal(local as u16), span);
+
+                self.state().function.cells += 1;
+                let place = Place::Cell(self.state().function.cells - 1);
+                self.set(place, span);
+
+                place
+            } else {
+                Place::Local(local as u16)
+            };
+
+            self.state().places.insert(key, place);
+        }
+
+        self.expr(body)?;
+        self.emit(Op::Return, body.span);
+        self.end();
+
+        self.emit(Op::Closure(function), span);
+
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Result<()> {
+        let span = expr.span;
+
+        match &expr.kind {
+            ExprKind::Integer(value) => self.constant(Value::Integer(*value), span),
+            ExprKind::Float(value) => self.constant(Value::Float(*value), span),
+            ExprKind::String(value) => {
+                let value = Value::from(self.interner.resolve(*value));
+                self.constant(value, span)
+            }
+            ExprKind::List(items) => {
+                for item in items {
+                    self.expr(item)?;
+                }
+
+                self.emit(Op::List(items.len() as u16), span);
+            }
+            ExprKind::Closure { params, body } => {
+                self.closure("<closure>".to_string(), params, body, span)?
+            }
+            ExprKind::Variable(def) => match self.fields.get(def).copied() {
+                Some((key, field)) => {
+                    self.get(key, span)?;
+
+                    let field = self.field(field);
+                    self.emit(Op::GetField(field), span);
+                }
+                None => self.get(Key::Def(*def), span)?,
+            },
+            ExprKind::Field { expr, field } => {
+                self.expr(expr)?;
+
+                let field = self.field(field);
+                self.emit(Op::GetField(field), span);
+            }
+            ExprKind::Method { receiver, method } => {
+                self.expr(receiver)?;
+
+                let method = self.method(method);
+                self.emit(Op::Method(method), span);
+            }
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                self.expr(receiver)?;
+
+                for arg in args {
+                    self.expr(arg)?;
+                }
+
+                let method = self.method(method);
+                self.emit(Op::CallMethod(method, args.len() as u8), span);
+            }
+            ExprKind::Call { callee, args } => {
+                self.expr(callee)?;
+
+                for arg in args {
+                    self.expr(arg)?;
+                }
+
+                self.emit(Op::Call(args.len() as u8), span);
+            }
+            ExprKind::BinOp {
+                op: BinOp::And,
+                lhs,
+                rhs,
+            } => {
+                self.expr(lhs)?;
+                let jump = self.emit(Op::JumpIfFalse(0), span);
+                self.emit(Op::Pop, span);
+                self.expr(rhs)?;
+
+                let end = self.chunk().code.len() as u32;
+                self.chunk().code[jump] = Op::JumpIfFalse(end);
+            }
+            ExprKind::BinOp { op, lhs, rhs } => {
+                self.expr(lhs)?;
+                self.expr(rhs)?;
+
+                let op = match op {
+                    BinOp::Add => Op::Add,
+                    BinOp::Sub => Op::Sub,
+                    BinOp::Mul => Op::Mul,
+                    BinOp::Div => Op::Div,
+                    BinOp::Eq => Op::Eq,
+                    BinOp::And => unreachable!(),
+                };
+
+                self.emit(op, span);
+            }
+            ExprKind::ToString(expr) => {
+                self.expr(expr)?;
+                self.emit(Op::ToString, span);
+            }
+            ExprKind::Error => {
+                return Err(
+                    Error::new(ErrorCode::UncheckedProgram, "expression with errors")
+                        .with_hint("this didn't resolve or type check", span),
+                )
+            }
+        }
+
+        Ok(())
+    }
+}
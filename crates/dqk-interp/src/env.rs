@@ -0,0 +1,49 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use dqk_resolve::DefId;
+
+use crate::Value;
+
+/// The variables of a scope at runtime.
+///
+/// Closures keep the frame they are created in alive, so they see later
+/// assignments to the variables they use.
+#[derive(Debug, Default)]
+pub(crate) struct Frame {
+    vars: RefCell<HashMap<DefId, Value>>,
+    parent: Option<Rc<Frame>>,
+}
+
+impl Frame {
+    pub fn child(parent: &Rc<Self>) -> Rc<Self> {
+        Rc::new(Self {
+            vars: RefCell::default(),
+            parent: Some(parent.clone()),
+        })
+    }
+
+    /// The value of `def` in this frame or its parents.
+    pub fn get(&self, def: DefId) -> Option<Value> {
+        match self.vars.borrow().get(&def) {
+            Some(value) => Some(value.clone()),
+            None => self.parent.as_ref()?.get(def),
+        }
+    }
+
+    pub fn define(&self, def: DefId, value: Value) {
+        self.vars.borrow_mut().insert(def, value);
+    }
+
+    /// Assigns to `def` in the frame that defines it.
+    pub fn assign(&self, def: DefId, value: Value) {
+        let mut vars = self.vars.borrow_mut();
+
+        match (vars.get_mut(&def), &self.parent) {
+            (Some(var), _) => *var = value,
+            (None, Some(parent)) => parent.assign(def, value),
+            (None, None) => {
+                vars.insert(def, value);
+            }
+        }
+    }
+}
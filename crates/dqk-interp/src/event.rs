@@ -0,0 +1,147 @@
+use std::{collections::HashMap, fmt, rc::Rc};
+
+use dqk_schema::{Entity, Event};
+use serde_json::Value as Json;
+
+use crate::EntityValue;
+
+/// An event and the entities it comes with, which listeners are run for.
+#[derive(Clone, Debug)]
+pub struct EventData {
+    pub event: Event,
+    pub entities: HashMap<Entity, Rc<EntityValue>>,
+}
+
+impl EventData {
+    /// Reads an event like
+    ///
+    /// ```json
+    /// {
+    ///     "event": "message_sent",
+    ///     "message": { "id": 1, "contents": "ping", "channel": { "id": 2 } }
+    /// }
+    /// ```
+    ///
+    /// Every entity of the payload of the event is under its lowercase name.
+    /// Entities need an `id`, their other fields are optional and unknown
+    /// ones are ignored. Ids may be strings, like the snowflakes of Discord.
+    pub fn from_json(json: &Json) -> Result<Self, EventError> {
+        let name = match json.get("event") {
+            Some(Json::String(name)) => name,
+            Some(_) => return Err(EventError::new("event", "expected the name of an event")),
+            None => return Err(EventError::new("event", "missing")),
+        };
+
+        let event = Event::from_name(name)
+            .ok_or_else(|| EventError::new("event", format!("unknown event '{}'", name)))?;
+
+        let mut entities = HashMap::new();
+
+        for &entity in event.payload() {
+            let path = entity.name().to_lowercase();
+            let json = json.get(&path).ok_or_else(|| {
+                EventError::new(&path, format!("missing, '{}' comes with it", event))
+            })?;
+
+            entities.insert(entity, EntityValue::from_json(entity, json, &path)?);
+        }
+
+        Ok(Self { event, entities })
+    }
+
+    /// Whether a listener with the default `events`, taking arguments of the
+    /// types `params`, runs for this event.
+    ///
+    /// It does if `events` contains the event, or is empty and the event comes
+    /// with every argument. Arguments that aren't entities never match.
+    pub fn runs(&self, events: &[Event], params: impl IntoIterator<Item = Option<Entity>>) -> bool {
+        let mut params = params.into_iter();
+
+        if events.is_empty() {
+            params.all(|entity| entity.is_some_and(|entity| self.entities.contains_key(&entity)))
+        } else {
+            events.contains(&self.event) && params.all(|entity| entity.is_some())
+        }
+    }
+}
+
+/// An event that can't be read, see [`EventData::from_json`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventError {
+    /// Where in the event the error is, like `message.channel.id`.
+    pub path: String,
+    pub msg: String,
+}
+
+impl EventError {
+    pub(crate) fn new(path: impl Into<String>, msg: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            msg: msg.into(),
+        }
+    }
+}
+
+impl fmt::Display for EventError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}': {}", self.path, self.msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    fn from_json(json: &str) -> Result<EventData, EventError> {
+        EventData::from_json(&serde_json::from_str(json).unwrap())
+    }
+
+    #[test]
+    fn message_sent() {
+        let event = from_json(
+            r#"{
+                "event": "message_sent",
+                "message": {
+                    "id": "1",
+                    "contents": "ping",
+                    "channel": { "id": 2, "name": "general" },
+                    "nonce": "ignored"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let message = &event.entities[&Entity::Message];
+
+        assert_eq!(event.event, Event::MessageSent);
+        assert_eq!(message.id, 1);
+        assert_eq!(message.field("contents"), Some(Value::from("ping")));
+        assert!(message.field("author").is_none());
+
+        match message.field("channel") {
+            Some(Value::Entity(channel)) => assert_eq!(channel.to_string(), "Channel(2)"),
+            field => panic!("expected channel, found {:?}", field),
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let error = |json| from_json(json).unwrap_err().to_string();
+
+        assert_eq!(
+            error(r#"{ "event": "message_snet" }"#),
+            "'event': unknown event 'message_snet'"
+        );
+        assert_eq!(
+            error(r#"{ "event": "member_joined", "user": { "id": 1 } }"#),
+            "'guild': missing, 'member_joined' comes with it"
+        );
+        assert_eq!(
+            error(
+                r#"{ "event": "message_sent", "message": { "id": 1, "channel": { "id": true } } }"#
+            ),
+            "'message.channel.id': expected an integer"
+        );
+    }
+}
@@ -0,0 +1,526 @@
+use std::{fmt, rc::Rc};
+
+use dqk_hir::{BinOp, Expr, ExprKind, Listener, ParamKind, Program, Stmt, StmtKind};
+use dqk_parser::{Error, ErrorCode, Interner, Result, Span};
+use dqk_resolve::{DefId, DefKind, Resolution};
+use dqk_schema::{Entity, Method};
+use dqk_typeck::Ty;
+
+use crate::{Closure, EntityValue, EventData, Frame, Value};
+
+/// A call of a method of a Discord entity, like the `channel.send 'pong'` of a
+/// listener.
+#[derive(Clone, Debug)]
+pub struct Action {
+    pub entity: Entity,
+    pub id: i64,
+    pub method: &'static Method,
+    pub args: Vec<Value>,
+    pub span: Span,
+}
+
+impl fmt::Display for Action {
+    /// Formats like the call, `Channel(2).send 'pong'`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({}).{}", self.entity, self.id, self.method.name)?;
+
+        for (index, arg) in self.args.iter().enumerate() {
+            let separator = if index == 0 { " " } else { ", " };
+
+            match arg {
+                Value::String(arg) => write!(f, "{}'{}'", separator, arg)?,
+                arg => write!(f, "{}{}", separator, arg)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs a lowered program, see the [crate] documentation.
+///
+/// The program should have no errors, as reported by resolving and type
+/// checking it, the expressions with errors fail to run. Errors that only
+/// happen at runtime, like a division by zero, are returned.
+pub struct Interpreter<'a> {
+    resolution: &'a Resolution,
+    interner: &'a Interner,
+    globals: Rc<Frame>,
+    /// The declared listeners and the frames they were declared in.
+    listeners: Vec<(&'a Listener, Rc<Frame>)>,
+    actions: Vec<Action>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(resolution: &'a Resolution, interner: &'a Interner) -> Self {
+        Self {
+            resolution,
+            interner,
+            globals: Rc::default(),
+            listeners: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Runs the top level of `program`, declaring its variables and listeners.
+    pub fn run(&mut self, program: &'a Program) -> Result<()> {
+        let globals = self.globals.clone();
+        self.stmts(&program.stmts, &globals)
+    }
+
+    /// Runs every listener for `event`, returning how many of them ran.
+    ///
+    /// A listener runs if [`EventData::runs`] it and its guard is true.
+    pub fn dispatch(&mut self, event: &EventData) -> Result<usize> {
+        let mut ran = 0;
+
+        // listeners declared while running others are only run for later events
+        for index in 0..self.listeners.len() {
+            let (listener, frame) = self.listeners[index].clone();
+
+            if self.listener(listener, &frame, event)? {
+                ran += 1;
+            }
+        }
+
+        Ok(ran)
+    }
+
+    /// The method calls of everything that ran so far, in order.
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Runs `listener` if it matches `event`, returning whether it ran.
+    fn listener(
+        &mut self,
+        listener: &'a Listener,
+        parent: &Rc<Frame>,
+        event: &EventData,
+    ) -> Result<bool> {
+        let entities = listener.params.iter().map(|param| match param.ty {
+            Ty::Entity(entity) => Some(entity),
+            _ => None,
+        });
+
+        let matches = event.runs(&listener.events, entities);
+
+        if !matches {
+            return Ok(false);
+        }
+
+        let frame = Frame::child(parent);
+
+        for param in &listener.params {
+            let entity = match param.ty {
+                Ty::Entity(entity) => entity,
+                _ => return Ok(false),
+            };
+
+            let value = match event.entities.get(&entity) {
+                Some(value) => value,
+                None => return Ok(false),
+            };
+
+            match &param.kind {
+                // fields the event didn't come with are reported when they are used
+                ParamKind::Expanded(fields) => {
+                    for (field, def) in fields {
+                        if let Some(value) = value.field(field.name) {
+                            frame.define(*def, value);
+                        }
+                    }
+                }
+                ParamKind::Named(def) => frame.define(*def, Value::Entity(value.clone())),
+            }
+        }
+
+        if let Some(guard) = &listener.guard {
+            if !matches!(self.expr(guard, &frame)?, Value::Bool(true)) {
+                return Ok(false);
+            }
+        }
+
+        self.stmts(&listener.body, &frame)?;
+
+        Ok(true)
+    }
+
+    fn stmts(&mut self, stmts: &'a [Stmt], frame: &Rc<Frame>) -> Result<()> {
+        for stmt in stmts {
+            match &stmt.kind {
+                StmtKind::Listener(listener) => self.listeners.push((listener, frame.clone())),
+                StmtKind::Let { def, expr } => {
+                    let value = self.expr(expr, frame)?;
+                    frame.define(*def, value);
+                }
+                StmtKind::Assign { def, expr } => {
+                    let value = self.expr(expr, frame)?;
+                    frame.assign(*def, value);
+                }
+                StmtKind::Expr(expr) => {
+                    self.expr(expr, frame)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr, frame: &Rc<Frame>) -> Result<Value> {
+        let value = match &expr.kind {
+            ExprKind::Integer(value) => Value::Integer(*value),
+            ExprKind::Float(value) => Value::Float(*value),
+            ExprKind::String(value) => Value::from(self.interner.resolve(*value)),
+            ExprKind::List(items) => Value::List(
+                items
+                    .iter()
+                    .map(|item| self.expr(item, frame))
+                    .collect::<Result<_>>()?,
+            ),
+            ExprKind::Closure { params, body } => Value::Closure(Rc::new(Closure {
+                params: params.clone(),
+                body: body.clone(),
+                frame: frame.clone(),
+            })),
+            ExprKind::Variable(def) => match frame.get(*def) {
+                Some(value) => value,
+                None => return Err(self.missing(*def, expr.span)),
+            },
+            ExprKind::Field {
+                expr: entity,
+                field,
+            } => {
+                let entity = self.entity(entity, frame)?;
+
+                match entity.field(field.name) {
+                    Some(value) => value,
+                    None => {
+                        return Err(Error::new(
+                            ErrorCode::MissingField,
+                            format!("{} has no field '{}'", entity, field.name),
+                        )
+                        .with_hint("the event didn't come with this field", expr.span))
+                    }
+                }
+            }
+            ExprKind::Method { receiver, method } => Value::Method {
+                receiver: self.entity(receiver, frame)?,
+                method,
+            },
+            ExprKind::MethodCall {
+                receiver,
+                method,
+                args,
+            } => {
+                let receiver = self.entity(receiver, frame)?;
+                let args = self.args(args, frame)?;
+
+                self.call_method(&receiver, method, args, expr.span)
+            }
+            ExprKind::Call { callee, args } => {
+                let span = callee.span;
+                let callee = self.expr(callee, frame)?;
+                let args = self.args(args, frame)?;
+
+                match callee {
+                    Value::Closure(closure) => {
+                        let frame = Frame::child(&closure.frame);
+
+                        for (param, arg) in closure.params.iter().zip(args) {
+                            frame.define(*param, arg);
+                        }
+
+                        self.expr(&closure.body, &frame)?
+                    }
+                    Value::Method { receiver, method } => {
+                        self.call_method(&receiver, method, args, expr.span)
+                    }
+                    value => {
+                        return Err(Error::new(
+                            ErrorCode::InvalidCall,
+                            format!("cannot call {}", value),
+                        )
+                        .with_hint("this is not a function", span))
+                    }
+                }
+            }
+            ExprKind::BinOp {
+                op: BinOp::And,
+                lhs,
+                rhs,
+            } => match self.expr(lhs, frame)? {
+                Value::Bool(true) => self.expr(rhs, frame)?,
+                lhs => lhs,
+            },
+            ExprKind::BinOp { op, lhs, rhs } => {
+                let lhs = self.expr(lhs, frame)?;
+                let rhs = self.expr(rhs, frame)?;
+
+                bin_op(*op, lhs, rhs, expr.span)?
+            }
+            ExprKind::ToString(expr) => Value::from(self.expr(expr, frame)?.to_string()),
+            ExprKind::Error => {
+                return Err(
+                    Error::new(ErrorCode::UncheckedProgram, "expression with errors")
+                        .with_hint("this didn't resolve or type check", expr.span),
+                )
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn args(&mut self, args: &[Expr], frame: &Rc<Frame>) -> Result<Vec<Value>> {
+        args.iter().map(|arg| self.expr(arg, frame)).collect()
+    }
+
+    /// Evaluates an expression the type checker made sure is an entity.
+    fn entity(&mut self, expr: &Expr, frame: &Rc<Frame>) -> Result<Rc<EntityValue>> {
+        match self.expr(expr, frame)? {
+            Value::Entity(entity) => Ok(entity),
+            value => Err(not_an_entity(&value, expr.span)),
+        }
+    }
+
+    fn call_method(
+        &mut self,
+        receiver: &EntityValue,
+        method: &'static Method,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Value {
+        self.actions.push(Action {
+            entity: receiver.entity,
+            id: receiver.id,
+            method,
+            args,
+            span,
+        });
+
+        Value::Unit
+    }
+
+    /// A variable without a value, which can only be a field of an expanded
+    /// listener argument that the event didn't come with.
+    fn missing(&self, def: DefId, span: Span) -> Error {
+        let def = self.resolution.definition(def);
+
+        match def.kind {
+            DefKind::Field { entity, name } => Error::new(
+                ErrorCode::MissingField,
+                format!("the {} of this event has no field '{}'", entity, name),
+            )
+            .with_hint("the event didn't come with this field", span)
+            .with_hint(format!("'{}' is a field of {}", name, entity), def.span),
+            _ => Error::new(
+                ErrorCode::UndefinedName,
+                format!(
+                    "'{}' is used before it is defined",
+                    self.interner.resolve(def.name)
+                ),
+            )
+            .with_hint("this has no value yet", span)
+            .with_hint("it is defined here", def.span),
+        }
+    }
+}
+
+/// A receiver of a field or method that type checking should have rejected.
+pub(crate) fn not_an_entity(value: &Value, span: Span) -> Error {
+    Error::new(
+        ErrorCode::MismatchedTypes,
+        format!("expected a Discord entity, found {}", value),
+    )
+    .with_hint("this has no fields or methods", span)
+}
+
+/// Applies an operator other than [`BinOp::And`], which is short-circuiting.
+pub(crate) fn bin_op(op: BinOp, lhs: Value, rhs: Value, span: Span) -> Result<Value> {
+    use Value::{Float, Integer};
+
+    let overflow = || {
+        Error::new(ErrorCode::IntegerOverflow, "integer overflow")
+            .with_hint("the result of this is too large", span)
+    };
+
+    let value = match (op, lhs, rhs) {
+        (BinOp::Eq, lhs, rhs) => Value::Bool(lhs == rhs),
+        (BinOp::Add, Value::String(lhs), Value::String(rhs)) => {
+            Value::from(String::from(&*lhs) + &rhs)
+        }
+        (BinOp::Div, Integer(_), Integer(0)) => {
+            return Err(Error::new(ErrorCode::DivisionByZero, "division by zero")
+                .with_hint("this divides by zero", span))
+        }
+        (op @ (BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div), Integer(lhs), Integer(rhs)) => {
+            let value = match op {
+                BinOp::Add => lhs.checked_add(rhs),
+                BinOp::Sub => lhs.checked_sub(rhs),
+                BinOp::Mul => lhs.checked_mul(rhs),
+                BinOp::Div => lhs.checked_div(rhs),
+                BinOp::Eq | BinOp::And => unreachable!(),
+            };
+
+            Integer(value.ok_or_else(overflow)?)
+        }
+        (
+            op @ (BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div),
+            lhs @ (Integer(_) | Float(_)),
+            rhs @ (Integer(_) | Float(_)),
+        ) => {
+            let float = |value| match value {
+                Integer(value) => value as f64,
+                Float(value) => value,
+                _ => unreachable!(),
+            };
+
+            let (lhs, rhs) = (float(lhs), float(rhs));
+
+            Float(match op {
+                BinOp::Add => lhs + rhs,
+                BinOp::Sub => lhs - rhs,
+                BinOp::Mul => lhs * rhs,
+                BinOp::Div => lhs / rhs,
+                BinOp::Eq | BinOp::And => unreachable!(),
+            })
+        }
+        (op, lhs, rhs) => {
+            return Err(Error::new(
+                ErrorCode::MismatchedTypes,
+                format!("cannot apply '{}' to {} and {}", op.as_str(), lhs, rhs),
+            )
+            .with_hint("the operator doesn't apply to these values", span))
+        }
+    };
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use dqk_parser::{Diagnostics, Parser, SourceMap};
+
+    use super::*;
+
+    const PING: &str = r#"{
+        "event": "message_sent",
+        "message": { "id": 1, "contents": "ping", "channel": { "id": 2 } }
+    }"#;
+
+    /// Runs `src` for the event `json`, returning the actions as strings.
+    fn run(src: &str, json: &str) -> Result<Vec<String>> {
+        run_checked(src, json, true)
+    }
+
+    /// Runs `src`, asserting it has no errors if `checked`.
+    fn run_checked(src: &str, json: &str, checked: bool) -> Result<Vec<String>> {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<dqk_parser::Program>().unwrap();
+        let resolution = dqk_resolve::resolve(&program, &interner, &mut diagnostics);
+        let types = dqk_typeck::check(&program, &resolution, &interner, &mut diagnostics);
+        assert_eq!(diagnostics.is_empty(), checked);
+
+        let program = dqk_hir::lower(&program, &resolution, &types, &interner);
+        let event = EventData::from_json(&serde_json::from_str(json).unwrap()).unwrap();
+
+        let mut interpreter = Interpreter::new(&resolution, &interner);
+        interpreter.run(&program)?;
+        interpreter.dispatch(&event)?;
+
+        let actions = interpreter.actions().iter().map(Action::to_string);
+        Ok(actions.collect())
+    }
+
+    #[test]
+    fn ping() {
+        let src = include_str!("../../../tests/ping.dqk");
+
+        assert_eq!(run(src, PING).unwrap(), ["Channel(2).send 'pong'"]);
+        assert_eq!(
+            run(src, &PING.replace("\"ping\"", "\"pong\"")).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn listeners_only_run_for_their_events() {
+        let src = "ln joined<member_joined>(User) {\n\tx := name\n}\nln any(Message) {\n\tchannel.send 'any'\n}\n";
+
+        assert_eq!(run(src, PING).unwrap(), ["Channel(2).send 'any'"]);
+    }
+
+    #[test]
+    fn variables_and_closures() {
+        let src = "count := 1\nadd := fn(a, b) a + b\nplus := fn(x) count + x\nln f<message_sent>(Message) {\n\tcount = add count, 2\n\tchannel.send 'count is {plus 0}, half is {count / 2.0}'\n}\n";
+
+        assert_eq!(
+            run(src, PING).unwrap(),
+            ["Channel(2).send 'count is 3, half is 1.5'"]
+        );
+    }
+
+    #[test]
+    fn uncalled_methods_are_values() {
+        let src = "ln f<message_sent>(Message) {\n\treply := channel.send\n\treply 'a'\n\treply 'b' + contents\n}\n";
+
+        assert_eq!(
+            run(src, PING).unwrap(),
+            ["Channel(2).send 'a'", "Channel(2).send 'bping'"]
+        );
+    }
+
+    #[test]
+    fn entities_are_mentioned() {
+        let src = "ln f<message_sent>(Message) {\n\tchannel.send 'see {channel}, {channel == channel}'\n}\n";
+
+        assert_eq!(
+            run(src, PING).unwrap(),
+            ["Channel(2).send 'see <#2>, true'"]
+        );
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let code = |src: &str| run(src, PING).unwrap_err().code();
+
+        assert_eq!(
+            code("zero := 0\nx := 1 / zero\n"),
+            ErrorCode::DivisionByZero
+        );
+        assert_eq!(
+            code("x := 9223372036854775807 + 1\n"),
+            ErrorCode::IntegerOverflow
+        );
+        assert_eq!(
+            code("ln f<message_sent>(Message) {\n\tchannel.send channel.name\n}\n"),
+            ErrorCode::MissingField
+        );
+        assert_eq!(
+            code("ln f<message_sent>(Message) {\n\tauthor.send 'hi'\n}\n"),
+            ErrorCode::MissingField
+        );
+    }
+
+    #[test]
+    fn programs_with_errors_fail_to_run() {
+        let code = |src: &str| run_checked(src, PING, false).unwrap_err().code();
+
+        assert_eq!(code("x := y\n"), ErrorCode::UncheckedProgram);
+        assert_eq!(
+            code("add := fn(a, b) a + b\nx := add [1], [2]\n"),
+            ErrorCode::MismatchedTypes
+        );
+        assert_eq!(code("x := 1\ny := x 2\n"), ErrorCode::InvalidCall);
+        assert_eq!(
+            code("ln f(Message) {\n}\nx := f\n"),
+            ErrorCode::UndefinedName
+        );
+    }
+}
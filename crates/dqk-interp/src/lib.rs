@@ -0,0 +1,27 @@
+#![forbid(unsafe_code)]
+
+//! Interpreters for lowered dqk programs.
+//!
+//! The [`Interpreter`] runs the top level of a [`Program`](dqk_hir::Program),
+//! which declares its variables and listeners, and then runs the listeners for
+//! every [`EventData`] it is given. Calling a method of a Discord entity, like
+//! `channel.send 'pong'`, doesn't talk to Discord but records an [`Action`].
+//!
+//! The [`Vm`] does the same for a program [`compile`]d to bytecode, which is
+//! faster than walking the tree.
+
+mod bytecode;
+mod compile;
+mod env;
+mod event;
+mod interp;
+mod value;
+mod vm;
+
+pub use bytecode::*;
+pub use compile::*;
+pub(crate) use env::*;
+pub use event::*;
+pub use interp::*;
+pub use value::*;
+pub use vm::*;
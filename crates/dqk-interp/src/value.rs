@@ -0,0 +1,376 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    rc::Rc,
+};
+
+use dqk_hir::Expr;
+use dqk_resolve::DefId;
+use dqk_schema::{Entity, FieldType, Method};
+use serde_json::Value as Json;
+
+use crate::{EventError, Frame, Function};
+
+/// A value at runtime.
+///
+/// Cloning a value is cheap, strings, lists and maps are shared.
+#[derive(Clone, Debug)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    String(Rc<str>),
+    Bool(bool),
+    /// What calling a method like `channel.send` results in.
+    Unit,
+    List(Rc<[Value]>),
+    /// A JSON object that isn't an entity, see [`Value::from_json`]. Scripts
+    /// can't write maps.
+    Map(Rc<BTreeMap<Rc<str>, Value>>),
+    Closure(Rc<Closure>),
+    /// A closure compiled to bytecode, see [`Vm`](crate::Vm).
+    Function(Rc<FunctionValue>),
+    /// A method that is not called yet, like the `channel.send` of
+    /// `reply := channel.send`.
+    Method {
+        receiver: Rc<EntityValue>,
+        method: &'static Method,
+    },
+    /// A handle to a Discord entity, identified by its snowflake.
+    Entity(Rc<EntityValue>),
+}
+
+impl Value {
+    /// Converts any JSON, with objects becoming maps and `null` becoming
+    /// [`Value::Unit`]. Numbers are integers if they fit in one.
+    pub fn from_json(json: &Json) -> Self {
+        match json {
+            Json::Null => Self::Unit,
+            Json::Bool(bool) => Self::Bool(*bool),
+            Json::Number(number) => match number.as_i64() {
+                Some(integer) => Self::Integer(integer),
+                None => Self::Float(number.as_f64().unwrap_or(f64::NAN)),
+            },
+            Json::String(string) => Self::from(string.as_str()),
+            Json::Array(items) => Self::List(items.iter().map(Self::from_json).collect()),
+            Json::Object(object) => Self::Map(Rc::new(
+                (object.iter())
+                    .map(|(key, value)| (key.as_str().into(), Self::from_json(value)))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Formats strings quoted, like in lists.
+    fn fmt_quoted(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::String(value) => write!(f, "'{}'", value),
+            value => write!(f, "{}", value),
+        }
+    }
+}
+
+impl PartialEq for Value {
+    /// Compares integers and floats by their exact numeric value, entities by
+    /// their snowflake and functions by identity. Everything else is compared
+    /// structurally.
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Integer(lhs), Self::Integer(rhs)) => lhs == rhs,
+            (Self::Integer(integer), Self::Float(float))
+            | (Self::Float(float), Self::Integer(integer)) => integer_eq_float(*integer, *float),
+            (Self::Float(lhs), Self::Float(rhs)) => lhs == rhs,
+            (Self::String(lhs), Self::String(rhs)) => lhs == rhs,
+            (Self::Bool(lhs), Self::Bool(rhs)) => lhs == rhs,
+            (Self::Unit, Self::Unit) => true,
+            (Self::List(lhs), Self::List(rhs)) => lhs == rhs,
+            (Self::Map(lhs), Self::Map(rhs)) => lhs == rhs,
+            (Self::Closure(lhs), Self::Closure(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (Self::Function(lhs), Self::Function(rhs)) => Rc::ptr_eq(lhs, rhs),
+            (
+                Self::Method {
+                    receiver: lhs,
+                    method: lhs_method,
+                },
+                Self::Method {
+                    receiver: rhs,
+                    method: rhs_method,
+                },
+            ) => lhs.same(rhs) && std::ptr::eq(*lhs_method, *rhs_method),
+            (Self::Entity(lhs), Self::Entity(rhs)) => lhs.same(rhs),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    /// Formats the value like it is sent in a message, with users, roles and
+    /// channels as mentions.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{}", value),
+            Self::String(value) => f.write_str(value),
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Unit => f.write_str("()"),
+            Self::List(items) => {
+                f.write_str("[")?;
+
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    item.fmt_quoted(f)?;
+                }
+
+                f.write_str("]")
+            }
+            Self::Map(entries) => {
+                f.write_str("{")?;
+
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    write!(f, "'{}': ", key)?;
+                    value.fmt_quoted(f)?;
+                }
+
+                f.write_str("}")
+            }
+            Self::Closure(_) | Self::Function(_) => f.write_str("<fn>"),
+            Self::Method { receiver, method } => write!(f, "{}.{}", receiver, method.name),
+            Self::Entity(entity) => match entity.entity {
+                Entity::User => write!(f, "<@{}>", entity.id),
+                Entity::Role => write!(f, "<@&{}>", entity.id),
+                Entity::Channel => write!(f, "<#{}>", entity.id),
+                _ => write!(f, "{}", entity),
+            },
+        }
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::String(value.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::String(value.into())
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Self {
+        Self::List(items.into())
+    }
+}
+
+/// A closure and the frame it was created in.
+#[derive(Debug)]
+pub struct Closure {
+    pub params: Vec<DefId>,
+    pub body: Rc<Expr>,
+    pub(crate) frame: Rc<Frame>,
+}
+
+/// A compiled function and the cells it captured.
+#[derive(Debug)]
+pub struct FunctionValue {
+    pub function: Rc<Function>,
+    pub captures: Vec<Rc<RefCell<Value>>>,
+}
+
+/// A Discord entity, with the fields its event came with.
+#[derive(Clone, Debug)]
+pub struct EntityValue {
+    pub entity: Entity,
+    /// The snowflake of the entity.
+    pub id: i64,
+    /// Every field except `id`. Fields the event didn't come with are missing.
+    pub fields: HashMap<&'static str, Value>,
+}
+
+impl EntityValue {
+    /// Reads an entity from the JSON of an event, see
+    /// [`EventData::from_json`](crate::EventData::from_json). `path` is where
+    /// it is in the event, for errors.
+    pub fn from_json(entity: Entity, json: &Json, path: &str) -> Result<Rc<Self>, EventError> {
+        let object = json
+            .as_object()
+            .ok_or_else(|| EventError::new(path, format!("expected {}", entity)))?;
+
+        let id = match object.get("id") {
+            Some(id) => integer_from_json(id, &format!("{}.id", path))?,
+            None => return Err(EventError::new(path, "missing 'id'")),
+        };
+
+        let mut fields = HashMap::new();
+
+        for field in entity.fields().iter().filter(|field| field.name != "id") {
+            let json = match object.get(field.name) {
+                Some(json) => json,
+                None => continue,
+            };
+
+            let path = format!("{}.{}", path, field.name);
+
+            let value = match field.ty {
+                FieldType::Integer => Value::Integer(integer_from_json(json, &path)?),
+                FieldType::String => match json {
+                    Json::String(string) => Value::from(string.as_str()),
+                    _ => return Err(EventError::new(&path, "expected a string")),
+                },
+                FieldType::Bool => match json {
+                    Json::Bool(bool) => Value::Bool(*bool),
+                    _ => return Err(EventError::new(&path, "expected a bool")),
+                },
+                FieldType::Entity(entity) => Value::Entity(Self::from_json(entity, json, &path)?),
+            };
+
+            fields.insert(field.name, value);
+        }
+
+        Ok(Rc::new(Self { entity, id, fields }))
+    }
+
+    pub fn field(&self, name: &str) -> Option<Value> {
+        match name {
+            "id" => Some(Value::Integer(self.id)),
+            name => self.fields.get(name).cloned(),
+        }
+    }
+
+    /// Whether both are the same Discord entity, whatever fields they have.
+    pub fn same(&self, other: &Self) -> bool {
+        self.entity == other.entity && self.id == other.id
+    }
+}
+
+impl fmt::Display for EntityValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.entity, self.id)
+    }
+}
+
+/// Whether `float` is exactly `integer`. Converting `integer` to a float would
+/// round snowflakes, which are larger than the integers floats can represent.
+fn integer_eq_float(integer: i64, float: f64) -> bool {
+    // -2^63 and 2^63 are exact floats, `as` saturates outside of them
+    let min = i64::MIN as f64;
+
+    float.fract() == 0.0 && (min..-min).contains(&float) && float as i64 == integer
+}
+
+/// Reads an integer, or a string of one like the snowflakes of Discord.
+fn integer_from_json(json: &Json, path: &str) -> Result<i64, EventError> {
+    let integer = match json {
+        Json::Number(number) => number.as_i64(),
+        Json::String(string) => string.parse().ok(),
+        _ => None,
+    };
+
+    integer.ok_or_else(|| EventError::new(path, "expected an integer"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(entity: Entity, id: i64) -> Value {
+        Value::Entity(Rc::new(EntityValue {
+            entity,
+            id,
+            fields: HashMap::new(),
+        }))
+    }
+
+    #[test]
+    fn equality() {
+        assert_eq!(Value::from(1), Value::from(1.0));
+        assert_eq!(
+            Value::from(vec![Value::from("a"), Value::from(2)]),
+            Value::from(vec![Value::from("a"), Value::from(2.0)])
+        );
+        assert_ne!(Value::from(vec![Value::from(1)]), Value::from(1));
+        assert_ne!(Value::from(f64::NAN), Value::from(f64::NAN));
+        assert_ne!(Value::from(1), Value::from(1.5));
+
+        // 2^53 + 1 rounds to 2^53 as a float, and i64::MAX rounds up to 2^63
+        assert_ne!(
+            Value::from(9007199254740993),
+            Value::from(9007199254740992.0)
+        );
+        assert_eq!(
+            Value::from(9007199254740992),
+            Value::from(9007199254740992.0)
+        );
+        assert_ne!(Value::from(i64::MAX), Value::from(i64::MAX as f64));
+        assert_eq!(Value::from(i64::MIN), Value::from(i64::MIN as f64));
+
+        let channel = Value::Entity(
+            EntityValue::from_json(
+                Entity::Channel,
+                &serde_json::json!({ "id": "2", "name": "general" }),
+                "channel",
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(channel, entity(Entity::Channel, 2));
+        assert_ne!(channel, entity(Entity::User, 2));
+    }
+
+    #[test]
+    fn display() {
+        let list = Value::from(vec![
+            Value::from("a"),
+            Value::from(1.5),
+            Value::from(true),
+            entity(Entity::User, 1),
+        ]);
+
+        assert_eq!(list.to_string(), "['a', 1.5, true, <@1>]");
+        assert_eq!(entity(Entity::Role, 2).to_string(), "<@&2>");
+        assert_eq!(entity(Entity::Channel, 3).to_string(), "<#3>");
+        assert_eq!(entity(Entity::Guild, 4).to_string(), "Guild(4)");
+    }
+
+    #[test]
+    fn from_json() {
+        let json = serde_json::json!({
+            "b": [1, 2.5, "three", null],
+            "a": { "nested": false },
+        });
+
+        let value = Value::from_json(&json);
+
+        assert_eq!(
+            value.to_string(),
+            "{'a': {'nested': false}, 'b': [1, 2.5, 'three', ()]}"
+        );
+        assert_eq!(value, Value::from_json(&json));
+    }
+}
@@ -0,0 +1,564 @@
+use std::{cell::RefCell, mem, rc::Rc};
+
+use dqk_hir::BinOp;
+use dqk_parser::{Error, ErrorCode, Result, Span};
+use dqk_schema::Method;
+
+use crate::{
+    bin_op, not_an_entity, Action, Capture, Chunk, EntityValue, EventData, FunctionValue, Module,
+    Op, Place, Value,
+};
+
+/// Runs a [`Module`] compiled from a program, like the
+/// [`Interpreter`](crate::Interpreter) runs the program.
+pub struct Vm<'a> {
+    module: &'a Module,
+    globals: Vec<Value>,
+    stack: Vec<Value>,
+    /// The declared listeners, by their index in the module, and the cells
+    /// they captured.
+    listeners: Vec<(u32, Rc<FunctionValue>)>,
+    actions: Vec<Action>,
+}
+
+/// A call that is running.
+struct CallFrame {
+    function: Rc<FunctionValue>,
+    cells: Vec<Rc<RefCell<Value>>>,
+    ip: usize,
+    /// Where the locals start on the stack, after the callee.
+    base: usize,
+}
+
+impl CallFrame {
+    fn chunk(&self) -> &Chunk {
+        &self.function.function.chunk
+    }
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(module: &'a Module) -> Self {
+        Self {
+            module,
+            globals: vec![Value::Unit; module.globals as usize],
+            stack: Vec::new(),
+            listeners: Vec::new(),
+            actions: Vec::new(),
+        }
+    }
+
+    /// Runs the top level of the module, declaring its variables and
+    /// listeners.
+    pub fn run(&mut self) -> Result<()> {
+        let script = Rc::new(FunctionValue {
+            function: self.module.functions[0].clone(),
+            captures: Vec::new(),
+        });
+
+        self.call(script, &[])?;
+
+        Ok(())
+    }
+
+    /// Runs every listener for `event`, returning how many of them ran.
+    ///
+    /// A listener runs if [`EventData::runs`] it and its guard is true.
+    pub fn dispatch(&mut self, event: &EventData) -> Result<usize> {
+        let mut ran = 0;
+
+        // listeners declared while running others are only run for later events
+        for index in 0..self.listeners.len() {
+            let (listener, function) = self.listeners[index].clone();
+            let info = &self.module.listeners[listener as usize];
+
+            let entities = info.params.iter().map(|(entity, _)| *entity);
+
+            if !event.runs(&info.events, entities) {
+                continue;
+            }
+
+            let args: Vec<_> = (info.params.iter())
+                .filter_map(|(entity, place)| {
+                    let value = event.entities.get(&(*entity)?)?;
+                    Some((*place, Value::Entity(value.clone())))
+                })
+                .collect();
+
+            if args.len() < info.params.len() {
+                continue;
+            }
+
+            if matches!(self.call(function, &args)?, Value::Bool(true)) {
+                ran += 1;
+            }
+        }
+
+        Ok(ran)
+    }
+
+    /// The method calls of everything that ran so far, in order.
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// Calls a function taking no arguments on the stack, passing `args` in
+    /// the locals and cells they are declared in.
+    fn call(&mut self, function: Rc<FunctionValue>, args: &[(Place, Value)]) -> Result<Value> {
+        let start = self.stack.len();
+
+        // the callee slot
+        self.stack.push(Value::Unit);
+
+        let base = self.stack.len();
+        let frame = self.frame(function, base);
+
+        for (place, value) in args {
+            match place {
+                Place::Local(local) => self.stack[base + *local as usize] = value.clone(),
+                Place::Cell(cell) => *frame.cells[*cell as usize].borrow_mut() = value.clone(),
+                Place::Global(_) | Place::Capture(_) => {
+                    unreachable!("arguments are declared in the listener")
+                }
+            }
+        }
+
+        let result = self.execute(frame);
+
+        if result.is_err() {
+            self.stack.truncate(start);
+        }
+
+        result
+    }
+
+    /// Makes room for the locals of a call whose arguments are on the stack.
+    fn frame(&mut self, function: Rc<FunctionValue>, base: usize) -> CallFrame {
+        let locals = function.function.locals as usize;
+        self.stack.resize(base + locals, Value::Unit);
+
+        let cells = (0..function.function.cells)
+            .map(|_| Rc::new(RefCell::new(Value::Unit)))
+            .collect();
+
+        CallFrame {
+            function,
+            cells,
+            ip: 0,
+            base,
+        }
+    }
+
+    fn execute(&mut self, frame: CallFrame) -> Result<Value> {
+        let mut frames = vec![frame];
+
+        loop {
+            let frame = frames.last_mut().unwrap();
+            let ip = frame.ip;
+            frame.ip += 1;
+
+            let op = frame.chunk().code[ip];
+            let span = || frame.chunk().spans[ip];
+
+            let ret = match op {
+                Op::Constant(index) => {
+                    let value = frame.chunk().constants[index as usize].clone();
+                    self.stack.push(value);
+                    None
+                }
+                Op::Unit => {
+                    self.stack.push(Value::Unit);
+                    None
+                }
+                Op::Pop => {
+                    self.stack.pop();
+                    None
+                }
+                Op::GetGlobal(global) => {
+                    let value = self.globals[global as usize].clone();
+                    self.stack.push(value);
+                    None
+                }
+                Op::SetGlobal(global) => {
+                    self.globals[global as usize] = self.pop();
+                    None
+                }
+                Op::GetLocal(local) => {
+                    let value = self.stack[frame.base + local as usize].clone();
+                    self.stack.push(value);
+                    None
+                }
+                Op::SetLocal(local) => {
+                    self.stack[frame.base + local as usize] = self.pop();
+                    None
+                }
+                Op::GetCell(cell) => {
+                    self.stack.push(frame.cells[cell as usize].borrow().clone());
+                    None
+                }
+                Op::SetCell(cell) => {
+                    *frame.cells[cell as usize].borrow_mut() = self.pop();
+                    None
+                }
+                Op::GetCapture(capture) => {
+                    let value = frame.function.captures[capture as usize].borrow().clone();
+                    self.stack.push(value);
+                    None
+                }
+                Op::SetCapture(capture) => {
+                    *frame.function.captures[capture as usize].borrow_mut() = self.pop();
+                    None
+                }
+                Op::GetField(field) => {
+                    let field = frame.chunk().fields[field as usize];
+                    let entity = self.entity(span())?;
+
+                    match entity.field(field.name) {
+                        Some(value) => self.stack.push(value),
+                        None => {
+                            return Err(Error::new(
+                                ErrorCode::MissingField,
+                                format!("{} has no field '{}'", entity, field.name),
+                            )
+                            .with_hint("the event didn't come with this field", span()))
+                        }
+                    }
+
+                    None
+                }
+                Op::SetField(field) => {
+                    let field = frame.chunk().fields[field as usize];
+                    let value = self.pop();
+                    let entity = self.entity(span())?;
+                    let mut entity = EntityValue::clone(&entity);
+
+                    match (field.name, value) {
+                        ("id", Value::Integer(id)) => entity.id = id,
+                        (name, value) => {
+                            entity.fields.insert(name, value);
+                        }
+                    }
+
+                    self.stack.push(Value::Entity(Rc::new(entity)));
+                    None
+                }
+                Op::Method(method) => {
+                    let method = frame.chunk().methods[method as usize];
+                    let receiver = self.entity(span())?;
+
+                    self.stack.push(Value::Method { receiver, method });
+                    None
+                }
+                Op::CallMethod(method, args) => {
+                    let method = frame.chunk().methods[method as usize];
+                    let args = self.stack.split_off(self.stack.len() - args as usize);
+                    let receiver = self.entity(span())?;
+
+                    self.call_method(&receiver, method, args, span());
+                    None
+                }
+                Op::Call(args) => {
+                    let base = self.stack.len() - args as usize;
+
+                    match mem::replace(&mut self.stack[base - 1], Value::Unit) {
+                        Value::Function(function) => {
+                            let frame = self.frame(function, base);
+                            frames.push(frame);
+                        }
+                        Value::Method { receiver, method } => {
+                            let args = self.stack.split_off(base);
+                            self.stack.pop();
+
+                            self.call_method(&receiver, method, args, span());
+                        }
+                        value => {
+                            return Err(Error::new(
+                                ErrorCode::InvalidCall,
+                                format!("cannot call {}", value),
+                            )
+                            .with_hint("this calls a value that is not a function", span()))
+                        }
+                    }
+
+                    None
+                }
+                Op::List(items) => {
+                    let items = self.stack.split_off(self.stack.len() - items as usize);
+                    self.stack.push(Value::from(items));
+                    None
+                }
+                Op::Closure(function) => {
+                    let function = self.closure(frame, function);
+                    self.stack.push(Value::Function(function));
+                    None
+                }
+                Op::Listener(listener) => {
+                    let function = self.module.listeners[listener as usize].function;
+                    let function = self.closure(frame, function);
+
+                    self.listeners.push((listener, function));
+                    None
+                }
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Eq => {
+                    let op = match op {
+                        Op::Add => BinOp::Add,
+                        Op::Sub => BinOp::Sub,
+                        Op::Mul => BinOp::Mul,
+                        Op::Div => BinOp::Div,
+                        _ => BinOp::Eq,
+                    };
+
+                    let rhs = self.pop();
+                    let lhs = self.pop();
+
+                    self.stack.push(bin_op(op, lhs, rhs, span())?);
+                    None
+                }
+                Op::ToString => {
+                    let value = self.pop().to_string();
+                    self.stack.push(Value::from(value));
+                    None
+                }
+                Op::JumpIfFalse(target) => {
+                    if !matches!(self.stack.last(), Some(Value::Bool(true))) {
+                        frame.ip = target as usize;
+                    }
+
+                    None
+                }
+                Op::Guard => match self.pop() {
+                    Value::Bool(true) => None,
+                    _ => Some(Value::Bool(false)),
+                },
+                Op::Return => Some(self.pop()),
+            };
+
+            if let Some(value) = ret {
+                let frame = frames.pop().unwrap();
+
+                // the locals and the callee
+                self.stack.truncate(frame.base - 1);
+
+                if frames.is_empty() {
+                    return Ok(value);
+                }
+
+                self.stack.push(value);
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("the stack is not empty")
+    }
+
+    /// Pops a value the type checker made sure is an entity, `span` is where
+    /// it is used.
+    fn entity(&mut self, span: Span) -> Result<Rc<EntityValue>> {
+        match self.pop() {
+            Value::Entity(entity) => Ok(entity),
+            value => Err(not_an_entity(&value, span)),
+        }
+    }
+
+    /// Creates a closure of `function`, capturing cells of `frame`.
+    fn closure(&self, frame: &CallFrame, function: u32) -> Rc<FunctionValue> {
+        let function = self.module.functions[function as usize].clone();
+
+        let captures = (function.captures.iter())
+            .map(|capture| match capture {
+                Capture::Cell(cell) => frame.cells[*cell as usize].clone(),
+                Capture::Capture(capture) => frame.function.captures[*capture as usize].clone(),
+            })
+            .collect();
+
+        Rc::new(FunctionValue { function, captures })
+    }
+
+    fn call_method(
+        &mut self,
+        receiver: &EntityValue,
+        method: &'static Method,
+        args: Vec<Value>,
+        span: Span,
+    ) {
+        self.actions.push(Action {
+            entity: receiver.entity,
+            id: receiver.id,
+            method,
+            args,
+            span,
+        });
+
+        self.stack.push(Value::Unit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dqk_parser::{Diagnostics, Interner, Parser, SourceMap};
+
+    use super::*;
+    use crate::{compile, Interpreter};
+
+    const PING: &str = r#"{
+        "event": "message_sent",
+        "message": { "id": 1, "contents": "ping", "channel": { "id": 2 } }
+    }"#;
+
+    /// Runs `src` for the event `json` twice, in the interpreter and in the
+    /// VM, checking that they do the same and returning the actions.
+    fn run(src: &str, json: &str) -> Result<Vec<String>> {
+        run_checked(src, json, true)
+    }
+
+    /// Runs `src` like [`run`], asserting it has no errors if `checked`.
+    fn run_checked(src: &str, json: &str, checked: bool) -> Result<Vec<String>> {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<dqk_parser::Program>().unwrap();
+        let resolution = dqk_resolve::resolve(&program, &interner, &mut diagnostics);
+        let types = dqk_typeck::check(&program, &resolution, &interner, &mut diagnostics);
+        assert_eq!(diagnostics.is_empty(), checked);
+
+        let program = dqk_hir::lower(&program, &resolution, &types, &interner);
+        let event = EventData::from_json(&serde_json::from_str(json).unwrap()).unwrap();
+
+        let mut interpreter = Interpreter::new(&resolution, &interner);
+        let expected = interpreter.run(&program).and_then(|()| {
+            let ran = interpreter.dispatch(&event)?;
+            Ok((ran, interpreter.dispatch(&event)?))
+        });
+
+        let module = match compile(&program, &resolution, &interner) {
+            Ok(module) => module,
+            Err(error) => {
+                assert_eq!(Some(error.code()), expected.err().map(|error| error.code()));
+                return Err(error);
+            }
+        };
+
+        let mut vm = Vm::new(&module);
+        let result = vm.run().and_then(|()| {
+            let ran = vm.dispatch(&event)?;
+            Ok((ran, vm.dispatch(&event)?))
+        });
+
+        match (&result, &expected) {
+            (Ok(ran), Ok(expected)) => assert_eq!(ran, expected),
+            (Err(error), Err(expected)) => assert_eq!(error.code(), expected.code()),
+            _ => panic!("expected {:?}, found {:?}", expected, result),
+        }
+
+        let actions: Vec<_> = vm.actions().iter().map(Action::to_string).collect();
+        let expected: Vec<_> = interpreter
+            .actions()
+            .iter()
+            .map(Action::to_string)
+            .collect();
+        assert_eq!(actions, expected);
+
+        result.map(|_| actions)
+    }
+
+    #[test]
+    fn ping() {
+        let src = include_str!("../../../tests/ping.dqk");
+
+        assert_eq!(
+            run(src, PING).unwrap(),
+            ["Channel(2).send 'pong'", "Channel(2).send 'pong'"]
+        );
+        assert!(run(src, &PING.replace("\"ping\"", "\"pong\""))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn closures_capture_variables() {
+        let src = "count := 1\nadd := fn(a, b) a + b\nln f<message_sent>(Message) {\n\tn := 1\n\tplus := fn(x) fn(y) n + x + y + count\n\tn = 10\n\tcount = add count, 2\n\tchannel.send '{(plus 100) 1000}'\n}\n";
+
+        assert_eq!(
+            run(src, PING).unwrap(),
+            ["Channel(2).send '1113'", "Channel(2).send '1115'"]
+        );
+    }
+
+    #[test]
+    fn nested_listeners_run_for_later_events() {
+        let src = "ln f<message_sent>(Message) {\n\tn := 1\n\tln g<message_sent>(Message) {\n\t\tn = n + 1\n\t\tchannel.send 'g {n}'\n\t}\n\tchannel.send 'f {n}'\n}\n";
+
+        assert_eq!(
+            run(src, PING).unwrap(),
+            [
+                "Channel(2).send 'f 1'",
+                "Channel(2).send 'f 1'",
+                "Channel(2).send 'g 2'"
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_are_equal_by_their_items() {
+        let src = "ln f<message_sent>(Message) {\n\tchannel.send '{[1] == [1]} {[1, 2] == [1]} {[[contents]] == [['ping']]}'\n}\n";
+
+        assert_eq!(
+            run(src, PING).unwrap(),
+            [
+                "Channel(2).send 'true false true'",
+                "Channel(2).send 'true false true'"
+            ]
+        );
+    }
+
+    #[test]
+    fn fields_and_methods() {
+        let src = "ln f<message_sent>(Message)\nwhere\n\tchannel.id == 2,\n\tcontents == 'ping',\n{\n\tcontents = contents + '!'\n\treply := channel.send\n\treply '{[contents]} {1.5} {id}'\n}\n";
+
+        assert_eq!(
+            run(src, PING).unwrap(),
+            [
+                "Channel(2).send '['ping!'] 1.5 1'",
+                "Channel(2).send '['ping!'] 1.5 1'"
+            ]
+        );
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let code = |src: &str| run(src, PING).unwrap_err().code();
+
+        assert_eq!(
+            code("zero := 0\nx := 1 / zero\n"),
+            ErrorCode::DivisionByZero
+        );
+        assert_eq!(
+            code("big := fn(x) x * 9223372036854775807\nx := big 2\n"),
+            ErrorCode::IntegerOverflow
+        );
+        assert_eq!(
+            code("ln f<message_sent>(Message) {\n\tauthor.send 'hi'\n}\n"),
+            ErrorCode::MissingField
+        );
+    }
+
+    #[test]
+    fn programs_with_errors_fail_to_run() {
+        let code = |src: &str| run_checked(src, PING, false).unwrap_err().code();
+
+        assert_eq!(code("x := y\n"), ErrorCode::UncheckedProgram);
+        assert_eq!(
+            code("add := fn(a, b) a + b\nx := add [1], [2]\n"),
+            ErrorCode::MismatchedTypes
+        );
+        assert_eq!(code("x := 1\ny := x 2\n"), ErrorCode::InvalidCall);
+        assert_eq!(
+            code("ln f(Message) {\n}\nx := f\n"),
+            ErrorCode::UndefinedName
+        );
+    }
+}
@@ -1,6 +1,25 @@
+mod parse;
 mod spanned;
+mod visit;
 
-#[proc_macro_derive(Spanned)]
+#[proc_macro_derive(Parse, attributes(parse))]
+pub fn derive_parse(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    parse::derive_parse(input)
+}
+
+/// Implements `Spanned` by joining the spans of the fields.
+///
+/// `Option` and `Vec` fields are joined when they have a span, but can't be
+/// the only fields. `#[span(skip)]` leaves a field out, and if any field is
+/// marked `#[span]` only the marked fields are joined. `#[span(with = path)]`
+/// on the type or a variant calls `path(&self)` instead, which is the only way
+/// to give a unit variant a span: it has no fields a `#[span]` could mark.
+#[proc_macro_derive(Spanned, attributes(span))]
 pub fn derive_spanned(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     spanned::derive_spanned(input)
 }
+
+#[proc_macro_derive(Visit, attributes(visit))]
+pub fn derive_visit(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    visit::derive_visit(input)
+}
@@ -0,0 +1,177 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, parse_quote,
+    punctuated::Punctuated,
+    Attribute, Data, DeriveInput, Expr, Field, Fields, Generics, Token,
+};
+
+pub fn derive_parse(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+
+    let name = input.ident;
+
+    let body = match parse(&input.data) {
+        Ok(body) => body,
+        Err(error) => return error.to_compile_error().into(),
+    };
+
+    add_generic(&mut input.generics);
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics crate::Parse for #name #ty_generics #where_clause {
+            fn parse(parser: &mut crate::Parser) -> crate::Result<Self> {
+                #body
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+fn add_generic(generics: &mut Generics) {
+    for generics in generics.type_params_mut() {
+        generics.bounds.push_value(parse_quote!(crate::Parse));
+    }
+}
+
+/// A single argument of a `#[parse(..)]` attribute.
+enum Arg {
+    /// `skip_eol`, skips line breaks before the field.
+    SkipEol,
+    /// `terminated = Punct::CloseParen`, parses a `Punctuated` up to the token.
+    Terminated(Expr),
+    /// `if_next = Keyword::Where`, parses a `SpannedOption` if the token is next.
+    IfNext(Expr),
+    /// `trailing = Trailing::Forbidden`, the trailing policy of `terminated`.
+    Trailing(Expr),
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: syn::Ident = input.parse()?;
+
+        match name.to_string().as_str() {
+            "skip_eol" => Ok(Self::SkipEol),
+            "terminated" => {
+                input.parse::<Token![=]>()?;
+                Ok(Self::Terminated(input.parse()?))
+            }
+            "if_next" => {
+                input.parse::<Token![=]>()?;
+                Ok(Self::IfNext(input.parse()?))
+            }
+            "trailing" => {
+                input.parse::<Token![=]>()?;
+                Ok(Self::Trailing(input.parse()?))
+            }
+            _ => Err(syn::Error::new(
+                name.span(),
+                "expected `skip_eol`, `terminated`, `trailing` or `if_next`",
+            )),
+        }
+    }
+}
+
+fn args(attrs: &[Attribute]) -> syn::Result<Vec<Arg>> {
+    let mut args = Vec::new();
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("parse")) {
+        args.extend(attr.parse_args_with(Punctuated::<Arg, Token![,]>::parse_terminated)?);
+    }
+
+    Ok(args)
+}
+
+/// Statements parsing `field` into `binding`.
+fn parse_field(field: &Field, binding: &syn::Ident) -> syn::Result<TokenStream> {
+    let mut skip_eol = false;
+    let mut terminated = None;
+    let mut if_next = None;
+    let mut trailing = None;
+
+    for arg in args(&field.attrs)? {
+        match arg {
+            Arg::SkipEol => skip_eol = true,
+            Arg::Terminated(kind) => terminated = Some(kind),
+            Arg::IfNext(kind) => if_next = Some(kind),
+            Arg::Trailing(policy) => trailing = Some(policy),
+        }
+    }
+
+    let parse = match (terminated, if_next, trailing) {
+        (Some(_), Some(kind), _) => {
+            return Err(syn::Error::new_spanned(
+                kind,
+                "`terminated` and `if_next` cannot be used together",
+            ));
+        }
+        (None, _, Some(policy)) => {
+            return Err(syn::Error::new_spanned(
+                policy,
+                "`trailing` can only be used with `terminated`",
+            ));
+        }
+        (Some(kind), None, trailing) => {
+            let trailing = trailing.unwrap_or_else(|| parse_quote!(crate::Trailing::Allowed));
+
+            Some(quote! {
+                crate::Punctuated::parse_terminated(
+                    parser,
+                    crate::TokenKind::from(#kind),
+                    #trailing,
+                )?
+            })
+        }
+        (None, Some(kind), None) => Some(quote! {
+            crate::SpannedOption::parse_if_next(parser, crate::TokenKind::from(#kind))?
+        }),
+        (None, None, None) => None,
+    };
+
+    let skip_eol = skip_eol.then(|| quote!(parser.skip_eol()?;));
+    let parse = parse.unwrap_or_else(|| quote!(parser.parse()?));
+
+    Ok(quote! {
+        #skip_eol
+        let #binding = #parse;
+    })
+}
+
+fn parse(data: &Data) -> syn::Result<TokenStream> {
+    let data = match data {
+        Data::Struct(data) => data,
+        _ => {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "Parse can only be derived for structs",
+            ))
+        }
+    };
+
+    let bindings: Vec<_> = (data.fields.iter().enumerate())
+        .map(|(index, field)| match field.ident {
+            Some(ref ident) => ident.clone(),
+            None => format_ident!("i{}", index),
+        })
+        .collect();
+
+    let fields = (data.fields.iter().zip(&bindings))
+        .map(|(field, binding)| parse_field(field, binding))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let construct = match data.fields {
+        Fields::Named(_) => quote!(Self { #(#bindings),* }),
+        Fields::Unnamed(_) => quote!(Self(#(#bindings),*)),
+        Fields::Unit => quote!(Self),
+    };
+
+    Ok(quote! {
+        #(#fields)*
+
+        Ok(#construct)
+    })
+}
@@ -1,13 +1,19 @@
-use proc_macro2::{Ident, Span, TokenStream};
-use quote::quote;
-use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Generics};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Attribute, Data, DeriveInput, Field, Fields,
+    Generics, Meta, NestedMeta, Path, Token, Type,
+};
 
 pub fn derive_spanned(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let mut input = parse_macro_input!(input as DeriveInput);
 
     let name = input.ident;
 
-    let span = span(input.data);
+    let span = match span(&input.data, &input.attrs) {
+        Ok(span) => span,
+        Err(error) => return error.to_compile_error().into(),
+    };
 
     add_generic(&mut input.generics);
 
@@ -30,89 +36,225 @@ fn add_generic(generics: &mut Generics) {
     }
 }
 
-fn span(data: Data) -> TokenStream {
-    match data {
-        Data::Struct(data) => match data.fields {
-            Fields::Named(named) => {
-                let fields = named.named.iter().map(|field| {
-                    let name = field.ident.as_ref().unwrap();
+/// What a `#[span]` attribute on a field says about it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Role {
+    /// No attribute, the field is part of the span.
+    Part,
+    /// `#[span]`, only the fields marked like this make up the span.
+    Source,
+    /// `#[span(skip)]`, the field is not part of the span.
+    Skip,
+}
+
+fn role(attrs: &[Attribute]) -> syn::Result<Role> {
+    let mut role = Role::Part;
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("span")) {
+        role = match attr.parse_meta()? {
+            Meta::Path(_) => Role::Source,
+            Meta::List(list) => match list.nested.iter().collect::<Vec<_>>()[..] {
+                [NestedMeta::Meta(Meta::Path(path))] if path.is_ident("skip") => Role::Skip,
+                _ => return Err(syn::Error::new_spanned(list, "expected `#[span(skip)]`")),
+            },
+            meta => return Err(syn::Error::new_spanned(meta, "expected `#[span]`")),
+        };
+    }
+
+    Ok(role)
+}
 
-                    quote!(&self.#name)
-                });
+/// The `path` of a `#[span(with = path)]` attribute on a struct or variant, a
+/// function taking `&Self` and returning its span.
+fn with(attrs: &[Attribute]) -> syn::Result<Option<Path>> {
+    let mut with = None;
 
-                impl_span(fields)
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("span")) {
+        let parse = |input: syn::parse::ParseStream| {
+            let name: syn::Ident = input.parse()?;
+
+            if name != "with" {
+                return Err(syn::Error::new(name.span(), "expected `with = ..`"));
             }
-            Fields::Unnamed(unnamed) => {
-                let mut index = 0u32;
 
-                let fields = unnamed.unnamed.iter().map(|_| {
-                    let ident = Ident::new(&index.to_string(), Span::call_site());
-                    index += 1;
+            input.parse::<Token![=]>()?;
+            input.parse::<Path>()
+        };
+
+        with = Some(attr.parse_args_with(parse)?);
+    }
 
-                    quote!(&self.#ident)
-                });
+    Ok(with)
+}
+
+/// Rejects a plain `#[span]` on a unit variant, which has no field it could
+/// mark as the source of its span.
+fn unit_marker(attrs: &[Attribute]) -> syn::Result<()> {
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("span")) {
+        if attr.tokens.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &attr.path,
+                "a unit variant has no fields to take a span from, use `#[span(with = ..)]`",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// How to get the span of a field.
+enum Kind {
+    /// The field implements `Spanned`.
+    Spanned,
+    /// `Option<T>`, which has no span when `None`.
+    Option,
+    /// `Vec<T>`, which has no span when empty.
+    Vec,
+}
+
+fn kind(ty: &Type) -> Kind {
+    let last = match ty {
+        Type::Path(path) => path.path.segments.last(),
+        _ => None,
+    };
+
+    match last {
+        Some(segment) if segment.ident == "Option" => Kind::Option,
+        Some(segment) if segment.ident == "Vec" => Kind::Vec,
+        _ => Kind::Spanned,
+    }
+}
+
+/// Joins the spans of `fields`, each given as an expression that evaluates to a
+/// reference to the field.
+fn join<'a>(
+    fields: impl Iterator<Item = (&'a Field, TokenStream)>,
+    error_span: Span,
+) -> syn::Result<TokenStream> {
+    let mut parts = Vec::new();
+
+    for (field, access) in fields {
+        parts.push((role(&field.attrs)?, kind(&field.ty), access));
+    }
+
+    if parts.iter().any(|(role, _, _)| *role == Role::Source) {
+        parts.retain(|(role, _, _)| *role == Role::Source);
+    } else {
+        parts.retain(|(role, _, _)| *role == Role::Part);
+    }
 
-                impl_span(fields)
+    let first = parts
+        .iter()
+        .position(|(_, kind, _)| matches!(kind, Kind::Spanned))
+        .ok_or_else(|| {
+            syn::Error::new(
+                error_span,
+                "Spanned needs a field that always has a span, add one or use `#[span(with = ..)]`",
+            )
+        })?;
+
+    let (_, _, first) = parts.remove(first);
+
+    let rest = parts.into_iter().map(|(_, kind, access)| match kind {
+        Kind::Spanned => quote! {
+            span |= crate::Spanned::span(#access);
+        },
+        Kind::Option => quote! {
+            if let Some(field) = #access {
+                span |= crate::Spanned::span(field);
             }
-            Fields::Unit => unimplemented!(),
         },
+        Kind::Vec => quote! {
+            if let (Some(first), Some(last)) = ((#access).first(), (#access).last()) {
+                span |= crate::Spanned::span(first) | crate::Spanned::span(last);
+            }
+        },
+    });
+
+    Ok(quote! {
+        #[allow(unused_mut)]
+        let mut span = crate::Spanned::span(#first);
+        #(#rest)*
+        span
+    })
+}
+
+fn span(data: &Data, attrs: &[Attribute]) -> syn::Result<TokenStream> {
+    if let Some(with) = with(attrs)? {
+        return Ok(quote!((#with)(self)));
+    }
+
+    match data {
+        Data::Struct(data) => {
+            let fields = (data.fields.iter().enumerate()).map(|(index, field)| {
+                let access = match field.ident {
+                    Some(ref ident) => quote!(&self.#ident),
+                    None => {
+                        let index = syn::Index::from(index);
+                        quote!(&self.#index)
+                    }
+                };
+
+                (field, access)
+            });
+
+            join(fields, Span::call_site())
+        }
         Data::Enum(data) => {
-            let variants = data.variants.iter().map(|variant| {
-                let name = &variant.ident;
-
-                match variant.fields {
-                    Fields::Named(ref fields) => {
-                        let idents = fields
-                            .named
-                            .iter()
-                            .map(|field| {
-                                let name = &field.ident.as_ref().unwrap();
-                                quote!(#name)
-                            })
-                            .collect::<Vec<_>>();
-
-                        let span = impl_span(idents.iter().cloned());
-
-                        quote! {
-                            Self::#name { #(#idents),* } => #span
+            let variants = data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let name = &variant.ident;
+
+                    let pattern = match variant.fields {
+                        Fields::Named(_) => {
+                            let idents = variant.fields.iter().map(|field| &field.ident);
+                            quote!(Self::#name { #(#idents),* })
                         }
-                    }
-                    Fields::Unnamed(ref fields) => {
-                        let mut index = 0;
+                        Fields::Unnamed(_) => {
+                            let idents = (0..variant.fields.len()).map(|i| format_ident!("i{}", i));
+                            quote!(Self::#name(#(#idents),*))
+                        }
+                        Fields::Unit => quote!(Self::#name),
+                    };
 
-                        let idents = fields
-                            .unnamed
-                            .iter()
-                            .map(|_| {
-                                let ident = Ident::new(&format!("i{}", index), Span::call_site());
-                                index += 1;
+                    if let Fields::Unit = variant.fields {
+                        unit_marker(&variant.attrs)?;
+                    }
 
-                                quote!(#ident)
-                            })
-                            .collect::<Vec<_>>();
+                    let span = match with(&variant.attrs)? {
+                        Some(with) => quote!((#with)(self)),
+                        None => {
+                            let fields = (variant.fields.iter().enumerate()).map(|(i, field)| {
+                                let binding = match field.ident {
+                                    Some(ref ident) => ident.clone(),
+                                    None => format_ident!("i{}", i),
+                                };
 
-                        let span = impl_span(idents.iter().cloned());
+                                (field, quote!(#binding))
+                            });
 
-                        quote! {
-                            Self::#name(#(#idents),*) => #span
+                            join(fields, variant.span())?
                         }
-                    }
-                    Fields::Unit => unimplemented!(),
-                }
-            });
+                    };
 
-            quote! {
+                    Ok(quote! {
+                        #[allow(unused_variables)]
+                        #pattern => { #span }
+                    })
+                })
+                .collect::<syn::Result<Vec<_>>>()?;
+
+            Ok(quote! {
                 match self {
-                    #(#variants),*
+                    #(#variants)*
                 }
-            }
+            })
         }
-        _ => unimplemented!(),
-    }
-}
-
-fn impl_span(fields: impl Iterator<Item = TokenStream>) -> TokenStream {
-    quote! {
-        #(crate::Spanned::span(#fields)) | *
+        Data::Union(_) => Err(syn::Error::new(
+            Span::call_site(),
+            "Spanned cannot be derived for unions",
+        )),
     }
 }
@@ -0,0 +1,190 @@
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Field, Fields, Generics, Meta,
+    NestedMeta,
+};
+
+pub fn derive_visit(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+
+    let name = input.ident;
+
+    let snake = snake_case(&name.to_string());
+    let visit = format_ident!("visit_{}", snake);
+    let visit_mut = format_ident!("visit_{}_mut", snake);
+
+    let (walk_ref, walk_mut) = match (walk(&input.data, false), walk(&input.data, true)) {
+        (Ok(walk_ref), Ok(walk_mut)) => (walk_ref, walk_mut),
+        (Err(error), _) | (_, Err(error)) => return error.to_compile_error().into(),
+    };
+
+    add_generic(&mut input.generics);
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let expanded = quote! {
+        impl #impl_generics crate::Walk for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn walk<'ast, V: crate::Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V) {
+                #walk_ref
+            }
+
+            #[allow(unused_variables)]
+            fn walk_mut<V: crate::VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+                #walk_mut
+            }
+        }
+
+        impl #impl_generics crate::Node for #name #ty_generics #where_clause {
+            fn accept<'ast, V: crate::Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V) {
+                visitor.#visit(self)
+            }
+
+            fn accept_mut<V: crate::VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+                visitor.#visit_mut(self)
+            }
+        }
+    };
+
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// `BinOpExpr` -> `bin_op_expr`.
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+
+    for (i, ch) in name.chars().enumerate() {
+        if ch.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+
+            snake.extend(ch.to_lowercase());
+        } else {
+            snake.push(ch);
+        }
+    }
+
+    snake
+}
+
+fn add_generic(generics: &mut Generics) {
+    for generics in generics.type_params_mut() {
+        generics.bounds.push_value(parse_quote!(crate::Node));
+    }
+}
+
+/// Whether the field has `#[visit(skip)]`, the only `#[visit]` attribute.
+fn is_skipped(attrs: &[Attribute]) -> syn::Result<bool> {
+    let mut skipped = false;
+
+    for attr in attrs.iter().filter(|attr| attr.path.is_ident("visit")) {
+        match attr.parse_meta()? {
+            Meta::List(list) => match list.nested.iter().collect::<Vec<_>>()[..] {
+                [NestedMeta::Meta(Meta::Path(path))] if path.is_ident("skip") => skipped = true,
+                _ => return Err(syn::Error::new_spanned(list, "expected `#[visit(skip)]`")),
+            },
+            meta => return Err(syn::Error::new_spanned(meta, "expected `#[visit(skip)]`")),
+        }
+    }
+
+    Ok(skipped)
+}
+
+fn accept(field: TokenStream, mutable: bool) -> TokenStream {
+    if mutable {
+        quote!(crate::Node::accept_mut(#field, visitor);)
+    } else {
+        quote!(crate::Node::accept(#field, visitor);)
+    }
+}
+
+/// Bindings for the fields of a struct or variant, with the skipped fields left out.
+fn bindings<'a>(fields: impl Iterator<Item = &'a Field>) -> syn::Result<Vec<Ident>> {
+    let mut bindings = Vec::new();
+
+    for (index, field) in fields.enumerate() {
+        if !is_skipped(&field.attrs)? {
+            bindings.push(match field.ident {
+                Some(ref ident) => ident.clone(),
+                None => Ident::new(&format!("i{}", index), Span::call_site()),
+            });
+        }
+    }
+
+    Ok(bindings)
+}
+
+fn walk(data: &Data, mutable: bool) -> syn::Result<TokenStream> {
+    let reference = if mutable { quote!(&mut) } else { quote!(&) };
+
+    match data {
+        Data::Struct(data) => {
+            let fields = match data.fields {
+                Fields::Named(ref named) => bindings(named.named.iter())?
+                    .into_iter()
+                    .map(|ident| accept(quote!(#reference self.#ident), mutable))
+                    .collect(),
+                Fields::Unnamed(ref unnamed) => {
+                    let mut fields = Vec::new();
+
+                    for (index, field) in unnamed.unnamed.iter().enumerate() {
+                        if !is_skipped(&field.attrs)? {
+                            let index = syn::Index::from(index);
+                            fields.push(accept(quote!(#reference self.#index), mutable));
+                        }
+                    }
+
+                    fields
+                }
+                Fields::Unit => Vec::new(),
+            };
+
+            Ok(quote!(#(#fields)*))
+        }
+        Data::Enum(data) => {
+            let variants = data.variants.iter().map(|variant| {
+                let name = &variant.ident;
+
+                Ok(match variant.fields {
+                    Fields::Named(ref fields) => {
+                        let bindings = bindings(fields.named.iter())?;
+                        let accepts =
+                            (bindings.iter()).map(|ident| accept(quote!(#ident), mutable));
+
+                        quote! {
+                            Self::#name { #(#bindings,)* .. } => { #(#accepts)* }
+                        }
+                    }
+                    Fields::Unnamed(ref fields) => {
+                        let all = (0..fields.unnamed.len())
+                            .map(|index| Ident::new(&format!("i{}", index), Span::call_site()));
+                        let accepts = bindings(fields.unnamed.iter())?
+                            .into_iter()
+                            .map(|ident| accept(quote!(#ident), mutable));
+
+                        quote! {
+                            Self::#name(#(#all),*) => { #(#accepts)* }
+                        }
+                    }
+                    Fields::Unit => quote! {
+                        Self::#name => {}
+                    },
+                })
+            });
+
+            let variants = variants.collect::<syn::Result<Vec<_>>>()?;
+
+            Ok(quote! {
+                match self {
+                    #(#variants)*
+                }
+            })
+        }
+        Data::Union(_) => Err(syn::Error::new(
+            Span::call_site(),
+            "Visit cannot be derived for unions",
+        )),
+    }
+}
@@ -0,0 +1,114 @@
+use std::ops::{BitOr, BitOrAssign};
+
+use dqk_macro::Spanned;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Span(usize, usize);
+
+impl BitOr for Span {
+    type Output = Span;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Span(self.0.min(rhs.0), self.1.max(rhs.1))
+    }
+}
+
+impl BitOrAssign for Span {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = *self | rhs;
+    }
+}
+
+trait Spanned {
+    fn span(&self) -> Span;
+}
+
+impl Spanned for Span {
+    fn span(&self) -> Span {
+        *self
+    }
+}
+
+#[derive(Spanned)]
+struct Stmt {
+    expr: Span,
+    semi: Option<Span>,
+    attrs: Vec<Span>,
+    #[span(skip)]
+    eol: Span,
+}
+
+#[derive(Spanned)]
+struct Program {
+    stmts: Vec<Stmt>,
+    #[span]
+    span: Span,
+}
+
+#[derive(Spanned)]
+#[span(with = Empty::span_of)]
+struct Empty;
+
+impl Empty {
+    fn span_of(&self) -> Span {
+        Span(0, 0)
+    }
+}
+
+#[derive(Spanned)]
+enum Token<T> {
+    Value(T),
+    Pair { left: T, right: Option<T> },
+    #[span(with = Token::eof_span)]
+    Eof,
+}
+
+impl<T> Token<T> {
+    fn eof_span(&self) -> Span {
+        Span(100, 100)
+    }
+}
+
+fn main() {
+    let stmt = Stmt {
+        expr: Span(4, 6),
+        semi: None,
+        attrs: vec![Span(0, 2), Span(2, 3)],
+        eol: Span(6, 7),
+    };
+    assert_eq!(stmt.span(), Span(0, 6));
+
+    let stmt = Stmt {
+        semi: Some(Span(6, 7)),
+        attrs: Vec::new(),
+        ..stmt
+    };
+    assert_eq!(stmt.span(), Span(4, 7));
+
+    let program = Program {
+        stmts: vec![stmt],
+        span: Span(0, 50),
+    };
+    assert_eq!(program.span(), Span(0, 50));
+
+    assert_eq!(Empty.span(), Span(0, 0));
+
+    assert_eq!(Token::Value(Span(1, 2)).span(), Span(1, 2));
+    assert_eq!(
+        Token::Pair {
+            left: Span(1, 2),
+            right: Some(Span(3, 4))
+        }
+        .span(),
+        Span(1, 4)
+    );
+    assert_eq!(
+        Token::Pair {
+            left: Span(1, 2),
+            right: None
+        }
+        .span(),
+        Span(1, 2)
+    );
+    assert_eq!(Token::<Span>::Eof.span(), Span(100, 100));
+}
@@ -0,0 +1,9 @@
+use dqk_macro::Parse;
+
+#[derive(Parse)]
+struct Stmt {
+    #[parse(skip)]
+    expr: Expr,
+}
+
+fn main() {}
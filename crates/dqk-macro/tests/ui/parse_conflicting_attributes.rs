@@ -0,0 +1,9 @@
+use dqk_macro::Parse;
+
+#[derive(Parse)]
+struct Call {
+    #[parse(terminated = Punct::CloseParen, if_next = Punct::OpenParen)]
+    args: Punctuated<Expr, Comma>,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use dqk_macro::Parse;
+
+#[derive(Parse)]
+enum Stmt {
+    Expr(Expr),
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use dqk_macro::Spanned;
+
+#[derive(Spanned)]
+struct Stmt {
+    #[span(ignore)]
+    expr: Expr,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use dqk_macro::Spanned;
+
+#[derive(Spanned)]
+union Token {
+    int: i64,
+    float: f64,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use dqk_macro::Spanned;
+
+#[derive(Spanned)]
+enum Token {
+    Ident(Ident),
+    #[span]
+    Eof,
+}
+
+fn main() {}
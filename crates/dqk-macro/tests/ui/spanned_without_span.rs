@@ -0,0 +1,10 @@
+use dqk_macro::Spanned;
+
+#[derive(Spanned)]
+struct Block {
+    stmts: Vec<Stmt>,
+    #[span(skip)]
+    eol: Eol,
+}
+
+fn main() {}
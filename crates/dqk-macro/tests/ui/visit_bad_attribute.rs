@@ -0,0 +1,9 @@
+use dqk_macro::Visit;
+
+#[derive(Visit)]
+struct Stmt {
+    #[visit(ignore)]
+    expr: Expr,
+}
+
+fn main() {}
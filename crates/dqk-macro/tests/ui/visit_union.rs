@@ -0,0 +1,9 @@
+use dqk_macro::Visit;
+
+#[derive(Visit)]
+union Token {
+    int: i64,
+    float: f64,
+}
+
+fn main() {}
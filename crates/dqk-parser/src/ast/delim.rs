@@ -1,33 +1,158 @@
-use dqk_macro::Spanned;
+use std::ops::{Deref, DerefMut};
 
-use crate::{Gt, Lt, Parse, Parser, Result};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Spanned)]
-pub struct Tupled<T> {
-    pub open: Lt,
+use crate::{
+    CloseBrace, CloseBracket, CloseParen, Error, Gt, Lt, Node, OpenBrace, OpenBracket, OpenParen,
+    Parse, Parser, Punct, Punctuated, Result, Span, Spanned, TokenKind, Trailing, Visit, VisitMut,
+};
+
+/// A pair of delimiters, the `D` of a [`Delimited`].
+pub trait Delimiter {
+    type Open: Parse + Spanned;
+    type Close: Parse + Spanned;
+
+    const OPEN: Punct;
+    const CLOSE: Punct;
+    /// How the opening delimiter is written, for error messages.
+    const OPEN_STR: &'static str;
+    /// Whether line breaks between the delimiters are skipped, they are kept
+    /// where they end statements.
+    const IGNORE_EOL: bool;
+}
+
+macro_rules! delimiters {
+    ($($(#[$attr:meta])* $ident:ident($open:ident $open_str:literal, $close:ident, $ignore_eol:literal)),* $(,)?) => {
+        $(
+            $(#[$attr])*
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct $ident;
+
+            impl Delimiter for $ident {
+                type Open = $open;
+                type Close = $close;
+
+                const OPEN: Punct = Punct::$open;
+                const CLOSE: Punct = Punct::$close;
+                const OPEN_STR: &'static str = $open_str;
+                const IGNORE_EOL: bool = $ignore_eol;
+            }
+        )*
+    };
+}
+
+delimiters! {
+    /// `( .. )`
+    Paren(OpenParen "(", CloseParen, true),
+    /// `{ .. }`
+    Brace(OpenBrace "{", CloseBrace, false),
+    /// `[ .. ]`
+    Bracket(OpenBracket "[", CloseBracket, true),
+    /// `< .. >`
+    Angle(Lt "<", Gt, true),
+}
+
+/// `contents` between a pair of delimiters `D`, like `(contents)`.
+///
+/// Line breaks are skipped anywhere inside `( .. )`, `[ .. ]` and `< .. >`,
+/// even in nested ones, but a `{ .. }` block holds statements that end at line
+/// breaks. There, only line breaks right after the opening and before the
+/// closing delimiter are skipped, and those after every punctuation of a
+/// delimited [`Punctuated`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "D::Open: Serialize, D::Close: Serialize, T: Serialize",
+    deserialize = "D::Open: Deserialize<'de>, D::Close: Deserialize<'de>, T: Deserialize<'de>"
+))]
+pub struct Delimited<D: Delimiter, T> {
+    pub open: D::Open,
     pub contents: T,
-    pub close: Gt,
+    pub close: D::Close,
 }
 
-impl<T> Tupled<T> {
+impl<D: Delimiter, T> Delimited<D, T> {
+    /// Parses the delimiters around `f`, which parses the contents.
     pub fn parse_with(
         parser: &mut Parser,
         f: impl FnOnce(&mut Parser) -> Result<T>,
     ) -> Result<Self> {
-        Ok(Self {
-            open: parser.parse()?,
-            contents: f(parser)?,
-            close: parser.parse()?,
+        let open: D::Open = parser.parse()?;
+
+        parser.ignoring_eol(D::IGNORE_EOL, |parser| {
+            parser.skip_eol()?;
+
+            let contents = f(parser)?;
+            parser.skip_eol()?;
+
+            let close = parser.parse().map_err(|error: Error| {
+                let msg = format!("unclosed '{}' opened here", D::OPEN_STR);
+                error.with_hint(msg, open.span())
+            })?;
+
+            Ok(Self {
+                open,
+                contents,
+                close,
+            })
         })
     }
 }
 
-impl<T: Parse> Parse for Tupled<T> {
+impl<D: Delimiter, T> Spanned for Delimited<D, T> {
+    fn span(&self) -> Span {
+        self.open.span() | self.close.span()
+    }
+}
+
+impl<D: Delimiter, T> Deref for Delimited<D, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.contents
+    }
+}
+
+impl<D: Delimiter, T> DerefMut for Delimited<D, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.contents
+    }
+}
+
+impl<D: Delimiter, T: Node> Node for Delimited<D, T> {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V) {
+        self.contents.accept(visitor)
+    }
+
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        self.contents.accept_mut(visitor)
+    }
+}
+
+impl<D: Delimiter, T: Parse> Parse for Delimited<D, T> {
+    fn parse(parser: &mut Parser) -> Result<Self> {
+        Self::parse_with(parser, T::parse)
+    }
+}
+
+impl<D: Delimiter, I: Parse + Spanned, P: Parse + Spanned> Parse
+    for Delimited<D, Punctuated<I, P>>
+{
+    /// Parses items up to the closing delimiter, on one or more lines.
     fn parse(parser: &mut Parser) -> Result<Self> {
-        Ok(Self {
-            open: parser.parse()?,
-            contents: parser.parse()?,
-            close: parser.parse()?,
+        let close = TokenKind::Punct(D::CLOSE);
+
+        Self::parse_with(parser, |parser| {
+            Punctuated::parse_terminated_with(
+                parser,
+                I::parse,
+                |parser| {
+                    let punct = parser.parse()?;
+                    parser.skip_eol()?;
+                    Ok(punct)
+                },
+                |kind: &TokenKind| *kind == close || kind.is_eol(),
+                Trailing::Allowed,
+            )
         })
     }
 }
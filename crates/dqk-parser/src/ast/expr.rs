@@ -1,38 +1,75 @@
 use dqk_macro::Spanned;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    Asterisk, CloseParen, Comma, EqualEqual, Ident, LitFloat, LitInt, LitStr, Minus, OpenParen,
-    Period, Plus, Punctuated, Slash,
+    Asterisk, Bracket, Comma, Delimited, EqualEqual, Fn, Ident, LitFloat, LitInt, LitStr,
+    LitStrEnd, LitStrMiddle, LitStrStart, Minus, Paren, Parse, Period, Plus, Punctuated, Slash,
+    Visit,
 };
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
 pub struct ParenExpr {
-    pub open: OpenParen,
-    pub expr: Box<Expr>,
-    pub close: CloseParen,
+    pub expr: Delimited<Paren, Box<Expr>>,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub enum LiteralExpr {
     Integer(LitInt),
     Float(LitFloat),
     String(LitStr),
 }
 
-#[derive(Clone, Debug, Spanned)]
+/// `'Hello {user.name}!'`
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
+pub struct InterpolatedExpr {
+    pub start: LitStrStart,
+    pub parts: Vec<Interpolation>,
+}
+
+/// An interpolated expression and the text after it.
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
+pub struct Interpolation {
+    pub expr: Box<Expr>,
+    pub next: InterpolationNext,
+}
+
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
+pub enum InterpolationNext {
+    /// `}text{`, another interpolation follows.
+    Middle(LitStrMiddle),
+    /// `}text'`
+    End(LitStrEnd),
+}
+
+/// `[a, b, c]`
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
+pub struct ListExpr {
+    pub items: Delimited<Bracket, Punctuated<Expr, Comma>>,
+}
+
+/// `fn(a, b) a + b`
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
+pub struct ClosureExpr {
+    #[serde(rename = "fn")]
+    pub _fn: Fn,
+    pub params: Delimited<Paren, Punctuated<Ident, Comma>>,
+    pub body: Box<Expr>,
+}
+
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub struct MemberExpr {
     pub expr: Box<Expr>,
     pub period: Period,
     pub ident: Ident,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub struct CallExpr {
     pub expr: Box<Expr>,
     pub args: Punctuated<Expr, Comma>,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub enum BinOp {
     Add(Plus),
     Sub(Minus),
@@ -51,17 +88,20 @@ impl BinOp {
     }
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub struct BinOpExpr {
     pub lhs: Box<Expr>,
     pub op: BinOp,
     pub rhs: Box<Expr>,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub enum Expr {
     Paren(ParenExpr),
     Literal(LiteralExpr),
+    Interpolated(InterpolatedExpr),
+    List(ListExpr),
+    Closure(ClosureExpr),
     Variable(Ident),
     Member(MemberExpr),
     Call(CallExpr),
@@ -7,7 +7,112 @@ mod ty;
 
 pub use delim::*;
 pub use expr::*;
-pub use parse::*;
 pub use punctuated::*;
 pub use stmt::*;
 pub use ty::*;
+
+/// Version of the serialized form of the AST.
+///
+/// Bumped whenever a node is added, removed or renamed, or its fields change,
+/// so consumers of `dqk parse --json` can detect incompatible output.
+pub const AST_SCHEMA_VERSION: u32 = 4;
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Colon, Comma, DefaultEvents, Diagnostics, ErrorCode, Ident, Interner, Parse, Parser,
+        Program, Punct, Punctuated, SemiColon, SourceMap, SpannedOption, Trailing,
+    };
+
+    /// `name: a, b;`, optionally followed by `<events>` on a later line.
+    #[derive(Parse)]
+    struct Decl {
+        ident: Ident,
+        _colon: Colon,
+        #[parse(terminated = Punct::SemiColon, trailing = Trailing::Forbidden)]
+        items: Punctuated<Ident, Comma>,
+        _semi_colon: SemiColon,
+        #[parse(skip_eol, if_next = Punct::Lt)]
+        events: SpannedOption<DefaultEvents>,
+    }
+
+    #[derive(Parse)]
+    struct Pair(Ident, Ident);
+
+    fn parse<T: Parse>(src: &str, interner: &Interner) -> (T, Diagnostics) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let mut diagnostics = Diagnostics::new();
+        let node = Parser::new(src, file, interner, &mut diagnostics)
+            .parse::<T>()
+            .unwrap();
+
+        (node, diagnostics)
+    }
+
+    #[test]
+    fn program_json_round_trip() {
+        let src = "ln log_message<message_sent>(Message)\nwhere\n\tcontents == 'ping',\n{\n\tchannel.send 'pong'\n\tx := (1 + 2) * 3\n}\n";
+
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("ping.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<Program>().unwrap();
+        assert!(diagnostics.is_empty());
+
+        let json = serde_json::to_value(&program).unwrap();
+        let program: Program = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(serde_json::to_value(&program).unwrap(), json);
+    }
+
+    #[test]
+    fn derived_parse_reads_fields_in_order() {
+        let interner = Interner::new();
+
+        let (decl, diagnostics) = parse::<Decl>("x: a, b;\n\n<message_sent>", &interner);
+        assert!(diagnostics.is_empty());
+
+        let names = |items: &Punctuated<Ident, Comma>| -> Vec<_> {
+            (items.iter())
+                .map(|ident| interner.resolve(ident.value()))
+                .collect()
+        };
+
+        assert_eq!(interner.resolve(decl.ident.value()), "x");
+        assert_eq!(names(&decl.items), ["a", "b"]);
+        assert_eq!(
+            decl.events.as_ref().map(|events| events.events.len()),
+            Some(1)
+        );
+
+        let (decl, diagnostics) = parse::<Decl>("x: a;\n", &interner);
+        assert!(diagnostics.is_empty());
+        assert!(decl.events.is_none());
+
+        let (Pair(a, b), _) = parse::<Pair>("a b", &interner);
+        assert_eq!(interner.resolve(a.value()), "a");
+        assert_eq!(interner.resolve(b.value()), "b");
+    }
+
+    #[test]
+    fn derived_parse_passes_the_trailing_policy() {
+        let interner = Interner::new();
+
+        let (decl, diagnostics) = parse::<Decl>("x: a, b,;", &interner);
+
+        assert_eq!(decl.items.len(), 2);
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|error| error.code())
+                .collect::<Vec<_>>(),
+            [ErrorCode::TrailingPunctuation]
+        );
+    }
+}
@@ -1,28 +1,21 @@
 use crate::{
-    BinOp, BinOpExpr, CallExpr, Error, Expr, Ident, LitFloat, LitInt, LitStr, LiteralExpr,
-    MemberExpr, ParenExpr, Parse, Parser, Punctuated, Result, Symbol, TokenKind,
+    BinOp, BinOpExpr, CallExpr, Error, ErrorCode, Expr, InterpolatedExpr, Interpolation,
+    InterpolationNext, Keyword, LitFloat, LitInt, LitStr, LitStrEnd, LitStrMiddle, LiteralExpr,
+    MemberExpr, Parse, Parser, Punct, Punctuated, Result, TokenKind, Trailing,
 };
 
 impl TokenKind {
     pub fn starts_expr(&self) -> bool {
-        match self {
+        matches!(
+            self,
             TokenKind::Integer(_)
-            | TokenKind::Float(_)
-            | TokenKind::String(_)
-            | TokenKind::Ident(_)
-            | TokenKind::Symbol(Symbol::OpenParen) => true,
-            _ => false,
-        }
-    }
-}
-
-impl Parse for ParenExpr {
-    fn parse(parser: &mut Parser) -> Result<Self> {
-        Ok(Self {
-            open: parser.parse()?,
-            expr: parser.parse()?,
-            close: parser.parse()?,
-        })
+                | TokenKind::Float(_)
+                | TokenKind::String(_)
+                | TokenKind::StringStart(_)
+                | TokenKind::Ident(_)
+                | TokenKind::Punct(Punct::OpenParen | Punct::OpenBracket)
+                | TokenKind::Keyword(Keyword::Fn)
+        )
     }
 }
 
@@ -34,35 +27,67 @@ impl Parse for LiteralExpr {
             TokenKind::Integer(value) => Ok(Self::Integer(LitInt::new(value, tok.span()))),
             TokenKind::Float(value) => Ok(Self::Float(LitFloat::new(value, tok.span()))),
             TokenKind::String(value) => Ok(Self::String(LitStr::new(value, tok.span()))),
-            kind => {
-                Err(Error::new("expected literal")
-                    .with_hint(format!("found '{:?}'", kind), tok.span()))
-            }
+            _ => Err(Error::new(ErrorCode::ExpectedLiteral, "expected literal")
+                .with_found_hint(tok, parser.interner())),
         }
     }
 }
 
-fn parse_term(parser: &mut Parser, ident: Option<Ident>) -> Result<Expr> {
-    if let Some(ident) = ident {
-        return Ok(Expr::Variable(ident));
+impl Parse for InterpolatedExpr {
+    fn parse(parser: &mut Parser) -> Result<Self> {
+        let start = parser.parse()?;
+        let mut parts = Vec::new();
+
+        loop {
+            let expr = parser.parse()?;
+            let tok = parser.next_token()?;
+
+            let next = match tok.kind() {
+                TokenKind::StringMiddle(value) => {
+                    InterpolationNext::Middle(LitStrMiddle::new(value, tok.span()))
+                }
+                TokenKind::StringEnd(value) => {
+                    InterpolationNext::End(LitStrEnd::new(value, tok.span()))
+                }
+                _ => {
+                    return Err(Error::new(ErrorCode::ExpectedToken, "expected '}'")
+                        .with_found_hint(tok, parser.interner()))
+                }
+            };
+
+            let end = matches!(next, InterpolationNext::End(_));
+            parts.push(Interpolation { expr, next });
+
+            if end {
+                break Ok(Self { start, parts });
+            }
+        }
     }
+}
 
+fn parse_term(parser: &mut Parser) -> Result<Expr> {
     let tok = parser.peek_token()?;
 
     match tok.kind() {
         TokenKind::Integer(_) | TokenKind::Float(_) | TokenKind::String(_) => {
             Ok(Expr::Literal(parser.parse()?))
         }
+        TokenKind::StringStart(_) => Ok(Expr::Interpolated(parser.parse()?)),
         TokenKind::Ident(_) => Ok(Expr::Variable(parser.parse()?)),
-        TokenKind::Symbol(Symbol::OpenParen) => Ok(Expr::Paren(parser.parse()?)),
-        _ => Err(Error::new("expected expression").with_fount_hint(tok)),
+        TokenKind::Punct(Punct::OpenParen) => Ok(Expr::Paren(parser.parse()?)),
+        TokenKind::Punct(Punct::OpenBracket) => Ok(Expr::List(parser.parse()?)),
+        TokenKind::Keyword(Keyword::Fn) => Ok(Expr::Closure(parser.parse()?)),
+        _ => Err(
+            Error::new(ErrorCode::ExpectedExpression, "expected expression")
+                .with_found_hint(tok, parser.interner()),
+        ),
     }
 }
 
-fn parse_member(parser: &mut Parser, ident: Option<Ident>) -> Result<Expr> {
-    let mut expr = parse_term(parser, ident)?;
+fn parse_member(parser: &mut Parser) -> Result<Expr> {
+    let mut expr = parse_term(parser)?;
 
-    while parser.peek_token()?.kind() == TokenKind::Symbol(Symbol::Period) {
+    while parser.peek_token()?.kind() == TokenKind::Punct(Punct::Period) {
         expr = Expr::Member(MemberExpr {
             expr: Box::new(expr),
             period: parser.parse()?,
@@ -73,17 +98,19 @@ fn parse_member(parser: &mut Parser, ident: Option<Ident>) -> Result<Expr> {
     Ok(expr)
 }
 
-fn parse_call(parser: &mut Parser, ident: Option<Ident>) -> Result<Expr> {
-    let expr = parse_member(parser, ident)?;
+fn parse_call(parser: &mut Parser) -> Result<Expr> {
+    let expr = parse_member(parser)?;
 
     let tok = parser.peek_token()?;
 
     match tok.kind() {
         kind if kind.starts_expr() => Ok(Expr::Call(CallExpr {
             expr: Box::new(expr),
-            args: Punctuated::parse_terminated(parser, |kind: &TokenKind| {
-                !kind.starts_expr() && *kind != TokenKind::Symbol(Symbol::Comma)
-            })?,
+            args: Punctuated::parse_terminated(
+                parser,
+                |kind: &TokenKind| !kind.starts_expr() && *kind != TokenKind::Punct(Punct::Comma),
+                Trailing::Forbidden,
+            )?,
         })),
         _ => Ok(expr),
     }
@@ -94,18 +121,18 @@ impl BinOp {
         let tok = parser.peek_token()?;
 
         match tok.kind() {
-            TokenKind::Symbol(Symbol::Plus) => Ok(Some(Self::Add(parser.parse()?))),
-            TokenKind::Symbol(Symbol::Minus) => Ok(Some(Self::Sub(parser.parse()?))),
-            TokenKind::Symbol(Symbol::Asterisk) => Ok(Some(Self::Mul(parser.parse()?))),
-            TokenKind::Symbol(Symbol::Slash) => Ok(Some(Self::Div(parser.parse()?))),
-            TokenKind::Symbol(Symbol::EqualEqual) => Ok(Some(Self::Eq(parser.parse()?))),
+            TokenKind::Punct(Punct::Plus) => Ok(Some(Self::Add(parser.parse()?))),
+            TokenKind::Punct(Punct::Minus) => Ok(Some(Self::Sub(parser.parse()?))),
+            TokenKind::Punct(Punct::Asterisk) => Ok(Some(Self::Mul(parser.parse()?))),
+            TokenKind::Punct(Punct::Slash) => Ok(Some(Self::Div(parser.parse()?))),
+            TokenKind::Punct(Punct::EqualEqual) => Ok(Some(Self::Eq(parser.parse()?))),
             _ => Ok(None),
         }
     }
 }
 
-fn parse_bin_op(parser: &mut Parser, ident: Option<Ident>) -> Result<Expr> {
-    let mut lhs = parse_call(parser, ident)?;
+fn parse_bin_op(parser: &mut Parser) -> Result<Expr> {
+    let mut lhs = parse_call(parser)?;
 
     while let Some(op) = BinOp::try_parse(parser)? {
         match lhs {
@@ -120,7 +147,7 @@ fn parse_bin_op(parser: &mut Parser, ident: Option<Ident>) -> Result<Expr> {
                     rhs: Box::new(Expr::BinOp(BinOpExpr {
                         lhs: lhs_rhs,
                         op,
-                        rhs: Box::new(parse_call(parser, None)?),
+                        rhs: Box::new(parse_call(parser)?),
                     })),
                 });
             }
@@ -128,7 +155,7 @@ fn parse_bin_op(parser: &mut Parser, ident: Option<Ident>) -> Result<Expr> {
                 lhs = Expr::BinOp(BinOpExpr {
                     lhs: Box::new(lhs),
                     op,
-                    rhs: Box::new(parse_call(parser, None)?),
+                    rhs: Box::new(parse_call(parser)?),
                 })
             }
         }
@@ -139,12 +166,6 @@ fn parse_bin_op(parser: &mut Parser, ident: Option<Ident>) -> Result<Expr> {
 
 impl Parse for Expr {
     fn parse(parser: &mut Parser) -> Result<Self> {
-        parse_bin_op(parser, None)
-    }
-}
-
-impl Expr {
-    pub fn parse_with_ident(parser: &mut Parser, ident: Ident) -> Result<Self> {
-        parse_bin_op(parser, Some(ident))
+        parse_bin_op(parser)
     }
 }
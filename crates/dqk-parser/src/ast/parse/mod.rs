@@ -1,7 +1,3 @@
 mod expr;
 mod stmt;
 mod ty;
-
-pub use expr::*;
-pub use stmt::*;
-pub use ty::*;
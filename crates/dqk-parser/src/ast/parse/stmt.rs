@@ -1,60 +1,11 @@
 use crate::{
-    AssignNewStmt, AssignStmt, Block, CloseParen, DefaultEvent, DefaultEvents, Error, Expr,
-    ExprStmt, Ident, Keyword, ListenerArgument, ListenerArguments, LnStmt, Parse, Parser,
-    Punctuated, Result, SpannedOption, Stmt, Symbol, TokenKind, Tupled, WhereClause,
+    Block, Delimited, Diagnostic, Error, ErrorCode, Expr, Keyword, ListenerArgument, Parse, Parser,
+    Program, Punct, Punctuated, Result, Spanned, Stmt, TokenKind, Trailing, Where, WhereClause,
 };
 
-impl Parse for AssignNewStmt {
-    fn parse(parser: &mut Parser) -> Result<Self> {
-        Ok(Self {
-            ident: parser.parse()?,
-            equal: parser.parse()?,
-            expr: parser.parse()?,
-            eol: parser.parse()?,
-        })
-    }
-}
-
-impl Parse for AssignStmt {
-    fn parse(parser: &mut Parser) -> Result<Self> {
-        Ok(Self {
-            ident: parser.parse()?,
-            equal: parser.parse()?,
-            expr: parser.parse()?,
-            eol: parser.parse()?,
-        })
-    }
-}
-
-impl Parse for ExprStmt {
-    fn parse(parser: &mut Parser) -> Result<Self> {
-        Ok(Self {
-            expr: parser.parse()?,
-        })
-    }
-}
-
-impl Parse for DefaultEvent {
-    fn parse(parser: &mut Parser) -> Result<Self> {
-        Ok(Self {
-            ident: parser.parse()?,
-        })
-    }
-}
-
-impl Parse for DefaultEvents {
-    fn parse(parser: &mut Parser) -> Result<Self> {
-        Ok(Self {
-            events: Tupled::parse_with(parser, |parser| {
-                Punctuated::parse_terminated(parser, TokenKind::Symbol(Symbol::Gt))
-            })?,
-        })
-    }
-}
-
 impl Parse for WhereClause {
     fn parse(parser: &mut Parser) -> Result<Self> {
-        let _where = parser.parse()?;
+        let _where: Where = parser.parse()?;
         parser.skip_eol()?;
 
         let bounds = Punctuated::parse_terminated_with(
@@ -65,10 +16,19 @@ impl Parse for WhereClause {
                 parser.skip_eol()?;
                 Ok(punct)
             },
-            TokenKind::Symbol(Symbol::OpenBrace),
+            TokenKind::Punct(Punct::OpenBrace),
+            Trailing::Allowed,
         )?;
         parser.skip_eol()?;
 
+        if bounds.is_empty() {
+            parser.emit(
+                Diagnostic::warning(ErrorCode::EmptyWhereClause, "empty where clause")
+                    .with_hint("this where clause has no bounds", _where.span())
+                    .with_help("add bounds or remove the 'where'"),
+            );
+        }
+
         Ok(Self { _where, bounds })
     }
 }
@@ -79,96 +39,43 @@ impl Parse for ListenerArgument {
 
         match tok.kind() {
             TokenKind::Ident(_) => Ok(Self::Expanded(parser.parse()?)),
-            kind => Err(Error::new("expected 'listener argument'")
-                .with_hint(format!("found '{:?}'", kind), tok.span())),
+            _ => Err(Error::new(
+                ErrorCode::ExpectedListenerArgument,
+                "expected 'listener argument'",
+            )
+            .with_found_hint(tok, parser.interner())),
         }
     }
 }
 
-impl Parse for ListenerArguments {
-    fn parse(parser: &mut Parser) -> Result<Self> {
-        Ok(Self {
-            open: parser.parse()?,
-            args: Punctuated::parse_terminated(parser, TokenKind::Symbol(Symbol::CloseParen))?,
-            close: parser.parse()?,
-        })
-    }
-}
-
 impl Parse for Block {
     fn parse(parser: &mut Parser) -> Result<Self> {
-        let open = parser.parse()?;
-
-        let mut stmts = Vec::new();
-
-        loop {
-            parser.skip_eol()?;
+        let stmts = Delimited::parse_with(parser, |parser| {
+            let mut stmts = Vec::new();
 
-            let tok = parser.peek_token()?;
+            loop {
+                parser.skip_eol()?;
 
-            if tok.kind() == TokenKind::Symbol(Symbol::CloseBrace) {
-                break;
+                let tok = parser.peek_token()?;
+
+                if matches!(
+                    tok.kind(),
+                    TokenKind::Punct(Punct::CloseBrace) | TokenKind::Eof
+                ) {
+                    break Ok(stmts);
+                }
+
+                match parser.parse() {
+                    Ok(stmt) => stmts.push(stmt),
+                    Err(error) => {
+                        parser.emit(error);
+                        parser.recover();
+                    }
+                }
             }
+        })?;
 
-            stmts.push(parser.parse()?);
-        }
-
-        Ok(Self {
-            open,
-            stmts,
-            close: parser.parse()?,
-        })
-    }
-}
-
-impl Parse for LnStmt {
-    fn parse(parser: &mut Parser) -> Result<Self> {
-        let ln = parser.parse()?;
-        let ident = parser.parse()?;
-        let default_events = SpannedOption::parse_if_next(parser, TokenKind::Symbol(Symbol::Lt))?;
-        let args = parser.parse()?;
-
-        parser.skip_eol()?;
-
-        let where_clause =
-            SpannedOption::parse_if_next(parser, TokenKind::Keyword(Keyword::Where))?;
-
-        parser.skip_eol()?;
-
-        let block = parser.parse()?;
-
-        Ok(Self {
-            ln,
-            ident,
-            default_events,
-            args,
-            where_clause,
-            block,
-        })
-    }
-}
-
-fn parse_ident_stmt(parser: &mut Parser) -> Result<Stmt> {
-    let ident = parser.parse::<Ident>()?;
-
-    let tok = parser.peek_token()?;
-
-    match tok.kind() {
-        TokenKind::Symbol(Symbol::ColonEqual) => Ok(Stmt::AssignNew(AssignNewStmt {
-            ident,
-            equal: parser.parse()?,
-            expr: parser.parse()?,
-            eol: parser.parse()?,
-        })),
-        TokenKind::Symbol(Symbol::Equal) => Ok(Stmt::Assign(AssignStmt {
-            ident,
-            equal: parser.parse()?,
-            expr: parser.parse()?,
-            eol: parser.parse()?,
-        })),
-        _ => Ok(Stmt::Expr(ExprStmt {
-            expr: Expr::parse_with_ident(parser, ident)?,
-        })),
+        Ok(Self { stmts })
     }
 }
 
@@ -178,10 +85,51 @@ impl Parse for Stmt {
 
         match tok.kind() {
             TokenKind::Keyword(Keyword::Ln) => Ok(Self::Ln(parser.parse()?)),
-            TokenKind::Ident(_) => Ok(parse_ident_stmt(parser)?),
+            TokenKind::Ident(_) => match parser.peek_nth(1)?.kind() {
+                TokenKind::Punct(Punct::ColonEqual) => Ok(Self::AssignNew(parser.parse()?)),
+                TokenKind::Punct(Punct::Equal) => Ok(Self::Assign(parser.parse()?)),
+                _ => Ok(Self::Expr(parser.parse()?)),
+            },
             kind if kind.starts_expr() => Ok(Self::Expr(parser.parse()?)),
-            kind => Err(Error::new("expected statement")
-                .with_hint(format!("found '{:?}'", kind), tok.span())),
+            _ => Err(
+                Error::new(ErrorCode::ExpectedStatement, "expected statement")
+                    .with_found_hint(tok, parser.interner()),
+            ),
+        }
+    }
+}
+
+impl Parse for Program {
+    /// Parses statements until the end of the file.
+    ///
+    /// Errors in a statement are emitted to the parser's diagnostics and the
+    /// parser recovers at the next line, so this only fails if the parser
+    /// itself cannot make progress.
+    fn parse(parser: &mut Parser) -> Result<Self> {
+        let start = parser.span();
+        let mut stmts = Vec::new();
+
+        loop {
+            parser.skip_eol()?;
+
+            if parser.peek_token()?.kind() == TokenKind::Eof {
+                break;
+            }
+
+            match parser.parse() {
+                Ok(stmt) => stmts.push(stmt),
+                Err(error) => {
+                    parser.emit(error);
+                    parser.recover();
+
+                    // there is no block for a '}' to close at the top level
+                    if parser.peek_token()?.kind() == TokenKind::Punct(Punct::CloseBrace) {
+                        parser.next_token()?;
+                    }
+                }
+            }
         }
+
+        Ok(Self::new(stmts, start | parser.span()))
     }
 }
@@ -1,4 +1,4 @@
-use crate::{Error, Parse, Parser, Result, TokenKind, Type};
+use crate::{Error, ErrorCode, Parse, Parser, Result, TokenKind, Type};
 
 impl Parse for Type {
     fn parse(parser: &mut Parser) -> Result<Self> {
@@ -6,10 +6,8 @@ impl Parse for Type {
 
         match tok.kind() {
             TokenKind::Ident(_) => Ok(Type::Ident(parser.parse()?)),
-            kind => {
-                Err(Error::new("expected {type}")
-                    .with_hint(format!("found '{:?}'", kind), tok.span()))
-            }
+            _ => Err(Error::new(ErrorCode::ExpectedType, "expected {type}")
+                .with_found_hint(tok, parser.interner())),
         }
     }
 }
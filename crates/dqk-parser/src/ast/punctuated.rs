@@ -1,4 +1,8 @@
-use crate::{Parse, Parser, Result, Span, Spanned, TokenKind};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Error, ErrorCode, Node, Parse, Parser, Result, Span, Spanned, TokenKind, Visit, VisitMut,
+};
 
 pub trait Termination {
     fn terminate(&self, kind: &TokenKind) -> bool;
@@ -16,7 +20,24 @@ impl<F: Fn(&TokenKind) -> bool> Termination for F {
     }
 }
 
-#[derive(Clone, Debug)]
+/// Whether a [`Punctuated`] may end in a punctuation, like the comma in
+/// `a, b,`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Trailing {
+    /// A trailing punctuation is optional.
+    #[default]
+    Allowed,
+    /// Non-empty lists must end in a punctuation.
+    Required,
+    /// Lists must not end in a punctuation.
+    Forbidden,
+}
+
+/// Items separated by punctuation, like the `a, b, c` of a call.
+///
+/// Every item but the last is followed by a punctuation, the last one may be
+/// followed by one, see [`Punctuated::trailing_punct`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Punctuated<I, P> {
     items: Vec<I>,
     punct: Vec<P>,
@@ -29,13 +50,58 @@ impl<I, P> Spanned for Punctuated<I, P> {
     }
 }
 
+impl<I: Node, P> Node for Punctuated<I, P> {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V) {
+        self.items.accept(visitor)
+    }
+
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        self.items.accept_mut(visitor)
+    }
+}
+
 impl<I, P> Punctuated<I, P> {
-    pub fn parse_terminated(parser: &mut Parser, termination: impl Termination) -> Result<Self>
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, I> {
+        self.items.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, I> {
+        self.items.iter_mut()
+    }
+
+    /// Every item with the punctuation after it, which is `None` only for the
+    /// last item if there is no trailing punctuation.
+    pub fn pairs(&self) -> impl Iterator<Item = (&I, Option<&P>)> {
+        (self.items.iter()).zip(self.punct.iter().map(Some).chain(std::iter::repeat(None)))
+    }
+
+    pub fn puncts(&self) -> std::slice::Iter<'_, P> {
+        self.punct.iter()
+    }
+
+    /// Whether the last item is followed by a punctuation.
+    pub fn trailing_punct(&self) -> bool {
+        !self.items.is_empty() && self.punct.len() == self.items.len()
+    }
+
+    pub fn parse_terminated(
+        parser: &mut Parser,
+        termination: impl Termination,
+        trailing: Trailing,
+    ) -> Result<Self>
     where
-        I: Parse,
-        P: Parse,
+        I: Parse + Spanned,
+        P: Parse + Spanned,
     {
-        Self::parse_terminated_with(parser, I::parse, P::parse, termination)
+        Self::parse_terminated_with(parser, I::parse, P::parse, termination, trailing)
     }
 
     // NOTE(changecaps): the function signature is fucked but this is the best way i could think of doing it.
@@ -44,8 +110,15 @@ impl<I, P> Punctuated<I, P> {
         mut parse_item: impl FnMut(&mut Parser) -> Result<I>,
         mut parse_punct: impl FnMut(&mut Parser) -> Result<P>,
         termination: impl Termination,
-    ) -> Result<Self> {
-        let span = parser.span();
+        trailing: Trailing,
+    ) -> Result<Self>
+    where
+        I: Spanned,
+        P: Spanned,
+    {
+        // an empty list is an empty span where the first item would be
+        let first = parser.peek_token()?.span();
+        let mut span = Span::new(first.file(), first.start(), 0);
 
         let mut items = Vec::new();
         let mut punct = Vec::new();
@@ -54,26 +127,189 @@ impl<I, P> Punctuated<I, P> {
             let tok = parser.peek_token()?;
 
             if termination.terminate(&tok.kind()) {
-                break Ok(Self {
-                    items,
-                    punct,
-                    span: span | parser.span(),
-                });
+                break;
             }
 
-            items.push(parse_item(parser)?);
+            let item = parse_item(parser)?;
+            span |= item.span();
+            items.push(item);
 
             let tok = parser.peek_token()?;
 
             if termination.terminate(&tok.kind()) {
-                break Ok(Self {
-                    items,
-                    punct,
-                    span: span | parser.span(),
-                });
+                break;
             }
 
-            punct.push(parse_punct(parser)?);
+            let p = parse_punct(parser)?;
+            span |= p.span();
+            punct.push(p);
         }
+
+        let punctuated = Self { items, punct, span };
+        punctuated.check_trailing(parser, trailing);
+
+        Ok(punctuated)
+    }
+
+    /// Parses one or more items separated by punctuation, without a trailing
+    /// one. The list ends at the first item not followed by a punctuation.
+    pub fn parse_separated_nonempty(parser: &mut Parser) -> Result<Self>
+    where
+        I: Parse + Spanned,
+        P: Parse + Spanned,
+    {
+        let item: I = parser.parse()?;
+        let mut span = item.span();
+
+        let mut items = vec![item];
+        let mut punct = Vec::new();
+
+        loop {
+            let checkpoint = parser.checkpoint();
+
+            let p: P = match parser.parse() {
+                Ok(p) => p,
+                Err(_) => {
+                    parser.rewind(checkpoint);
+                    break;
+                }
+            };
+
+            let item: I = parser.parse()?;
+            span |= p.span() | item.span();
+
+            punct.push(p);
+            items.push(item);
+        }
+
+        Ok(Self { items, punct, span })
+    }
+
+    /// Reports a list that breaks the `trailing` policy, parsing continues.
+    fn check_trailing(&self, parser: &mut Parser, trailing: Trailing)
+    where
+        I: Spanned,
+        P: Spanned,
+    {
+        match trailing {
+            Trailing::Forbidden if self.trailing_punct() => {
+                let p = self.punct.last().unwrap();
+
+                parser.emit(
+                    Error::new(
+                        ErrorCode::TrailingPunctuation,
+                        "unexpected trailing punctuation",
+                    )
+                    .with_hint("remove this punctuation", p.span()),
+                );
+            }
+            Trailing::Required if !self.is_empty() && !self.trailing_punct() => {
+                let item = self.items.last().unwrap();
+
+                parser.emit(
+                    Error::new(
+                        ErrorCode::TrailingPunctuation,
+                        "missing trailing punctuation",
+                    )
+                    .with_hint("add a punctuation after this", item.span()),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<I, P> IntoIterator for Punctuated<I, P> {
+    type Item = I;
+    type IntoIter = std::vec::IntoIter<I>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'a, I, P> IntoIterator for &'a Punctuated<I, P> {
+    type Item = &'a I;
+    type IntoIter = std::slice::Iter<'a, I>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, I, P> IntoIterator for &'a mut Punctuated<I, P> {
+    type Item = &'a mut I;
+    type IntoIter = std::slice::IterMut<'a, I>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CallExpr, Comma, Diagnostics, Expr, Ident, Interner, SourceMap, Stmt};
+
+    fn call(src: &str, diagnostics: &mut Diagnostics) -> CallExpr {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut parser = Parser::new(src, file, &interner, diagnostics);
+
+        match parser.parse::<Stmt>().unwrap() {
+            Stmt::Expr(stmt) => match stmt.expr {
+                Expr::Call(call) => call,
+                expr => panic!("expected call, found {:?}", expr),
+            },
+            stmt => panic!("expected expression, found {:?}", stmt),
+        }
+    }
+
+    #[test]
+    fn pairs_and_trailing_punct() {
+        let mut diagnostics = Diagnostics::new();
+        let call = call("send a, b, c\n", &mut diagnostics);
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(call.args.len(), 3);
+        assert!(!call.args.trailing_punct());
+
+        let puncts: Vec<_> = call.args.pairs().map(|(_, p)| p.is_some()).collect();
+        assert_eq!(puncts, [true, true, false]);
+        assert_eq!((&call.args).into_iter().count(), 3);
+    }
+
+    #[test]
+    fn forbidden_trailing_punct() {
+        let mut diagnostics = Diagnostics::new();
+        let call = call("send a, b,\n", &mut diagnostics);
+
+        assert!(call.args.trailing_punct());
+
+        let codes: Vec<_> = diagnostics.iter().map(|error| error.code()).collect();
+        assert_eq!(codes, [ErrorCode::TrailingPunctuation]);
+    }
+
+    #[test]
+    fn separated_nonempty() {
+        let mut source_map = SourceMap::new();
+        let src = "a, b c";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let list = Punctuated::<Ident, Comma>::parse_separated_nonempty(&mut parser).unwrap();
+
+        assert_eq!(list.len(), 2);
+        assert!(!list.trailing_punct());
+        assert_eq!(source_map.snippet(list.span()), "a, b");
+        assert!(parser.parse::<Ident>().is_ok());
+
+        let mut parser = Parser::new("", file, &interner, &mut diagnostics);
+        assert!(Punctuated::<Ident, Comma>::parse_separated_nonempty(&mut parser).is_err());
     }
 }
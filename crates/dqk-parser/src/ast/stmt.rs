@@ -1,9 +1,11 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    CloseBrace, CloseParen, ColonEqual, Comma, Eol, Equal, Expr, Ident, Ln, OpenBrace, OpenParen,
-    Punctuated, SemiColon, Span, Spanned, SpannedOption, Tupled, Type, Where,
+    Angle, Brace, ColonEqual, Comma, Delimited, Eol, Equal, Expr, Ident, Keyword, Ln, Paren, Parse,
+    Punct, Punctuated, SemiColon, Span, Spanned, SpannedOption, Type, Visit, Where,
 };
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
 pub struct AssignNewStmt {
     pub ident: Ident,
     pub equal: ColonEqual,
@@ -11,7 +13,7 @@ pub struct AssignNewStmt {
     pub eol: Eol,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
 pub struct AssignStmt {
     pub ident: Ident,
     pub equal: Equal,
@@ -19,74 +21,83 @@ pub struct AssignStmt {
     pub eol: Eol,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub struct Block {
-    pub open: OpenBrace,
-    pub stmts: Vec<Stmt>,
-    pub close: CloseBrace,
-}
-
-impl Spanned for Block {
-    fn span(&self) -> Span {
-        self.open.span() | self.close.span()
-    }
+    pub stmts: Delimited<Brace, Vec<Stmt>>,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
 pub struct Argument {
     pub ident: Ident,
     pub semi_colon: SemiColon,
     pub ty: Type,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub enum ListenerArgument {
     Expanded(Type),
     Args(Argument),
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
 pub struct ListenerArguments {
-    pub open: OpenParen,
-    pub args: Punctuated<ListenerArgument, Comma>,
-    pub close: CloseParen,
+    pub args: Delimited<Paren, Punctuated<ListenerArgument, Comma>>,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub struct WhereClause {
+    #[serde(rename = "where")]
     pub _where: Where,
     pub bounds: Punctuated<Expr, Comma>,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
 pub struct DefaultEvent {
     pub ident: Ident,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
 pub struct DefaultEvents {
-    pub events: Tupled<Punctuated<DefaultEvent, Comma>>,
+    pub events: Delimited<Angle, Punctuated<DefaultEvent, Comma>>,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
 pub struct LnStmt {
     pub ln: Ln,
     pub ident: Ident,
+    #[parse(if_next = Punct::Lt)]
     pub default_events: SpannedOption<DefaultEvents>,
     pub args: ListenerArguments,
+    #[parse(skip_eol, if_next = Keyword::Where)]
     pub where_clause: SpannedOption<WhereClause>,
+    #[parse(skip_eol)]
     pub block: Block,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Parse, Spanned, Visit, Serialize, Deserialize)]
 pub struct ExprStmt {
     pub expr: Expr,
 }
 
-#[derive(Clone, Debug, Spanned)]
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub enum Stmt {
-    Ln(LnStmt),
+    Ln(Box<LnStmt>),
     AssignNew(AssignNewStmt),
     Assign(AssignStmt),
     Expr(ExprStmt),
 }
+
+/// The statements of a whole source file.
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
+pub struct Program {
+    pub stmts: Vec<Stmt>,
+    #[span]
+    #[visit(skip)]
+    span: Span,
+}
+
+impl Program {
+    pub fn new(stmts: Vec<Stmt>, span: Span) -> Self {
+        Self { stmts, span }
+    }
+}
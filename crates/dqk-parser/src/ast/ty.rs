@@ -1,6 +1,8 @@
-use crate::{Ident, Spanned};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Spanned)]
+use crate::{Ident, Spanned, Visit};
+
+#[derive(Clone, Debug, Spanned, Visit, Serialize, Deserialize)]
 pub enum Type {
     Ident(Ident),
 }
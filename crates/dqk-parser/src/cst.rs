@@ -0,0 +1,470 @@
+use std::{fmt, iter::Peekable, rc::Rc, sync::Arc, vec};
+
+use crate::{
+    AstNode, Events, FileId, NodeEvent, NodeKind, Parser, Program, Result, Span, Spanned, Token,
+    TokenKind, Visit,
+};
+
+/// Kind of a token in a syntax tree.
+///
+/// Unlike the tokens the parser sees, the syntax tree also keeps the text
+/// between them, so it can reproduce the source exactly.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyntaxTokenKind {
+    Token(TokenKind),
+    Whitespace,
+    /// A `#` comment, up to the end of the line.
+    Comment,
+    /// Text the lexer could not make sense of.
+    Unknown,
+}
+
+impl SyntaxTokenKind {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Self::Whitespace | Self::Comment)
+    }
+}
+
+/// A token of the green tree, which only knows its text and not where it is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GreenToken {
+    kind: SyntaxTokenKind,
+    text: Box<str>,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxTokenKind, text: impl Into<Box<str>>) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxTokenKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// A node of the green tree.
+///
+/// Green nodes are immutable and only store their width, so equal subtrees
+/// can be shared and a tree can be edited by rebuilding the path to the root.
+/// [`SyntaxNode`] adds positions and parents on top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GreenNode {
+    kind: NodeKind,
+    width: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: NodeKind, children: Vec<GreenElement>) -> Self {
+        Self {
+            kind,
+            width: children.iter().map(GreenElement::width).sum(),
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    /// Length of the text of the node in bytes.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GreenElement {
+    Node(Arc<GreenNode>),
+    Token(Arc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn width(&self) -> usize {
+        match self {
+            Self::Node(node) => node.width(),
+            Self::Token(token) => token.text().len(),
+        }
+    }
+}
+
+struct NodeData {
+    green: Arc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    file: FileId,
+    offset: usize,
+}
+
+/// A node of a lossless syntax tree, with its position in the file.
+///
+/// Every node corresponds to the AST node of the same [`NodeKind`] and
+/// [`Span`], so the typed AST acts as a view over the tree, see
+/// [`SyntaxNode::find`]. Printing the root reproduces the source exactly.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+impl SyntaxNode {
+    pub fn new_root(green: Arc<GreenNode>, file: FileId) -> Self {
+        Self(Rc::new(NodeData {
+            green,
+            parent: None,
+            file,
+            offset: 0,
+        }))
+    }
+
+    /// Builds the tree of `program` from the `tokens` of `src`.
+    ///
+    /// The text between tokens becomes whitespace, comment and unknown tokens,
+    /// and every token is put in the innermost node whose span contains it.
+    pub fn build(src: &str, file: FileId, tokens: &[Token], program: &Program) -> Self {
+        let mut builder = Builder {
+            src,
+            pieces: pieces(src, tokens).into_iter().peekable(),
+            stack: Vec::new(),
+            root: None,
+        };
+
+        Events(|event| builder.event(event)).visit_program(program);
+
+        Self::new_root(builder.root.unwrap(), file)
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.0.green.kind()
+    }
+
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.0.offset;
+
+        self.0.green.children().iter().map(move |child| {
+            let start = offset;
+            offset += child.width();
+
+            match child {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                    green: green.clone(),
+                    parent: Some(self.clone()),
+                    file: self.0.file,
+                    offset: start,
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                    green: green.clone(),
+                    parent: self.clone(),
+                    offset: start,
+                }),
+            }
+        })
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Every token in the node, in source order.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+
+        tokens
+    }
+
+    /// Finds the syntax node of an AST node in this tree.
+    pub fn find<T: AstNode>(&self, node: &T) -> Option<SyntaxNode> {
+        let span = node.span();
+
+        if self.kind() == T::KIND && self.span() == span {
+            return Some(self.clone());
+        }
+
+        let child = self.child_nodes().find(|child| {
+            let child = child.span();
+            child.start() <= span.start() && span.end() <= child.end()
+        })?;
+
+        child.find(node)
+    }
+}
+
+impl Spanned for SyntaxNode {
+    fn span(&self) -> Span {
+        Span::new(self.0.file, self.0.offset, self.0.green.width())
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write(green: &GreenNode, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            for child in green.children() {
+                match child {
+                    GreenElement::Node(node) => write(node, f)?,
+                    GreenElement::Token(token) => f.write_str(token.text())?,
+                }
+            }
+
+            Ok(())
+        }
+
+        write(self.green(), f)
+    }
+}
+
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        write!(f, "{:?}@{}..{}", self.kind(), span.start(), span.end())
+    }
+}
+
+/// A token of a lossless syntax tree, with its position in the file.
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Arc<GreenToken>,
+    parent: SyntaxNode,
+    offset: usize,
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxTokenKind {
+        self.green.kind()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+}
+
+impl Spanned for SyntaxToken {
+    fn span(&self) -> Span {
+        Span::new(self.parent.0.file, self.offset, self.text().len())
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{} {:?}", self.kind(), self.offset, self.text())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// A token of the tree before it is put in a node, as a byte range of the source.
+struct Piece {
+    kind: SyntaxTokenKind,
+    start: usize,
+    end: usize,
+}
+
+/// Splits the text between tokens into whitespace, comments and unknown text.
+fn trivia(src: &str, start: usize, end: usize, pieces: &mut Vec<Piece>) {
+    let mut chars = src[start..end].char_indices().peekable();
+
+    while let Some(&(offset, ch)) = chars.peek() {
+        let (kind, rest): (_, fn(char) -> bool) = if ch == '#' {
+            (SyntaxTokenKind::Comment, |c| c != '\n')
+        } else if ch.is_whitespace() {
+            (SyntaxTokenKind::Whitespace, char::is_whitespace)
+        } else {
+            (SyntaxTokenKind::Unknown, |c| !c.is_whitespace() && c != '#')
+        };
+
+        chars.next();
+
+        let mut piece_end = start + offset + ch.len_utf8();
+
+        while let Some(&(offset, ch)) = chars.peek() {
+            if !rest(ch) {
+                break;
+            }
+
+            piece_end = start + offset + ch.len_utf8();
+            chars.next();
+        }
+
+        pieces.push(Piece {
+            kind,
+            start: start + offset,
+            end: piece_end,
+        });
+    }
+}
+
+fn pieces(src: &str, tokens: &[Token]) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut end = 0;
+
+    for token in tokens {
+        let span = token.span();
+
+        trivia(src, end, span.start(), &mut pieces);
+
+        pieces.push(Piece {
+            kind: SyntaxTokenKind::Token(token.kind()),
+            start: span.start(),
+            end: span.end(),
+        });
+
+        end = span.end();
+    }
+
+    trivia(src, end, src.len(), &mut pieces);
+
+    pieces
+}
+
+struct Builder<'a> {
+    src: &'a str,
+    pieces: Peekable<vec::IntoIter<Piece>>,
+    stack: Vec<(NodeKind, Vec<GreenElement>)>,
+    root: Option<Arc<GreenNode>>,
+}
+
+impl Builder<'_> {
+    /// Adds the pieces starting before `end` to the innermost open node.
+    fn flush(&mut self, end: usize) {
+        let (_, children) = self.stack.last_mut().unwrap();
+
+        while let Some(piece) = self.pieces.next_if(|piece| piece.start < end) {
+            let text = &self.src[piece.start..piece.end];
+            children.push(GreenElement::Token(Arc::new(GreenToken::new(
+                piece.kind, text,
+            ))));
+        }
+    }
+
+    fn event(&mut self, event: NodeEvent) {
+        match event {
+            NodeEvent::Enter(kind, span) => {
+                if !self.stack.is_empty() {
+                    self.flush(span.start());
+                }
+
+                self.stack.push((kind, Vec::new()));
+            }
+            NodeEvent::Exit(span) => {
+                // the root also gets everything after its last node
+                let end = if self.stack.len() == 1 {
+                    usize::MAX
+                } else {
+                    span.end()
+                };
+
+                self.flush(end);
+
+                let (kind, children) = self.stack.pop().unwrap();
+                let node = Arc::new(GreenNode::new(kind, children));
+
+                match self.stack.last_mut() {
+                    Some((_, children)) => children.push(GreenElement::Node(node)),
+                    None => self.root = Some(node),
+                }
+            }
+        }
+    }
+}
+
+impl Parser<'_> {
+    /// Parses a whole file into its AST and a lossless syntax tree of it.
+    pub fn parse_syntax(&mut self) -> Result<(Program, SyntaxNode)> {
+        let program = self.parse::<Program>()?;
+        let tree = SyntaxNode::build(self.source(), self.file(), self.tokens(), &program);
+
+        Ok((program, tree))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Diagnostics, Interner, SourceMap, Stmt};
+
+    fn parse(src: &str) -> (Program, SyntaxNode, Diagnostics) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let (program, tree) = parser.parse_syntax().unwrap();
+
+        (program, tree, diagnostics)
+    }
+
+    #[test]
+    fn round_trips_source() {
+        let src = "# greet everyone\n\nln greet<message_sent>(Message)  \nwhere\n\tcontents == 'hi',   # only hi\n{\n\n\tchannel.send   'hello', (1+2) * 3\n}\n\n\n";
+
+        let (_, tree, diagnostics) = parse(src);
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(tree.to_string(), src);
+        assert_eq!(tree.span().end(), src.len());
+    }
+
+    #[test]
+    fn round_trips_errors() {
+        let src = "x := 1 +\ny := $ 'oops\n  z  = 3";
+
+        let (_, tree, diagnostics) = parse(src);
+
+        assert!(diagnostics.has_errors());
+        assert_eq!(tree.to_string(), src);
+    }
+
+    #[test]
+    fn ast_nodes_find_their_syntax() {
+        let src = "x := foo 1,  2 # call\n";
+
+        let (program, tree, _) = parse(src);
+
+        let stmt = match &program.stmts[0] {
+            Stmt::AssignNew(stmt) => stmt,
+            stmt => panic!("expected declaration, found {:?}", stmt),
+        };
+
+        let syntax = tree.find(&stmt.expr).unwrap();
+        assert_eq!(syntax.kind(), NodeKind::Expr);
+        assert_eq!(syntax.to_string(), "foo 1,  2");
+
+        let comment = tree.tokens().into_iter().rev().nth(1).unwrap();
+        assert_eq!(comment.kind(), SyntaxTokenKind::Comment);
+        assert_eq!(comment.parent().kind(), NodeKind::AssignNewStmt);
+    }
+}
@@ -0,0 +1,462 @@
+use std::{fmt, panic::Location};
+
+use serde::Serialize;
+
+use crate::{ErrorCode, Interner, Keyword, SourceMap, Span, Token, TokenKind};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A fatal [`Diagnostic`], as returned by parsing.
+pub type Error = Diagnostic;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Severity {
+    Help,
+    Note,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Help => "help",
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    severity: Severity,
+    code: ErrorCode,
+    hints: Vec<ErrorHint>,
+    children: Vec<SubDiagnostic>,
+    msg: String,
+    location: &'static Location<'static>,
+}
+
+impl Diagnostic {
+    /// Creates an error, use [`Diagnostic::warning`] or
+    /// [`Diagnostic::with_severity`] for other severities.
+    #[track_caller]
+    pub fn new(code: ErrorCode, msg: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            code,
+            hints: Vec::new(),
+            children: Vec::new(),
+            msg: msg.into(),
+            location: Location::caller(),
+        }
+    }
+
+    #[track_caller]
+    pub fn warning(code: ErrorCode, msg: impl Into<String>) -> Self {
+        Self::new(code, msg).with_severity(Severity::Warning)
+    }
+
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    pub fn with_hint(mut self, msg: impl Into<String>, span: Span) -> Self {
+        self.hints.push(ErrorHint::new(msg, span));
+        self
+    }
+
+    /// Adds a hint pointing at the unexpected token `found`.
+    ///
+    /// If `found` is an identifier that looks like a misspelled keyword, a
+    /// "did you mean" hint is added as well.
+    pub fn with_found_hint(mut self, found: Token, interner: &Interner) -> Self {
+        self.hints.push(ErrorHint::new(
+            format!("found '{}'", found.kind().describe(interner)),
+            found.span(),
+        ));
+
+        if let TokenKind::Ident(ident) = found.kind() {
+            if let Some(keyword) = Keyword::suggest(interner.resolve(ident)) {
+                self = self.with_suggestion(keyword.as_str(), found.span());
+            }
+        }
+
+        self
+    }
+
+    pub fn with_suggestion(self, suggestion: &str, span: Span) -> Self {
+        self.with_hint(format!("did you mean '{}'?", suggestion), span)
+    }
+
+    pub fn with_child(mut self, child: SubDiagnostic) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn with_note(self, msg: impl Into<String>) -> Self {
+        self.with_child(SubDiagnostic::new(Severity::Note, msg))
+    }
+
+    pub fn with_help(self, msg: impl Into<String>) -> Self {
+        self.with_child(SubDiagnostic::new(Severity::Help, msg))
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    pub fn hints(&self) -> &[ErrorHint] {
+        &self.hints
+    }
+
+    pub fn children(&self) -> &[SubDiagnostic] {
+        &self.children
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn location(&self) -> &'static Location<'static> {
+        self.location
+    }
+
+    /// Displays the diagnostic with the file, line and source text of every hint.
+    pub fn display<'a>(&'a self, source_map: &'a SourceMap) -> DisplayDiagnostic<'a> {
+        DisplayDiagnostic {
+            diagnostic: self,
+            source_map,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}[{}]: {}", self.severity, self.code, self.msg)?;
+
+        for hint in &self.hints {
+            let span = hint.span();
+            writeln!(f, "  --> {}..{}: {}", span.start(), span.end(), hint.msg())?;
+        }
+
+        for child in &self.children {
+            writeln!(f, "  = {}: {}", child.severity(), child.msg())?;
+        }
+
+        Ok(())
+    }
+}
+
+pub struct DisplayDiagnostic<'a> {
+    diagnostic: &'a Diagnostic,
+    source_map: &'a SourceMap,
+}
+
+impl fmt::Display for DisplayDiagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let diagnostic = self.diagnostic;
+
+        writeln!(
+            f,
+            "{}[{}]: {}",
+            diagnostic.severity, diagnostic.code, diagnostic.msg
+        )?;
+
+        let gutter = (diagnostic.hints.iter())
+            .map(|hint| self.source_map.line_col(hint.span()).line.to_string().len())
+            .max()
+            .unwrap_or(0);
+
+        let mut previous_file = None;
+
+        for hint in &diagnostic.hints {
+            let span = hint.span();
+            let file = self.source_map.file(span.file());
+            let start = file.line_col(span.start());
+            let end = file.line_col(span.end());
+
+            let line = file.line(start.line);
+
+            // keep tabs so the markers line up with the source line
+            let padding: String = (line.chars().take(start.col - 1))
+                .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            let length = if end.line == start.line {
+                end.col - start.col
+            } else {
+                line.chars().count() + 1 - start.col
+            };
+
+            // like rustc, the file is named once for consecutive hints in it
+            match previous_file {
+                None => writeln!(f, "{:gutter$}--> {}:{}", "", file.name().display(), start)?,
+                Some(previous) if previous != span.file() => {
+                    writeln!(f, "{:gutter$}::: {}:{}", "", file.name().display(), start)?
+                }
+                Some(_) => {}
+            }
+
+            previous_file = Some(span.file());
+
+            writeln!(f, "{:gutter$} |", "")?;
+            writeln!(f, "{:>gutter$} | {}", start.line, line)?;
+            writeln!(
+                f,
+                "{:gutter$} | {}{} {}",
+                "",
+                padding,
+                "^".repeat(length.max(1)),
+                hint.msg()
+            )?;
+        }
+
+        for child in &diagnostic.children {
+            writeln!(f, "{:gutter$} = {}: {}", "", child.severity(), child.msg())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A note or help message attached to a [`Diagnostic`].
+#[derive(Clone, Debug)]
+pub struct SubDiagnostic {
+    severity: Severity,
+    msg: String,
+}
+
+impl SubDiagnostic {
+    pub fn new(severity: Severity, msg: impl Into<String>) -> Self {
+        Self {
+            severity,
+            msg: msg.into(),
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ErrorHint {
+    span: Span,
+    msg: String,
+}
+
+impl ErrorHint {
+    pub fn new(msg: impl Into<String>, span: Span) -> Self {
+        Self {
+            span,
+            msg: msg.into(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+}
+
+/// Collects the diagnostics emitted by parsing and the passes after it.
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+    diagnostics: Vec<Diagnostic>,
+    deny_warnings: bool,
+    allow_warnings: bool,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emits every warning as an error from now on.
+    pub fn deny_warnings(&mut self) {
+        self.deny_warnings = true;
+    }
+
+    /// Drops every warning emitted from now on.
+    pub fn allow_warnings(&mut self) {
+        self.allow_warnings = true;
+    }
+
+    /// An empty collector with the same lint levels as `self`.
+    pub fn without_diagnostics(&self) -> Self {
+        Self {
+            diagnostics: Vec::new(),
+            ..*self
+        }
+    }
+
+    pub fn emit(&mut self, mut diagnostic: Diagnostic) {
+        if diagnostic.severity() == Severity::Warning {
+            if self.deny_warnings {
+                diagnostic = diagnostic
+                    .with_severity(Severity::Error)
+                    .with_note("'--deny warnings' turns warnings into errors");
+            } else if self.allow_warnings {
+                return;
+            }
+        }
+
+        self.diagnostics.push(diagnostic);
+    }
+
+    pub fn extend(&mut self, diagnostics: Diagnostics) {
+        for diagnostic in diagnostics {
+            self.emit(diagnostic);
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Diagnostic> {
+        self.diagnostics.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.diagnostics.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    pub fn count(&self, severity: Severity) -> usize {
+        self.iter()
+            .filter(|diagnostic| diagnostic.severity() == severity)
+            .count()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.iter().any(Diagnostic::is_error)
+    }
+}
+
+impl IntoIterator for Diagnostics {
+    type Item = Diagnostic;
+    type IntoIter = std::vec::IntoIter<Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.diagnostics.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Diagnostics {
+    type Item = &'a Diagnostic;
+    type IntoIter = std::slice::Iter<'a, Diagnostic>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, Program};
+
+    fn parse_empty_where(diagnostics: &mut Diagnostics) {
+        let mut source_map = SourceMap::new();
+        let src = "ln f(Message) where {\n}\n";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut parser = Parser::new(src, file, &interner, diagnostics);
+
+        parser.parse::<Program>().unwrap();
+    }
+
+    #[test]
+    fn empty_where_clause_is_a_warning() {
+        let mut diagnostics = Diagnostics::new();
+        parse_empty_where(&mut diagnostics);
+
+        let codes: Vec<_> = diagnostics.iter().map(Diagnostic::code).collect();
+
+        assert_eq!(codes, [ErrorCode::EmptyWhereClause]);
+        assert_eq!(diagnostics.count(Severity::Warning), 1);
+        assert!(!diagnostics.has_errors());
+    }
+
+    #[test]
+    fn denied_warnings_are_errors() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.deny_warnings();
+        parse_empty_where(&mut diagnostics);
+
+        let diagnostic = diagnostics.iter().next().unwrap();
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostic.code(), ErrorCode::EmptyWhereClause);
+        assert_eq!(diagnostic.severity(), Severity::Error);
+        assert!(diagnostics.has_errors());
+    }
+
+    #[test]
+    fn allowed_warnings_are_dropped() {
+        let mut diagnostics = Diagnostics::new();
+        diagnostics.allow_warnings();
+        parse_empty_where(&mut diagnostics);
+
+        assert!(diagnostics.is_empty());
+
+        // errors are never allowed
+        diagnostics.emit(Diagnostic::new(ErrorCode::UndefinedName, "cannot find 'y'"));
+        assert!(diagnostics.has_errors());
+    }
+
+    #[test]
+    fn files_are_named_once_per_run_of_hints() {
+        let mut source_map = SourceMap::new();
+        let a = source_map.add("a.dqk", "x := y\nz := y\n");
+        let b = source_map.add("b.dqk", "y := 1\n");
+
+        let diagnostic = Diagnostic::new(ErrorCode::UndefinedName, "cannot find 'y'")
+            .with_hint("here", Span::new(a, 5, 1))
+            .with_hint("and here", Span::new(a, 12, 1))
+            .with_hint("defined here", Span::new(b, 0, 1));
+
+        assert_eq!(
+            diagnostic.display(&source_map).to_string(),
+            "\
+error[E0012]: cannot find 'y'
+ --> a.dqk:1:6
+  |
+1 | x := y
+  |      ^ here
+  |
+2 | z := y
+  |      ^ and here
+ ::: b.dqk:1:1
+  |
+1 | y := 1
+  | ^ defined here
+"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::{fmt, str::FromStr};
+
+use serde::Serialize;
+
+macro_rules! error_codes {
+    ($($code:ident => $variant:ident,)*) => {
+        /// Stable identifier of a kind of [`Diagnostic`](crate::Diagnostic).
+        ///
+        /// Error codes start with `E` and warning codes with `W`. Codes are never
+        /// reused or renumbered, so they can be searched for and referenced in
+        /// documentation. `dqk explain <code>` prints the long description
+        /// returned by [`ErrorCode::explain`].
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+        pub enum ErrorCode {
+            $($variant),*
+        }
+
+        impl ErrorCode {
+            pub const ALL: &'static [ErrorCode] = &[$(Self::$variant),*];
+
+            pub const fn as_str(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => stringify!($code)),*
+                }
+            }
+
+            /// Long description of the error with an example and a fix, in markdown.
+            pub const fn explain(&self) -> &'static str {
+                match self {
+                    $(Self::$variant => include_str!(concat!(
+                        "error_codes/",
+                        stringify!($code),
+                        ".md"
+                    ))),*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    E0001 => UnexpectedCharacter,
+    E0002 => UnterminatedString,
+    E0003 => MalformedInteger,
+    E0004 => ExpectedToken,
+    E0005 => ExpectedEol,
+    E0006 => ExpectedExpression,
+    E0007 => ExpectedLiteral,
+    E0008 => ExpectedStatement,
+    E0009 => ExpectedType,
+    E0010 => ExpectedListenerArgument,
+    E0011 => TrailingPunctuation,
+    E0012 => UndefinedName,
+    E0013 => DuplicateDefinition,
+    E0014 => UnknownType,
+    E0015 => MismatchedTypes,
+    E0016 => UnknownMember,
+    E0017 => InvalidCall,
+    E0018 => CannotInfer,
+    E0019 => UnknownEvent,
+    E0020 => IncompatibleEvent,
+    E0021 => DivisionByZero,
+    E0022 => IntegerOverflow,
+    E0023 => MissingField,
+    E0024 => NotAValue,
+    E0025 => UncheckedProgram,
+    W0001 => EmptyWhereClause,
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownErrorCode;
+
+impl fmt::Display for UnknownErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown error code")
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = UnknownErrorCode;
+
+    /// Parses codes like `E0004` or `W0001`, also accepting lowercase `e0004`.
+    /// A bare number like `0004` is taken to be an error code.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let matches = |code: &ErrorCode| {
+            if s.starts_with(|c: char| c.is_ascii_digit()) {
+                code.as_str() == format!("E{}", s)
+            } else {
+                code.as_str().eq_ignore_ascii_case(s)
+            }
+        };
+
+        Self::ALL
+            .iter()
+            .copied()
+            .find(matches)
+            .ok_or(UnknownErrorCode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_parse_in_any_case() {
+        assert_eq!("E0004".parse(), Ok(ErrorCode::ExpectedToken));
+        assert_eq!("e0004".parse(), Ok(ErrorCode::ExpectedToken));
+        assert_eq!(" E0012\n".parse(), Ok(ErrorCode::UndefinedName));
+        assert_eq!("w0001".parse(), Ok(ErrorCode::EmptyWhereClause));
+    }
+
+    #[test]
+    fn bare_numbers_are_error_codes() {
+        assert_eq!("0004".parse(), Ok(ErrorCode::ExpectedToken));
+        // W0001 can only be written with its letter
+        assert_eq!("0001".parse(), Ok(ErrorCode::UnexpectedCharacter));
+    }
+
+    #[test]
+    fn unknown_codes() {
+        for code in ["E9999", "W0004", "X0001", "4", "", "E"] {
+            assert_eq!(
+                code.parse::<ErrorCode>(),
+                Err(UnknownErrorCode),
+                "{:?}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn every_code_is_explained() {
+        for code in ErrorCode::ALL {
+            assert_eq!(code.as_str().parse(), Ok(*code));
+            assert!(
+                code.explain().contains("Erroneous code example"),
+                "{}",
+                code
+            );
+        }
+
+        assert!(ErrorCode::ExpectedToken
+            .explain()
+            .starts_with("A specific token was expected"));
+    }
+}
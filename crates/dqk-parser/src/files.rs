@@ -0,0 +1,249 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crate::{Diagnostics, FileId, Interner, Node, Parser, Program, SourceMap, Symbol, VisitMut};
+
+/// Extension of dqk source files.
+pub const EXTENSION: &str = "dqk";
+
+/// Finds every `.dqk` file in `dir` and its subdirectories, sorted by path.
+pub fn find_files(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    fn visit(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+
+            if path.is_dir() {
+                visit(&path, files)?;
+            } else if path.extension().is_some_and(|ext| ext == EXTENSION) {
+                files.push(path);
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    visit(dir.as_ref(), &mut files)?;
+    files.sort();
+
+    Ok(files)
+}
+
+/// Loads and parses every `.dqk` file in `dir` in parallel.
+///
+/// See [`parse_sources`].
+pub fn parse_files(
+    dir: impl AsRef<Path>,
+    source_map: &mut SourceMap,
+    interner: &Interner,
+    diagnostics: &mut Diagnostics,
+) -> io::Result<Vec<Program>> {
+    let files = find_files(dir)?
+        .into_iter()
+        .map(|path| source_map.load(path))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok(parse_sources(&files, source_map, interner, diagnostics))
+}
+
+/// Parses `files` in parallel, interning their strings into `interner`.
+///
+/// The programs are returned in the order of `files`, and the diagnostics of
+/// each file are emitted in that order too. Every file is parsed into its own
+/// interner, whose strings are added to `interner` file by file when the
+/// results are merged, so the numbering of the [`Symbol`]s doesn't depend on
+/// how the work was scheduled either.
+pub fn parse_sources(
+    files: &[FileId],
+    source_map: &SourceMap,
+    interner: &Interner,
+    diagnostics: &mut Diagnostics,
+) -> Vec<Program> {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = files.len().div_ceil(threads).max(1);
+
+    let chunks: Vec<Vec<(Option<Program>, Interner, Diagnostics)>> = thread::scope(|scope| {
+        let handles: Vec<_> = (files.chunks(chunk_size))
+            .map(|chunk| {
+                // every file gets a fresh collector with the same lint levels
+                let template = diagnostics.without_diagnostics();
+
+                scope.spawn(move || {
+                    (chunk.iter())
+                        .map(|&file| {
+                            let mut diagnostics = template.clone();
+                            let interner = Interner::new();
+                            let mut parser = Parser::new(
+                                source_map.source(file),
+                                file,
+                                &interner,
+                                &mut diagnostics,
+                            );
+
+                            let program = match parser.parse::<Program>() {
+                                Ok(program) => Some(program),
+                                Err(error) => {
+                                    diagnostics.emit(error);
+                                    None
+                                }
+                            };
+
+                            (program, interner, diagnostics)
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+
+        (handles.into_iter())
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    let mut programs = Vec::with_capacity(files.len());
+
+    for (program, file_interner, file_diagnostics) in chunks.into_iter().flatten() {
+        diagnostics.extend(file_diagnostics);
+
+        if let Some(mut program) = program {
+            let symbols: Vec<_> = (file_interner.iter())
+                .map(|(_, string)| interner.intern(string))
+                .collect();
+
+            program.accept_mut(&mut Remap(&symbols));
+            programs.push(program);
+        }
+    }
+
+    programs
+}
+
+/// Replaces the symbols of a file's interner by those of the shared one, by
+/// their index.
+struct Remap<'a>(&'a [Symbol]);
+
+impl VisitMut for Remap<'_> {
+    fn visit_symbol_mut(&mut self, symbol: &mut Symbol) {
+        *symbol = self.0[symbol.index()];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Spanned, Stmt};
+
+    #[test]
+    fn parse_sources_keeps_file_order() {
+        let mut source_map = SourceMap::new();
+
+        let files: Vec<_> = (0..20)
+            .map(|i| {
+                source_map.add(
+                    format!("{}.dqk", i),
+                    format!("x{} := {}\nshared := 0\n", i, i),
+                )
+            })
+            .collect();
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+
+        let programs = parse_sources(&files, &source_map, &interner, &mut diagnostics);
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(programs.len(), files.len());
+
+        let mut shared = Vec::new();
+
+        for (i, (program, file)) in programs.iter().zip(&files).enumerate() {
+            assert_eq!(program.span().file(), *file);
+
+            match (&program.stmts[0], &program.stmts[1]) {
+                (Stmt::AssignNew(first), Stmt::AssignNew(second)) => {
+                    assert_eq!(interner.resolve(first.ident.value()), format!("x{}", i));
+                    shared.push(second.ident.value());
+                }
+                stmts => panic!("expected declarations, found {:?}", stmts),
+            }
+        }
+
+        assert!(shared.windows(2).all(|pair| pair[0] == pair[1]));
+    }
+
+    #[test]
+    fn symbols_are_numbered_in_file_order() {
+        let mut source_map = SourceMap::new();
+
+        let files: Vec<_> = (0..20)
+            .map(|i| {
+                source_map.add(
+                    format!("{}.dqk", i),
+                    format!("shared := 'a{{x{}}}b'\nx{} := shared\n", i, i),
+                )
+            })
+            .collect();
+
+        let parse = || {
+            let interner = Interner::new();
+            let programs = parse_sources(&files, &source_map, &interner, &mut Diagnostics::new());
+
+            let json = serde_json::to_string(&programs).unwrap();
+            let symbols: Vec<_> = interner.iter().map(|(_, s)| s.to_string()).collect();
+
+            (json, symbols)
+        };
+
+        let (json, symbols) = parse();
+
+        // strings are numbered as the files are read one after the other
+        assert_eq!(symbols[..5], ["shared", "a", "x0", "b", "x1"]);
+
+        for _ in 0..10 {
+            assert_eq!(parse(), (json.clone(), symbols.clone()));
+        }
+    }
+
+    #[test]
+    fn every_symbol_is_remapped() {
+        let mut source_map = SourceMap::new();
+
+        // every file interns its own strings first, so no index lines up with
+        // the shared interner by chance
+        let files: Vec<_> = (0..4)
+            .map(|i| {
+                source_map.add(
+                    format!("{}.dqk", i),
+                    format!(
+                        "ln f{i}<message_sent>(Message) where author.id == 'u{i}' {{\n\
+                         \tchannel.send 'a{i} {{author.name}} b{i} {{x{i}}} c{i}', [x{i}]\n}}\n",
+                        i = i
+                    ),
+                )
+            })
+            .collect();
+
+        let parallel = Interner::new();
+        let programs = parse_sources(&files, &source_map, &parallel, &mut Diagnostics::new());
+
+        // the same files parsed one after the other into a single interner
+        let sequential = Interner::new();
+        let expected: Vec<Program> = (files.iter())
+            .map(|&file| {
+                let mut diagnostics = Diagnostics::new();
+                Parser::new(source_map.source(file), file, &sequential, &mut diagnostics)
+                    .parse()
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(
+            serde_json::to_string(&programs).unwrap(),
+            serde_json::to_string(&expected).unwrap()
+        );
+        assert!(parallel.iter().eq(sequential.iter()));
+    }
+}
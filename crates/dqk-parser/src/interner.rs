@@ -0,0 +1,210 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    fmt,
+    hash::BuildHasher,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Handle to a string stored in an [`Interner`].
+///
+/// Symbols are only meaningful for the interner that created them, use
+/// [`Interner::resolve`] to get the string back.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub const fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Symbol({})", self.0)
+    }
+}
+
+/// Size of the first chunk of the arena, every following chunk doubles in size.
+const FIRST_CHUNK: usize = 64;
+/// Enough chunks to hold every `u32` symbol.
+const CHUNKS: usize = 27;
+const SHARDS: usize = 16;
+
+type Chunk = Box<[OnceLock<Box<str>>]>;
+
+/// Deduplicates identifiers and string literals.
+///
+/// Interned strings live in an append-only arena of chunks, which is freed with
+/// the interner, so reloading scripts with a fresh interner doesn't leak. The
+/// interner is shared by reference and can be used by several [`Parser`]s on
+/// different threads at once, lookups are sharded by hash to keep contention
+/// low.
+///
+/// [`Parser`]: crate::Parser
+pub struct Interner {
+    chunks: [OnceLock<Chunk>; CHUNKS],
+    len: AtomicUsize,
+    /// Symbols by the hash of their string.
+    shards: [Mutex<HashMap<u64, Vec<Symbol>>>; SHARDS],
+    hasher: RandomState,
+}
+
+impl Default for Interner {
+    fn default() -> Self {
+        Self {
+            chunks: std::array::from_fn(|_| OnceLock::new()),
+            len: AtomicUsize::new(0),
+            shards: std::array::from_fn(|_| Mutex::default()),
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl fmt::Debug for Interner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// Chunk and offset in the chunk of the symbol with `index`.
+fn location(index: usize) -> (usize, usize) {
+    let chunk = (usize::BITS - 1 - (index / FIRST_CHUNK + 1).leading_zeros()) as usize;
+    let offset = index - FIRST_CHUNK * ((1 << chunk) - 1);
+
+    (chunk, offset)
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intern(&self, string: impl AsRef<str>) -> Symbol {
+        let string = string.as_ref();
+        let hash = self.hasher.hash_one(string);
+
+        let mut shard = self.shards[hash as usize % SHARDS].lock().unwrap();
+        let bucket = shard.entry(hash).or_default();
+
+        if let Some(symbol) = self.find(bucket, string) {
+            return symbol;
+        }
+
+        let index = self.len.fetch_add(1, Ordering::Relaxed);
+        let (chunk, offset) = location(index);
+
+        let chunk = self.chunks[chunk].get_or_init(|| {
+            let size = FIRST_CHUNK << chunk;
+            (0..size).map(|_| OnceLock::new()).collect()
+        });
+
+        let _ = chunk[offset].set(Box::from(string));
+
+        let symbol = Symbol(index as u32);
+        bucket.push(symbol);
+
+        symbol
+    }
+
+    /// Finds the symbol of `string` if it has been interned.
+    pub fn get(&self, string: impl AsRef<str>) -> Option<Symbol> {
+        let string = string.as_ref();
+        let hash = self.hasher.hash_one(string);
+
+        let shard = self.shards[hash as usize % SHARDS].lock().unwrap();
+
+        self.find(shard.get(&hash)?, string)
+    }
+
+    fn find(&self, bucket: &[Symbol], string: &str) -> Option<Symbol> {
+        bucket
+            .iter()
+            .copied()
+            .find(|symbol| self.resolve(*symbol) == string)
+    }
+
+    fn try_resolve(&self, symbol: Symbol) -> Option<&str> {
+        let (chunk, offset) = location(symbol.index());
+
+        Some(self.chunks[chunk].get()?[offset].get()?)
+    }
+
+    /// # Panics
+    /// * if `symbol` was not created by this interner.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        self.try_resolve(symbol)
+            .expect("symbol was not created by this interner")
+    }
+
+    /// Number of interned strings, including ones another thread is
+    /// interning right now.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Symbol, &str)> {
+        (0..self.len()).filter_map(|index| {
+            let symbol = Symbol(index as u32);
+            Some((symbol, self.try_resolve(symbol)?))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interning_deduplicates() {
+        let interner = Interner::new();
+
+        let ping = interner.intern("ping");
+        let pong = interner.intern("pong");
+
+        assert_eq!(interner.intern("ping"), ping);
+        assert_ne!(ping, pong);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.resolve(pong), "pong");
+        assert_eq!(interner.get("pong"), Some(pong));
+        assert_eq!(interner.get("pang"), None);
+    }
+
+    #[test]
+    fn chunk_locations_are_contiguous() {
+        assert_eq!(location(0), (0, 0));
+        assert_eq!(location(FIRST_CHUNK - 1), (0, FIRST_CHUNK - 1));
+        assert_eq!(location(FIRST_CHUNK), (1, 0));
+        assert_eq!(location(3 * FIRST_CHUNK), (2, 0));
+        assert_eq!(location(u32::MAX as usize).0, CHUNKS - 1);
+    }
+
+    #[test]
+    fn interning_from_many_threads() {
+        let interner = Interner::new();
+        let names: Vec<String> = (0..500).map(|i| format!("name_{}", i)).collect();
+
+        let symbols: Vec<Vec<Symbol>> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| names.iter().map(|name| interner.intern(name)).collect()))
+                .collect();
+
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+
+        assert!(symbols.windows(2).all(|pair| pair[0] == pair[1]));
+        assert_eq!(interner.len(), names.len());
+
+        for (name, symbol) in names.iter().zip(&symbols[0]) {
+            assert_eq!(interner.resolve(*symbol), name);
+        }
+    }
+}
@@ -0,0 +1,460 @@
+use crate::{
+    Error, ErrorCode, FileId, Float, Integer, IntegerKind, Interner, Keyword, Punct, Result, Span,
+    Token, TokenKind,
+};
+
+/// Splits source text into [`Token`]s.
+///
+/// Whitespace and `#` comments are skipped, line breaks are [`TokenKind::Eol`]
+/// tokens. As an [`Iterator`] the lexer yields every token up to, but not
+/// including, [`TokenKind::Eof`]. After an error it continues with the next
+/// token, so all errors of a file can be reported.
+///
+/// A string with interpolations like `'a{x}b{y}c'` is split into a
+/// [`TokenKind::StringStart`], the tokens of each expression separated by
+/// [`TokenKind::StringMiddle`], and a [`TokenKind::StringEnd`].
+pub struct Lexer<'a> {
+    interner: &'a Interner,
+    src: &'a str,
+    file: FileId,
+    /// Byte offset of the next char.
+    start: usize,
+    /// Number of unclosed `{` in each open interpolation, innermost last. A `}`
+    /// at depth 0 continues the string.
+    interpolations: Vec<usize>,
+    done: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(src: &'a str, file: FileId, interner: &'a Interner) -> Self {
+        Self {
+            interner,
+            src,
+            file,
+            start: 0,
+            interpolations: Vec::new(),
+            done: false,
+        }
+    }
+
+    pub fn interner(&self) -> &'a Interner {
+        self.interner
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.src
+    }
+
+    pub fn file(&self) -> FileId {
+        self.file
+    }
+
+    /// Empty span at the position of the next char.
+    pub fn span(&self) -> Span {
+        Span::new(self.file, self.start, 0)
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.src[self.start..].chars().nth(n)
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.peek_char()?;
+        self.start += ch.len_utf8();
+
+        Some(ch)
+    }
+
+    /// Skips whitespace, except line breaks, and `#` comments.
+    fn skip_whitespace(&mut self) {
+        while let Some(ch) = self.peek_char() {
+            if ch == '#' {
+                while self.peek_char().is_some_and(|c| c != '\n') {
+                    self.next_char();
+                }
+            } else if ch.is_whitespace() && ch != '\n' {
+                self.next_char();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Lexes an integer like `12`, `0b1010` or `0xff`, or a float like `1.3`.
+    fn lex_number(&mut self) -> Option<Result<TokenKind>> {
+        if !self.peek_char()?.is_ascii_digit() {
+            return None;
+        }
+
+        let start = self.span();
+
+        let kind = match (self.peek_char(), self.peek_nth(1)) {
+            (Some('0'), Some('b')) => IntegerKind::Binary,
+            (Some('0'), Some('x')) => IntegerKind::Hex,
+            _ => IntegerKind::Decimal,
+        };
+
+        if kind != IntegerKind::Decimal {
+            self.next_char();
+            self.next_char();
+        }
+
+        let mut value = Some(0i64);
+        let mut digits = 0;
+
+        while let Some(digit) = self.peek_char().and_then(|ch| ch.to_digit(kind.radix())) {
+            value = value
+                .and_then(|value| value.checked_mul(kind.radix() as i64))
+                .and_then(|value| value.checked_add(digit as i64));
+            digits += 1;
+
+            self.next_char();
+        }
+
+        if digits == 0 {
+            let error = Error::new(ErrorCode::MalformedInteger, "malformed integer")
+                .with_hint("expected integer after radix", self.span());
+
+            return Some(Err(error));
+        }
+
+        // a period is only part of the number if a digit follows, `1.len` is a member
+        let fraction =
+            self.peek_char() == Some('.') && self.peek_nth(1).is_some_and(|ch| ch.is_ascii_digit());
+
+        if kind == IntegerKind::Decimal && fraction {
+            self.next_char();
+
+            while self.peek_char().is_some_and(|ch| ch.is_ascii_digit()) {
+                self.next_char();
+            }
+
+            let text = &self.src[start.start()..self.start];
+            let value = text.parse().expect("float literals are valid f64s");
+
+            return Some(Ok(TokenKind::Float(Float::new(value))));
+        }
+
+        match value {
+            Some(value) => Some(Ok(TokenKind::Integer(Integer::new(value, kind)))),
+            None => {
+                let error = Error::new(ErrorCode::MalformedInteger, "malformed integer")
+                    .with_hint("integer is too large", start | self.span());
+
+                Some(Err(error))
+            }
+        }
+    }
+
+    fn lex_ident(&mut self) -> Option<&'a str> {
+        let start = self.start;
+
+        while let Some(ch) = self.peek_char() {
+            if ch == '_' || ch.is_alphabetic() || (self.start > start && ch.is_numeric()) {
+                self.next_char();
+            } else {
+                break;
+            }
+        }
+
+        if self.start == start {
+            None
+        } else {
+            Some(&self.src[start..self.start])
+        }
+    }
+
+    /// Lexes a string, or the part of a string after an interpolation if the
+    /// next char is the `}` closing it.
+    ///
+    /// A doubled `''`, `{{` or `}}` is an escaped quote or brace.
+    fn lex_string(&mut self) -> Option<Result<TokenKind>> {
+        let resumed = match self.peek_char() {
+            Some('\'') => false,
+            Some('}') if self.interpolations.last() == Some(&0) => {
+                self.interpolations.pop();
+                true
+            }
+            _ => return None,
+        };
+
+        self.next_char();
+
+        let starts = self.span();
+        let mut value = String::new();
+
+        loop {
+            match self.next_char() {
+                Some(ch @ ('\'' | '{' | '}')) if self.peek_char() == Some(ch) => {
+                    self.next_char();
+                    value.push(ch);
+                }
+                Some(end @ ('\'' | '{')) => {
+                    let string = self.interner.intern(&value);
+
+                    if end == '{' {
+                        self.interpolations.push(0);
+                    }
+
+                    break Some(Ok(match (resumed, end) {
+                        (false, '\'') => TokenKind::String(string),
+                        (false, _) => TokenKind::StringStart(string),
+                        (true, '\'') => TokenKind::StringEnd(string),
+                        (true, _) => TokenKind::StringMiddle(string),
+                    }));
+                }
+                Some(ch) => value.push(ch),
+                None => {
+                    break Some(Err(Error::new(
+                        ErrorCode::UnterminatedString,
+                        "unterminated string",
+                    )
+                    .with_hint("starting at", starts)))
+                }
+            }
+        }
+    }
+
+    fn lex_punct(&mut self) -> Option<Punct> {
+        macro_rules! punct {
+            ($($ch:literal => $(> $second:literal => $second_punct:expr,)* $punct:expr $(,)?,)*) => {
+                match self.peek_char() {
+					$(Some($ch) => {
+                        self.next_char();
+
+						match self.peek_char() {
+                            $(Some($second) => {
+                                self.next_char();
+
+                                Some($second_punct)
+                            })*
+                            _ => Some($punct)
+                        }
+					})*
+                    _ => None,
+                }
+            };
+        }
+
+        use Punct::*;
+
+        punct! {
+            '(' => OpenParen,
+            '{' => OpenBrace,
+            '[' => OpenBracket,
+            ')' => CloseParen,
+            '}' => CloseBrace,
+            ']' => CloseBracket,
+            ':' =>
+                > '=' => ColonEqual,
+                Colon,
+            ';' => SemiColon,
+            ',' => Comma,
+            '.' => Period,
+            '+' => Plus,
+            '-' => Minus,
+            '*' => Asterisk,
+            '/' => Slash,
+            '=' =>
+                > '=' => EqualEqual,
+                Equal,
+            '>' =>
+                > '=' => GtEqual,
+                Gt,
+            '<' =>
+                > '=' => LtEqual,
+                Lt,
+        }
+    }
+
+    /// Lexes the next token, [`TokenKind::Eof`] at the end of the source.
+    pub fn next_token(&mut self) -> Result<Token> {
+        self.skip_whitespace();
+
+        if self.peek_char().is_none() {
+            return Ok(Token::new(TokenKind::Eof, self.span()));
+        }
+
+        let start = self.span();
+
+        if self.peek_char() == Some('\n') {
+            self.next_char();
+            return Ok(Token::new(TokenKind::Eol, start | self.span()));
+        }
+
+        if let Some(number) = self.lex_number() {
+            return Ok(Token::new(number?, start | self.span()));
+        }
+
+        if let Some(ident) = self.lex_ident() {
+            let kind = match Keyword::from_ident(ident) {
+                Some(keyword) => TokenKind::Keyword(keyword),
+                None => TokenKind::Ident(self.interner.intern(ident)),
+            };
+            return Ok(Token::new(kind, start | self.span()));
+        }
+
+        if let Some(string) = self.lex_string() {
+            return Ok(Token::new(string?, start | self.span()));
+        }
+
+        if let Some(punct) = self.lex_punct() {
+            if let Some(depth) = self.interpolations.last_mut() {
+                match punct {
+                    Punct::OpenBrace => *depth += 1,
+                    Punct::CloseBrace => *depth -= 1,
+                    _ => {}
+                }
+            }
+
+            return Ok(Token::new(TokenKind::Punct(punct), start | self.span()));
+        }
+
+        let msg = format!("found character '{}'", self.next_char().unwrap());
+        Err(
+            Error::new(ErrorCode::UnexpectedCharacter, "unexpected character")
+                .with_hint(msg, start | self.span()),
+        )
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.next_token() {
+            Ok(token) if token.kind() == TokenKind::Eof => {
+                self.done = true;
+                None
+            }
+            result => Some(result),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SourceMap;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+
+        Lexer::new(src, file, &interner)
+            .map(|token| token.unwrap().kind())
+            .collect()
+    }
+
+    #[test]
+    fn numbers() {
+        let int = |value, kind| TokenKind::Integer(Integer::new(value, kind));
+
+        assert_eq!(
+            kinds("12 0b101 0xff 1.3 0.25"),
+            [
+                int(12, IntegerKind::Decimal),
+                int(5, IntegerKind::Binary),
+                int(255, IntegerKind::Hex),
+                TokenKind::Float(Float::new(1.3)),
+                TokenKind::Float(Float::new(0.25)),
+            ]
+        );
+    }
+
+    #[test]
+    fn period_after_integer_is_member_access() {
+        let kinds = kinds("1.len");
+
+        assert_eq!(
+            kinds[0],
+            TokenKind::Integer(Integer::new(1, IntegerKind::Decimal))
+        );
+        assert_eq!(kinds[1], TokenKind::Punct(Punct::Period));
+    }
+
+    #[test]
+    fn interpolated_strings() {
+        let mut source_map = SourceMap::new();
+        let src = "'a{x}b{ {} }c' ''";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let kinds: Vec<_> = Lexer::new(src, file, &interner)
+            .map(|token| token.unwrap().kind().describe(&interner))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [
+                "StringStart(\"a\")",
+                "Ident(\"x\")",
+                "StringMiddle(\"b\")",
+                "Punct(OpenBrace)",
+                "Punct(CloseBrace)",
+                "StringEnd(\"c\")",
+                "String(\"\")",
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_quotes_and_braces() {
+        let mut source_map = SourceMap::new();
+        let src = "'it''s' '{{x}}' 'a{{{b}''}}}' '''' ''";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let kinds: Vec<_> = Lexer::new(src, file, &interner)
+            .map(|token| token.unwrap().kind().describe(&interner))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [
+                "String(\"it's\")",
+                "String(\"{x}\")",
+                "StringStart(\"a{\")",
+                "Ident(\"b\")",
+                "StringEnd(\"'}}\")",
+                "String(\"'\")",
+                "String(\"\")",
+            ]
+        );
+    }
+
+    #[test]
+    fn continues_after_errors() {
+        let mut source_map = SourceMap::new();
+        let src = "x $ 0x 99999999999999999999\ny";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let tokens: Vec<_> = Lexer::new(src, file, &interner).collect();
+
+        let codes: Vec<_> = (tokens.iter())
+            .filter_map(|token| token.as_ref().err())
+            .map(|error| error.code())
+            .collect();
+
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(
+            codes,
+            [
+                ErrorCode::UnexpectedCharacter,
+                ErrorCode::MalformedInteger,
+                ErrorCode::MalformedInteger,
+            ]
+        );
+    }
+}
@@ -1,17 +1,31 @@
-#![deny(unsafe_op_in_unsafe_fn)]
+#![forbid(unsafe_code)]
 
 mod ast;
-mod error;
+mod cst;
+mod diagnostic;
+mod error_code;
+mod files;
+mod interner;
+mod lexer;
 mod parse;
 mod parser;
+mod source_map;
 mod span;
-mod string_allocator;
+mod suggest;
 mod token;
+mod visit;
 
 pub use ast::*;
-pub use error::*;
+pub use cst::*;
+pub use diagnostic::*;
+pub use error_code::*;
+pub use files::*;
+pub use interner::*;
+pub use lexer::*;
 pub use parse::*;
 pub use parser::*;
+pub use source_map::*;
 pub use span::*;
-pub use string_allocator::*;
+pub use suggest::*;
 pub use token::*;
+pub use visit::*;
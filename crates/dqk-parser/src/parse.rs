@@ -1,5 +1,19 @@
-use crate::{Error, FromToken, Parser, Result, SpannedOption, Symbol, TokenKind, Tupled};
+use crate::{Parser, Result};
 
+pub use dqk_macro::Parse;
+
+/// Parses a node from the tokens of a [`Parser`].
+///
+/// Structs whose fields are parsed one after another can derive this, the
+/// derive accepts these field attributes:
+///
+/// - `#[parse(skip_eol)]` skips line breaks before the field.
+/// - `#[parse(terminated = Punct::CloseParen)]` parses a
+///   [`Punctuated`](crate::Punctuated) up to, but not including, the token.
+///   Add `trailing = Trailing::Forbidden` to change the
+///   [`Trailing`](crate::Trailing) policy, which is `Allowed` by default.
+/// - `#[parse(if_next = Keyword::Where)]` parses a
+///   [`SpannedOption`](crate::SpannedOption) only if the token is next.
 pub trait Parse: Sized {
     fn parse(parser: &mut Parser) -> Result<Self>;
 }
@@ -1,264 +1,160 @@
-use std::{iter::Peekable, path::Path, str::Chars};
+use std::mem;
 
 use crate::{
-    Error, Integer, IntegerKind, Keyword, Parse, Result, Span, StringAllocator, Symbol, Token,
-    TokenKind,
+    Diagnostic, Diagnostics, Error, ErrorCode, FileId, Interner, Lexer, Parse, Punct, Result, Span,
+    Token, TokenKind,
 };
 
+/// Position in the token stream of a [`Parser`], see [`Parser::checkpoint`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
 pub struct Parser<'a> {
-    string_allocator: &'a mut StringAllocator,
-    file_path: Option<&'static Path>,
-    start: usize,
-    chars: Peekable<Chars<'a>>,
-    next_token: Option<Token>,
+    lexer: Lexer<'a>,
+    diagnostics: &'a mut Diagnostics,
+    /// Every token lexed so far, in order.
+    tokens: Vec<Token>,
+    /// Index in `tokens` of the next token.
+    position: usize,
+    /// The end of the file, once the lexer has reached it.
+    eof: Option<Token>,
+    /// Whether line breaks are skipped, see [`Parser::ignoring_eol`].
+    ignore_eol: bool,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(
         src: &'a str,
-        file_path: Option<&'static Path>,
-        string_allocator: &'a mut StringAllocator,
+        file: FileId,
+        interner: &'a Interner,
+        diagnostics: &'a mut Diagnostics,
     ) -> Self {
         Self {
-            string_allocator,
-            file_path,
-            start: 0,
-            chars: src.chars().peekable(),
-            next_token: None,
+            lexer: Lexer::new(src, file, interner),
+            diagnostics,
+            tokens: Vec::new(),
+            position: 0,
+            eof: None,
+            ignore_eol: false,
         }
     }
 
-    pub fn span(&self) -> Span {
-        Span::new(self.file_path, self.start, 0)
+    pub fn interner(&self) -> &'a Interner {
+        self.lexer.interner()
     }
 
-    pub fn next_char(&mut self) -> Option<char> {
-        let ch = self.chars.next();
-
-        if ch.is_some() {
-            self.start += 1;
-        }
+    pub fn source(&self) -> &'a str {
+        self.lexer.source()
+    }
 
-        ch
+    pub fn file(&self) -> FileId {
+        self.lexer.file()
     }
 
-    pub fn peek_char(&mut self) -> Option<char> {
-        self.chars.peek().cloned()
+    /// Every token lexed so far, including peeked ones, without the
+    /// whitespace and comments between them.
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
     }
 
-    fn skip_whitespace(&mut self) {
-        while self
-            .peek_char()
-            .map_or(false, |c| char::is_whitespace(c) && c != '\n')
-        {
-            self.next_char();
+    /// Empty span at the end of the last consumed token.
+    pub fn span(&self) -> Span {
+        match self.position.checked_sub(1) {
+            Some(last) => {
+                let span = self.tokens[last].span();
+                Span::new(span.file(), span.end(), 0)
+            }
+            None => Span::new(self.file(), 0, 0),
         }
     }
 
-    fn parse_integer(&mut self) -> Option<Result<Integer>> {
-        let mut value = 0i64;
-        let mut digits = 0;
-        let mut kind = IntegerKind::Decimal;
-
-        loop {
-            if let Some(ch) = self.peek_char() {
-                if let Some(digit) = ch.to_digit(kind.radix()) {
-                    value += digit as i64 * (kind.radix() as i64).pow(digits);
-                    digits += 1;
-
-                    self.next_char();
-                    continue;
-                } else if ch == 'b' && value == 0 && digits == 1 && kind == IntegerKind::Decimal {
-                    digits = 0;
-                    kind = IntegerKind::Binary;
-
-                    self.next_char();
-                    continue;
-                } else if ch == 'x' && value == 0 && digits == 1 && kind == IntegerKind::Decimal {
-                    digits = 0;
-                    kind = IntegerKind::Hex;
-
-                    self.next_char();
-                    continue;
-                }
+    /// Lexes tokens until there is one at `index`, or the file ends.
+    ///
+    /// Lexer errors are emitted right away and the broken text is skipped, so
+    /// they are reported once no matter how often the parser rewinds.
+    fn fill(&mut self, index: usize) {
+        while self.eof.is_none() && self.tokens.len() <= index {
+            match self.lexer.next_token() {
+                Ok(token) if token.kind() == TokenKind::Eof => self.eof = Some(token),
+                Ok(token) => self.tokens.push(token),
+                Err(error) => self.emit(error),
             }
+        }
+    }
 
-            if digits == 0 && kind != IntegerKind::Decimal {
-                let error = Error::new("malformed integer")
-                    .with_hint("expected integer after radix", self.span());
+    /// Looks at the token `n` tokens ahead without consuming anything,
+    /// `peek_nth(0)` is the next token.
+    pub fn peek_nth(&mut self, n: usize) -> Result<Token> {
+        let index = self.index_of(n);
 
-                break Some(Err(error));
-            } else if digits > 0 {
-                break Some(Ok(Integer::new(value, kind)));
-            } else {
-                break None;
-            }
+        match self.tokens.get(index) {
+            Some(token) => Ok(*token),
+            None => Ok(self.eof.unwrap()),
         }
     }
 
-    fn parse_ident(&mut self) -> Option<&'static str> {
-        let mut ident = String::new();
+    /// Index in `tokens` of the token `n` tokens ahead, past the line breaks
+    /// that are ignored. At the end of the file this is `tokens.len()`.
+    fn index_of(&mut self, mut n: usize) -> usize {
+        let mut index = self.position;
 
         loop {
-            if let Some(ch) = self.peek_char() {
-                if ch == '_' || ch.is_alphabetic() || (!ident.is_empty() && ch.is_numeric()) {
-                    ident.push(ch);
-                    self.next_char();
+            self.fill(index);
 
-                    continue;
-                }
+            match self.tokens.get(index) {
+                None => break index,
+                Some(token) if self.ignore_eol && token.kind() == TokenKind::Eol => {}
+                Some(_) if n == 0 => break index,
+                Some(_) => n -= 1,
             }
 
-            if ident.is_empty() {
-                break None;
-            } else {
-                break Some(self.string_allocator.get(&ident));
-            }
+            index += 1;
         }
     }
 
-    fn parse_string(&mut self) -> Option<Result<&'static str>> {
-        if self.peek_char() != Some('\'') {
-            return None;
-        }
-
-        self.next_char();
+    pub fn peek_token(&mut self) -> Result<Token> {
+        self.peek_nth(0)
+    }
 
-        let starts = self.span();
-        let mut string = String::new();
+    /// Consumes the next token, at the end of the file this keeps returning
+    /// [`TokenKind::Eof`].
+    pub fn next_token(&mut self) -> Result<Token> {
+        let index = self.index_of(0);
 
-        loop {
-            if let Some(ch) = self.next_char() {
-                if ch == '\'' {
-                    break Some(Ok(self.string_allocator.get(&string)));
-                } else {
-                    string.push(ch);
-                }
-            } else {
-                break Some(Err(
-                    Error::new("unterminated string").with_hint("starting at", starts)
-                ));
+        match self.tokens.get(index) {
+            Some(token) => {
+                self.position = index + 1;
+                Ok(*token)
+            }
+            None => {
+                self.position = index;
+                Ok(self.eof.unwrap())
             }
         }
     }
 
-    fn parse_symbol(&mut self) -> Option<Symbol> {
-        macro_rules! symbol {
-            ($($ch:literal => $(> $second:literal => $second_symbol:expr,)* $symbol:expr $(,)?,)*) => {
-                match self.peek_char() {
-					$(Some($ch) => {
-                        self.next_char();
-
-						match self.peek_char() {
-                            $(Some($second) => {
-                                self.next_char();
-
-                                Some($second_symbol)
-                            })*
-                            _ => Some($symbol)
-                        }
-					})*
-                    _ => None,
-                }
-            };
-        }
+    /// Runs `f` with line breaks ignored or not, so `peek` and `next` skip
+    /// them, like inside `( .. )`, or see them again, like in a `{ .. }` block
+    /// inside those.
+    pub fn ignoring_eol<T>(&mut self, ignore: bool, f: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = mem::replace(&mut self.ignore_eol, ignore);
+        let result = f(self);
+        self.ignore_eol = outer;
 
-        use Symbol::*;
-
-        symbol! {
-            '(' => OpenParen,
-            '{' => OpenBrace,
-            '[' => OpenBracket,
-            ')' => CloseParen,
-            '}' => CloseBrace,
-            ']' => CloseBracket,
-            ':' =>
-                > '=' => ColonEqual,
-                Colon,
-            ';' => SemiColon,
-            ',' => Comma,
-            '.' => Period,
-            '+' => Plus,
-            '-' => Minus,
-            '*' => Asterisk,
-            '/' => Slash,
-            '=' =>
-                > '=' => EqualEqual,
-                Equal,
-            '>' =>
-                > '=' => GtEqual,
-                Gt,
-            '<' =>
-                > '=' => LtEqual,
-                Lt,
-        }
+        result
     }
 
-    fn parse_token(&mut self) -> Result<Token> {
-        self.skip_whitespace();
-
-        if self.peek_char().is_none() {
-            return Ok(Token::new(TokenKind::Eof, self.span()));
-        }
-
-        if self.peek_char() == Some('\n') {
-            self.next_char();
-            return Ok(Token::new(TokenKind::Eol, self.span()));
-        }
-
-        let start = self.span();
-
-        if let Some(integer) = self.parse_integer() {
-            return Ok(Token::new(
-                TokenKind::Integer(integer?),
-                start | self.span(),
-            ));
-        }
-
-        if let Some(ident) = self.parse_ident() {
-            let kind = match ident {
-                "event" => TokenKind::Keyword(Keyword::Event),
-                "query" => TokenKind::Keyword(Keyword::Query),
-                "where" => TokenKind::Keyword(Keyword::Where),
-                "ln" => TokenKind::Keyword(Keyword::Ln),
-                "fn" => TokenKind::Keyword(Keyword::Fn),
-                "if" => TokenKind::Keyword(Keyword::If),
-                "for" => TokenKind::Keyword(Keyword::For),
-                "else" => TokenKind::Keyword(Keyword::Else),
-                "return" => TokenKind::Keyword(Keyword::Return),
-                _ => TokenKind::Ident(ident),
-            };
-            return Ok(Token::new(kind, start | self.span()));
-        }
-
-        if let Some(string) = self.parse_string() {
-            return Ok(Token::new(TokenKind::String(string?), start | self.span()));
-        }
-
-        if let Some(symbol) = self.parse_symbol() {
-            return Ok(Token::new(TokenKind::Symbol(symbol), start | self.span()));
-        }
-
-        let msg = format!("found character '{}'", self.next_char().unwrap());
-        Err(Error::new("unexpected character").with_hint(msg, self.span()))
+    /// Saves the position in the token stream, so the parser can go back to it
+    /// with [`Parser::rewind`] after trying one way to parse something.
+    ///
+    /// Only the position is restored, diagnostics emitted in between are kept,
+    /// so a speculative parse should return its errors instead of emitting them.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.position)
     }
 
-    pub fn next_token(&mut self) -> Result<Token> {
-        if let Some(token) = self.next_token.take() {
-            Ok(token)
-        } else {
-            self.parse_token()
-        }
-    }
-
-    pub fn peek_token(&mut self) -> Result<Token> {
-        if let Some(token) = self.next_token.clone().take() {
-            Ok(token)
-        } else {
-            let token = self.parse_token()?;
-            self.next_token = Some(token.clone());
-            Ok(token)
-        }
+    pub fn rewind(&mut self, checkpoint: Checkpoint) {
+        self.position = checkpoint.0;
     }
 
     pub fn parse<T: Parse>(&mut self) -> Result<T> {
@@ -271,8 +167,10 @@ impl<'a> Parser<'a> {
         if tok.kind() == kind {
             Ok(tok.span())
         } else {
-            Err(Error::new(format!("expected '{:?}'", kind))
-                .with_hint(format!("found '{:?}'", tok.kind()), tok.span()))
+            Err(
+                Error::new(ErrorCode::ExpectedToken, format!("expected '{:?}'", kind))
+                    .with_found_hint(tok, self.interner()),
+            )
         }
     }
 
@@ -281,8 +179,44 @@ impl<'a> Parser<'a> {
 
         match tok.kind() {
             TokenKind::Eol | TokenKind::Eof => Ok(tok.span()),
-            kind => Err(Error::new("expected 'end of line'")
-                .with_hint(format!("found '{:?}'", kind), tok.span())),
+            _ => Err(Error::new(ErrorCode::ExpectedEol, "expected 'end of line'")
+                .with_found_hint(tok, self.interner())),
+        }
+    }
+
+    /// Reports a non-fatal diagnostic, parsing continues.
+    pub fn emit(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.emit(diagnostic);
+    }
+
+    /// Skips the rest of the line after an error, so parsing can resume at the
+    /// next statement.
+    ///
+    /// Braces opened on the skipped part of the line are skipped up to their
+    /// matching close, even across lines. A `}` that closes the block around
+    /// the line is left for the block, like in `{ x := }`.
+    pub fn recover(&mut self) {
+        let mut depth = 0usize;
+
+        loop {
+            let tok = match self.peek_token() {
+                Ok(tok) => tok,
+                Err(error) => {
+                    self.emit(error);
+                    continue;
+                }
+            };
+
+            match tok.kind() {
+                TokenKind::Eof => break,
+                TokenKind::Eol if depth == 0 => break,
+                TokenKind::Punct(Punct::CloseBrace) if depth == 0 => break,
+                TokenKind::Punct(Punct::OpenBrace) => depth += 1,
+                TokenKind::Punct(Punct::CloseBrace) => depth -= 1,
+                _ => {}
+            }
+
+            let _ = self.next_token();
         }
     }
 
@@ -296,4 +230,201 @@ impl<'a> Parser<'a> {
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+    use crate::{AssignNewStmt, Expr, InterpolationNext, Program, SourceMap, Spanned, Stmt};
+
+    fn tokens(source_map: &mut SourceMap, src: &str) -> Vec<Token> {
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let mut tokens = Vec::new();
+
+        loop {
+            let tok = parser.next_token().unwrap();
+
+            if tok.kind() == TokenKind::Eof {
+                break tokens;
+            }
+
+            tokens.push(tok);
+        }
+    }
+
+    #[test]
+    fn spans_are_byte_offsets() {
+        let mut source_map = SourceMap::new();
+        let src = "名前 := 'héllo 👋' + x\n";
+
+        let tokens = tokens(&mut source_map, src);
+        let snippets: Vec<_> = (tokens.iter())
+            .map(|tok| source_map.snippet(tok.span()))
+            .collect();
+
+        assert_eq!(snippets, ["名前", ":=", "'héllo 👋'", "+", "x", "\n"]);
+        assert_eq!(tokens[1].span().start(), "名前 ".len());
+        assert_eq!(tokens.last().unwrap().span().end(), src.len());
+    }
+
+    #[test]
+    fn multibyte_identifier_declaration() {
+        let mut source_map = SourceMap::new();
+        let src = "café := 'ça va 🙂'\n";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let stmt = match parser.parse::<Stmt>().unwrap() {
+            Stmt::AssignNew(stmt) => stmt,
+            stmt => panic!("expected declaration, found {:?}", stmt),
+        };
+
+        assert_eq!(source_map.snippet(stmt.ident.span()), "café");
+        assert_eq!(source_map.snippet(stmt.expr.span()), "'ça va 🙂'");
+        assert_eq!(source_map.line_col(stmt.expr.span()).col, 9);
+        assert_eq!(source_map.line_col_utf16(stmt.expr.span()).col, 9);
+        assert_eq!(source_map.end_line_col(stmt.expr.span()).col, 18);
+    }
+
+    #[test]
+    fn lookahead_and_rewind() {
+        let mut source_map = SourceMap::new();
+        let src = "a := b + c\n";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        assert_eq!(
+            parser.peek_nth(1).unwrap().kind(),
+            TokenKind::Punct(Punct::ColonEqual)
+        );
+        assert_eq!(parser.peek_nth(10).unwrap().kind(), TokenKind::Eof);
+
+        let checkpoint = parser.checkpoint();
+        let first = parser.next_token().unwrap();
+        parser.next_token().unwrap();
+
+        parser.rewind(checkpoint);
+        assert_eq!(parser.next_token().unwrap(), first);
+        assert_eq!(parser.tokens().len(), 6);
+    }
+
+    #[test]
+    fn unclosed_delimiter_points_at_opener() {
+        let mut source_map = SourceMap::new();
+        let src = "x := (1 + 2\ny := 3\n";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let error = parser.parse::<Stmt>().unwrap_err();
+        let hint = (error.hints().iter())
+            .find(|hint| hint.msg() == "unclosed '(' opened here")
+            .expect("hint at the opening delimiter");
+
+        assert_eq!(source_map.snippet(hint.span()), "(");
+        assert_eq!(hint.span().start(), "x := ".len());
+    }
+
+    #[test]
+    fn misspelled_keyword_is_suggested() {
+        let mut source_map = SourceMap::new();
+        let src = "ln f(Message) wehre x {\n}\n";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let error = parser.parse::<Stmt>().unwrap_err();
+        let hint = (error.hints().iter())
+            .find(|hint| hint.msg() == "did you mean 'where'?")
+            .expect("suggestion for the misspelled keyword");
+
+        assert_eq!(error.code(), ErrorCode::ExpectedToken);
+        assert_eq!(source_map.snippet(hint.span()), "wehre");
+    }
+
+    #[test]
+    fn line_breaks_inside_delimiters() {
+        let mut source_map = SourceMap::new();
+        let src = "x := (1 +\n\t2) * f([\n\t3,\n\t4\n\t\t+ 5\n])\ny := 6\n";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<Program>().unwrap();
+        let snippets: Vec<_> = (program.stmts.iter())
+            .map(|stmt| source_map.snippet(stmt.span()))
+            .collect();
+
+        assert!(diagnostics.is_empty());
+        assert_eq!(
+            snippets,
+            [
+                "x := (1 +\n\t2) * f([\n\t3,\n\t4\n\t\t+ 5\n])\n",
+                "y := 6\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn recovery_keeps_the_closing_brace() {
+        let mut source_map = SourceMap::new();
+        let src = "ln f(Message) { x := }\ny := 1\n}\nz := 2\n";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<Program>().unwrap();
+        let codes: Vec<_> = diagnostics.iter().map(|error| error.code()).collect();
+
+        // the stray '}' at the top level is reported, but not as unclosed
+        assert_eq!(
+            codes,
+            [ErrorCode::ExpectedExpression, ErrorCode::ExpectedStatement]
+        );
+        assert_eq!(program.stmts.len(), 3);
+    }
+
+    #[test]
+    fn interpolated_string() {
+        let mut source_map = SourceMap::new();
+        let src = "x := 'hi {a.name}, {b + 1}!'\n";
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let expr = match parser.parse::<Stmt>().unwrap() {
+            Stmt::AssignNew(AssignNewStmt {
+                expr: Expr::Interpolated(expr),
+                ..
+            }) => expr,
+            stmt => panic!("expected interpolation, found {:?}", stmt),
+        };
+
+        let exprs: Vec<_> = (expr.parts.iter())
+            .map(|part| source_map.snippet(part.expr.span()))
+            .collect();
+
+        assert_eq!(interner.resolve(expr.start.value()), "hi ");
+        assert_eq!(exprs, ["a.name", "b + 1"]);
+        assert!(matches!(expr.parts[1].next, InterpolationNext::End(_)));
+        assert_eq!(source_map.snippet(expr.span()), "'hi {a.name}, {b + 1}!'");
+    }
+}
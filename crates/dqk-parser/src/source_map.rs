@@ -0,0 +1,290 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Span;
+
+/// Compact handle to a file loaded into a [`SourceMap`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct FileId(u32);
+
+impl FileId {
+    pub const fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A position in a file, both `line` and `col` start at 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct LineCol {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for LineCol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    name: PathBuf,
+    source: String,
+    /// Byte offset of the first char of every line.
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    fn new(name: PathBuf, source: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+
+        Self {
+            name,
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn name(&self) -> &Path {
+        &self.name
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Index of the line containing the byte at `offset`, counted from 0.
+    fn line_index(&self, offset: usize) -> usize {
+        match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        }
+    }
+
+    /// Line and column of the byte at `offset`, the column is counted in chars.
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let line = self.line_index(offset);
+        let prefix = &self.source[self.line_starts[line]..offset];
+
+        LineCol {
+            line: line + 1,
+            col: prefix.chars().count() + 1,
+        }
+    }
+
+    /// Line and column of the byte at `offset`, the column is counted in UTF-16
+    /// code units as editor protocols like LSP expect.
+    pub fn line_col_utf16(&self, offset: usize) -> LineCol {
+        let line = self.line_index(offset);
+        let prefix = &self.source[self.line_starts[line]..offset];
+
+        LineCol {
+            line: line + 1,
+            col: prefix.chars().map(char::len_utf16).sum::<usize>() + 1,
+        }
+    }
+
+    /// Byte offset of a position whose column is counted in UTF-16 code units,
+    /// the inverse of [`SourceFile::line_col_utf16`].
+    ///
+    /// Positions past the end of a line are clamped to the end of the line.
+    pub fn offset_utf16(&self, position: LineCol) -> usize {
+        let line = self.line(position.line);
+        let start = self.line_starts[position.line - 1];
+
+        let mut col = 1;
+
+        for (offset, ch) in line.char_indices() {
+            if col >= position.col {
+                return start + offset;
+            }
+
+            col += ch.len_utf16();
+        }
+
+        start + line.len()
+    }
+
+    /// Text of `line`, counted from 1, without the line break.
+    pub fn line(&self, line: usize) -> &str {
+        let start = self.line_starts[line - 1];
+        let end = self
+            .line_starts
+            .get(line)
+            .map_or(self.source.len(), |next_line| next_line - 1);
+
+        self.source[start..end].trim_end_matches('\r')
+    }
+}
+
+/// Owns every loaded source file and resolves [`Span`]s back to them.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: impl Into<PathBuf>, source: impl Into<String>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+
+        self.files.push(SourceFile::new(name.into(), source.into()));
+
+        id
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<FileId> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+
+        Ok(self.add(path, source))
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.index()]
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        (self.files.iter().enumerate()).map(|(index, file)| (FileId(index as u32), file))
+    }
+
+    pub fn source(&self, id: FileId) -> &str {
+        self.file(id).source()
+    }
+
+    pub fn file_name(&self, span: Span) -> &Path {
+        self.file(span.file()).name()
+    }
+
+    /// Line and column of the start of `span`.
+    pub fn line_col(&self, span: Span) -> LineCol {
+        self.file(span.file()).line_col(span.start())
+    }
+
+    /// Line and column of the end of `span`.
+    pub fn end_line_col(&self, span: Span) -> LineCol {
+        self.file(span.file()).line_col(span.end())
+    }
+
+    /// Line and column of the start of `span`, counted in UTF-16 code units.
+    pub fn line_col_utf16(&self, span: Span) -> LineCol {
+        self.file(span.file()).line_col_utf16(span.start())
+    }
+
+    /// Source text covered by `span`.
+    pub fn snippet(&self, span: Span) -> &str {
+        &self.source(span.file())[span.start()..span.end()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_col_counts_chars() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", "x := 'héllo'\ny := '👋' + z\n");
+
+        let file = source_map.file(file);
+        let z = file.source().find('z').unwrap();
+
+        assert_eq!(z, 28);
+        assert_eq!(file.line_col(z), LineCol { line: 2, col: 12 });
+        assert_eq!(file.line(2), "y := '👋' + z");
+    }
+
+    #[test]
+    fn utf16_columns_round_trip() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", "a := 'é👋' + b\n");
+
+        let file = source_map.file(file);
+        let b = file.source().find('b').unwrap();
+
+        // 'é' is one UTF-16 code unit, '👋' is a surrogate pair
+        assert_eq!(file.line_col(b), LineCol { line: 1, col: 13 });
+        assert_eq!(file.line_col_utf16(b), LineCol { line: 1, col: 14 });
+        assert_eq!(file.offset_utf16(file.line_col_utf16(b)), b);
+    }
+
+    #[test]
+    fn lines_and_columns() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", "ln f(Message) {\n\tx := 1\n}\n");
+
+        let file = source_map.file(file);
+
+        assert_eq!(file.line_count(), 4);
+        assert_eq!(file.line_col(0), LineCol { line: 1, col: 1 });
+        // the line break belongs to the line it ends
+        assert_eq!(file.line_col(15), LineCol { line: 1, col: 16 });
+        assert_eq!(file.line_col(16), LineCol { line: 2, col: 1 });
+        assert_eq!(file.line_col(17), LineCol { line: 2, col: 2 });
+        assert_eq!(file.line(2), "\tx := 1");
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", "x := 1\r\ny := 2\r\n");
+
+        let file = source_map.file(file);
+        let y = file.source().find('y').unwrap();
+
+        assert_eq!(file.line(1), "x := 1");
+        assert_eq!(file.line(2), "y := 2");
+        assert_eq!(file.line_col(6), LineCol { line: 1, col: 7 });
+        assert_eq!(file.line_col(y), LineCol { line: 2, col: 1 });
+    }
+
+    #[test]
+    fn offsets_at_the_end_of_the_file() {
+        let mut source_map = SourceMap::new();
+        let trailing = source_map.add("trailing.dqk", "x := 1\n");
+        let missing = source_map.add("missing.dqk", "x := 1");
+
+        let trailing = source_map.file(trailing);
+        assert_eq!(trailing.line_col(7), LineCol { line: 2, col: 1 });
+        assert_eq!(trailing.line(2), "");
+
+        let missing = source_map.file(missing);
+        assert_eq!(missing.line_col(6), LineCol { line: 1, col: 7 });
+        assert_eq!(missing.line(1), "x := 1");
+    }
+
+    #[test]
+    fn spans_resolve_in_their_file() {
+        let mut source_map = SourceMap::new();
+        let a = source_map.add("a.dqk", "x := 1\n");
+        let b = source_map.add("b.dqk", "\nyy := x\n");
+
+        let span = Span::new(b, 1, 2);
+
+        assert_ne!(a, b);
+        assert_eq!(source_map.file_name(span), Path::new("b.dqk"));
+        assert_eq!(source_map.snippet(span), "yy");
+        assert_eq!(source_map.line_col(span), LineCol { line: 2, col: 1 });
+        assert_eq!(source_map.end_line_col(span), LineCol { line: 2, col: 3 });
+        assert_eq!(source_map.snippet(Span::new(a, 0, 1)), "x");
+
+        let files: Vec<_> = source_map
+            .files()
+            .map(|(id, file)| (id, file.name()))
+            .collect();
+        assert_eq!(files, [(a, Path::new("a.dqk")), (b, Path::new("b.dqk"))]);
+    }
+}
@@ -1,32 +1,37 @@
-use std::{
-    ops::{BitOr, BitOrAssign, Deref, DerefMut},
-    path::Path,
-};
+use std::ops::{BitOr, BitOrAssign, Deref, DerefMut};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 pub use dqk_macro::Spanned;
 
-use crate::{Parse, Parser, Result, TokenKind};
+use crate::{FileId, Parse, Parser, Result, TokenKind};
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Span {
-    file_path: Option<&'static Path>,
+    file: FileId,
     start: usize,
     length: usize,
 }
 
 impl Span {
-    pub const fn new(file_path: Option<&'static Path>, start: usize, length: usize) -> Self {
+    pub const fn new(file: FileId, start: usize, length: usize) -> Self {
         Self {
-            file_path,
+            file,
             start,
             length,
         }
     }
 
-    pub const fn file_path(&self) -> Option<&'static Path> {
-        self.file_path
+    pub const fn file(&self) -> FileId {
+        self.file
+    }
+
+    pub const fn start(&self) -> usize {
+        self.start
+    }
+
+    pub const fn length(&self) -> usize {
+        self.length
     }
 
     pub const fn end(&self) -> usize {
@@ -44,14 +49,12 @@ impl BitOr for Span {
     type Output = Span;
 
     fn bitor(self, rhs: Self) -> Self::Output {
-        if let (Some(lhs), Some(rhs)) = (self.file_path(), rhs.file_path()) {
-            assert_eq!(lhs as *const _, rhs as *const _);
-        }
+        assert_eq!(self.file, rhs.file, "cannot join spans of different files");
 
         let start = self.start.min(rhs.start);
 
         Self {
-            file_path: self.file_path,
+            file: self.file,
             start,
             length: self.end().max(rhs.end()) - start,
         }
@@ -74,7 +77,7 @@ impl<T: Spanned> Spanned for Box<T> {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SpannedOption<T> {
     value: Option<T>,
     span: Span,
@@ -0,0 +1,93 @@
+/// Edit distance between `a` and `b`, counted in chars.
+///
+/// Insertions, deletions, substitutions and transpositions of two adjacent
+/// chars each count as one edit, so `retrun` is a single edit away from `return`.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut prev_prev = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for i in 0..a.len() {
+        curr[0] = i + 1;
+
+        for j in 0..b.len() {
+            let substitution = prev[j] + (a[i] != b[j]) as usize;
+            let mut distance = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                distance = distance.min(prev_prev[j - 1] + 1);
+            }
+
+            curr[j + 1] = distance;
+        }
+
+        std::mem::swap(&mut prev_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Finds the candidate closest to `name`, if any is close enough to be a likely typo.
+///
+/// A candidate is accepted when at most a third of `name` has to be edited to
+/// reach it, with a minimum of one edit. Ties are broken by candidate order.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distances() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("", "ln"), 2);
+        assert_eq!(edit_distance("where", "where"), 0);
+        assert_eq!(edit_distance("wher", "where"), 1);
+        assert_eq!(edit_distance("whereh", "where"), 1);
+        assert_eq!(edit_distance("whare", "where"), 1);
+        assert_eq!(edit_distance("retrun", "return"), 1);
+        assert_eq!(edit_distance("wehre", "where"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("naïve", "naive"), 1);
+    }
+
+    #[test]
+    fn threshold() {
+        // a third of the name, at least one edit
+        assert_eq!(suggest("retrun", ["return"]), Some("return"));
+        assert_eq!(suggest("abcdef", ["abcxyf"]), Some("abcxyf"));
+        assert_eq!(suggest("abcdef", ["axyzef"]), None);
+        assert_eq!(suggest("ab", ["ax"]), Some("ax"));
+        assert_eq!(suggest("ab", ["xy"]), None);
+    }
+
+    #[test]
+    fn closest_and_first_of_ties() {
+        assert_eq!(
+            suggest("contnts", ["content", "contents"]),
+            Some("contents")
+        );
+        assert_eq!(suggest("cat", ["bat", "hat"]), Some("bat"));
+        assert_eq!(suggest("cat", ["hat", "bat"]), Some("hat"));
+    }
+
+    #[test]
+    fn no_candidates() {
+        assert_eq!(suggest("x", []), None);
+        assert_eq!(suggest("where", ["where"]), None);
+    }
+}
@@ -1,6 +1,9 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::{Error, Parse, Parser, Result, Span, Spanned};
+use crate::{
+    suggest, Error, ErrorCode, Interner, Node, Parse, Parser, Result, Span, Spanned, Symbol, Visit,
+    VisitMut,
+};
 
 pub trait FromToken: Sized {
     const NAME: &'static str;
@@ -8,7 +11,7 @@ pub trait FromToken: Sized {
     fn from_token(token: Token) -> Option<Self>;
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Token {
     kind: TokenKind,
     span: Span,
@@ -28,29 +31,45 @@ impl Token {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TokenKind {
-    Ident(&'static str),
-    String(&'static str),
-    Comment(&'static str),
+    Ident(Symbol),
+    String(Symbol),
+    /// `'text{`, the start of a string with interpolations.
+    StringStart(Symbol),
+    /// `}text{`, text between two interpolations.
+    StringMiddle(Symbol),
+    /// `}text'`, the end of a string with interpolations.
+    StringEnd(Symbol),
+    Comment(Symbol),
     Keyword(Keyword),
     Integer(Integer),
     Float(Float),
-    Symbol(Symbol),
+    Punct(Punct),
     Eol,
     Eof,
 }
 
 impl TokenKind {
     pub fn is_eol(&self) -> bool {
+        matches!(self, Self::Eol | Self::Eof)
+    }
+
+    /// Formats like `Debug`, but with the interned strings resolved.
+    pub fn describe(&self, interner: &Interner) -> String {
         match self {
-            Self::Eol | Self::Eof => true,
-            _ => false,
+            Self::Ident(symbol) => format!("Ident({:?})", interner.resolve(*symbol)),
+            Self::String(symbol) => format!("String({:?})", interner.resolve(*symbol)),
+            Self::StringStart(symbol) => format!("StringStart({:?})", interner.resolve(*symbol)),
+            Self::StringMiddle(symbol) => format!("StringMiddle({:?})", interner.resolve(*symbol)),
+            Self::StringEnd(symbol) => format!("StringEnd({:?})", interner.resolve(*symbol)),
+            Self::Comment(symbol) => format!("Comment({:?})", interner.resolve(*symbol)),
+            kind => format!("{:?}", kind),
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Integer {
     value: i64,
     kind: IntegerKind,
@@ -70,7 +89,7 @@ impl Integer {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IntegerKind {
     Binary,
     Decimal,
@@ -87,7 +106,7 @@ impl IntegerKind {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Float {
     value: f64,
 }
@@ -108,13 +127,19 @@ macro_rules! sub_token {
             $($variant:ident),* $(,)?
         }
     ) => {
-        #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
         pub enum $ident {
             $($variant),*
         }
 
+        impl From<$ident> for TokenKind {
+            fn from(token: $ident) -> Self {
+                Self::$ident(token)
+            }
+        }
+
         $(
-            #[derive(Clone, Copy, PartialEq, Eq, Serialize)]
+            #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
             pub struct $variant {
                 span: Span,
             }
@@ -138,6 +163,11 @@ macro_rules! sub_token {
                     })
                 }
             }
+
+            impl Node for $variant {
+                fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, _: &mut V) {}
+                fn accept_mut<V: VisitMut + ?Sized>(&mut self, _: &mut V) {}
+            }
         )*
     };
 }
@@ -157,7 +187,7 @@ sub_token! {
 }
 
 sub_token! {
-    pub enum Symbol {
+    pub enum Punct {
         OpenParen,
         OpenBrace,
         OpenBracket,
@@ -182,10 +212,46 @@ sub_token! {
     }
 }
 
+/// Spelling of every keyword, used by the lexer and for "did you mean" suggestions.
+pub const KEYWORDS: &[(&str, Keyword)] = &[
+    ("event", Keyword::Event),
+    ("query", Keyword::Query),
+    ("where", Keyword::Where),
+    ("ln", Keyword::Ln),
+    ("fn", Keyword::Fn),
+    ("if", Keyword::If),
+    ("for", Keyword::For),
+    ("else", Keyword::Else),
+    ("return", Keyword::Return),
+];
+
+impl Keyword {
+    pub fn from_ident(ident: &str) -> Option<Self> {
+        KEYWORDS
+            .iter()
+            .find(|(name, _)| *name == ident)
+            .map(|(_, keyword)| *keyword)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        KEYWORDS
+            .iter()
+            .find(|(_, keyword)| keyword == self)
+            .map(|(name, _)| *name)
+            .unwrap()
+    }
+
+    /// Finds a keyword that `ident` is likely a misspelling of.
+    pub fn suggest(ident: &str) -> Option<Self> {
+        let name = suggest(ident, KEYWORDS.iter().map(|(name, _)| *name))?;
+        Self::from_ident(name)
+    }
+}
+
 macro_rules! value_tokens {
     ($($pat:pat => $ident:ident($value:ident: $ty:ty, $name:literal)),* $(,)?) => {
 		$(
-			#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+			#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 			pub struct $ident {
 				value: $ty,
 				span: Span,
@@ -195,6 +261,10 @@ macro_rules! value_tokens {
 				pub const fn new(value: $ty, span: Span) -> Self {
 					Self { value, span }
 				}
+
+				pub const fn value(&self) -> $ty {
+					self.value
+				}
 			}
 
 			impl Spanned for $ident {
@@ -210,8 +280,8 @@ macro_rules! value_tokens {
 					match tok.kind() {
 						$pat => Ok(Self::new($value, tok.span())),
 						_ => Err(
-                            Error::new(format!("expected '{}'", $name))
-                                .with_hint(format!("found '{:?}'", tok.kind()), tok.span())
+                            Error::new(ErrorCode::ExpectedToken, format!("expected '{}'", $name))
+                                .with_found_hint(tok, parser.interner())
                         ),
 					}
 				}
@@ -221,14 +291,17 @@ macro_rules! value_tokens {
 }
 
 value_tokens! {
-    TokenKind::Ident(value) => Ident(value: &'static str, "{identifier}"),
-    TokenKind::String(value) => LitStr(value: &'static str, "{string}"),
-    TokenKind::Comment(value) => Comment(value: &'static str, "{comment}"),
+    TokenKind::Ident(value) => Ident(value: Symbol, "{identifier}"),
+    TokenKind::String(value) => LitStr(value: Symbol, "{string}"),
+    TokenKind::StringStart(value) => LitStrStart(value: Symbol, "{string}"),
+    TokenKind::StringMiddle(value) => LitStrMiddle(value: Symbol, "}"),
+    TokenKind::StringEnd(value) => LitStrEnd(value: Symbol, "}"),
+    TokenKind::Comment(value) => Comment(value: Symbol, "{comment}"),
     TokenKind::Integer(value) => LitInt(value: Integer, "{integer}"),
     TokenKind::Float(value) => LitFloat(value: Float, "{float}"),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Eol {
     span: Span,
 }
@@ -239,6 +312,16 @@ impl Spanned for Eol {
     }
 }
 
+impl Node for Eol {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, _: &mut V) {}
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, _: &mut V) {}
+}
+
+impl Node for Comment {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, _: &mut V) {}
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, _: &mut V) {}
+}
+
 impl Parse for Eol {
     fn parse(parser: &mut Parser) -> Result<Self> {
         Ok(Self {
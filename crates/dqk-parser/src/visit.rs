@@ -0,0 +1,380 @@
+use crate::{
+    Argument, AssignNewStmt, AssignStmt, BinOp, BinOpExpr, Block, CallExpr, ClosureExpr,
+    DefaultEvent, DefaultEvents, Expr, ExprStmt, Float, Ident, Integer, InterpolatedExpr,
+    Interpolation, InterpolationNext, ListExpr, ListenerArgument, ListenerArguments, LitFloat,
+    LitInt, LitStr, LitStrEnd, LitStrMiddle, LitStrStart, LiteralExpr, LnStmt, MemberExpr,
+    ParenExpr, Program, Span, Spanned, SpannedOption, Stmt, Symbol, Type, WhereClause,
+};
+
+pub use dqk_macro::Visit;
+
+/// An AST node that dispatches to the matching method of a visitor.
+///
+/// Nodes implement this with `#[derive(Visit)]`, which calls `visit_<node>` on
+/// the visitor. Containers like `Box`, `Vec` and [`Punctuated`](crate::Punctuated) forward to
+/// their elements, and tokens don't visit anything.
+pub trait Node {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V);
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V);
+}
+
+/// Visits the children of an AST node, implemented by `#[derive(Visit)]`.
+pub trait Walk {
+    fn walk<'ast, V: Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V);
+    fn walk_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V);
+}
+
+/// A node of the AST that has a [`NodeKind`].
+pub trait AstNode: Node + Spanned {
+    const KIND: NodeKind;
+}
+
+/// The value of a token, which visits the [`Symbol`]s among them.
+pub trait TokenValue {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&self, visitor: &mut V);
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V);
+}
+
+impl TokenValue for Symbol {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&self, visitor: &mut V) {
+        visitor.visit_symbol(*self)
+    }
+
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        visitor.visit_symbol_mut(self)
+    }
+}
+
+impl TokenValue for Integer {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&self, _: &mut V) {}
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, _: &mut V) {}
+}
+
+impl TokenValue for Float {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&self, _: &mut V) {}
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, _: &mut V) {}
+}
+
+/// Entering or leaving a node, see [`Events`].
+pub(crate) enum NodeEvent {
+    Enter(NodeKind, Span),
+    Exit(Span),
+}
+
+/// Visitor that calls a closure when entering and leaving every node.
+pub(crate) struct Events<F>(pub F);
+
+macro_rules! visitor {
+    (
+        nodes {
+            $($node:ident => $visit:ident, $walk:ident, $visit_mut:ident, $walk_mut:ident;)*
+        }
+        leaves {
+            $($leaf:ident => $leaf_visit:ident, $leaf_visit_mut:ident;)*
+        }
+    ) => {
+        /// Walks a borrowed AST.
+        ///
+        /// Every method defaults to visiting the children of the node, override
+        /// a method to act on that kind of node and call the matching `walk_*`
+        /// function to keep descending.
+        pub trait Visit<'ast> {
+            $(
+                fn $visit(&mut self, node: &'ast $node) {
+                    $walk(self, node)
+                }
+            )*
+
+            $(
+                fn $leaf_visit(&mut self, node: &'ast $leaf) {
+                    node.value().accept(self)
+                }
+            )*
+
+            /// Called for the value of every token holding a [`Symbol`].
+            fn visit_symbol(&mut self, symbol: Symbol) {
+                let _ = symbol;
+            }
+        }
+
+        /// Walks a mutable AST, see [`Visit`].
+        pub trait VisitMut {
+            $(
+                fn $visit_mut(&mut self, node: &mut $node) {
+                    $walk_mut(self, node)
+                }
+            )*
+
+            $(
+                fn $leaf_visit_mut(&mut self, node: &mut $leaf) {
+                    let mut value = node.value();
+                    value.accept_mut(self);
+                    *node = $leaf::new(value, node.span());
+                }
+            )*
+
+            /// Called for the value of every token holding a [`Symbol`], see
+            /// [`Visit::visit_symbol`].
+            fn visit_symbol_mut(&mut self, symbol: &mut Symbol) {
+                let _ = symbol;
+            }
+        }
+
+        $(
+            pub fn $walk<'ast, V: Visit<'ast> + ?Sized>(visitor: &mut V, node: &'ast $node) {
+                Walk::walk(node, visitor)
+            }
+
+            pub fn $walk_mut<V: VisitMut + ?Sized>(visitor: &mut V, node: &mut $node) {
+                Walk::walk_mut(node, visitor)
+            }
+        )*
+
+        /// Kind of every node of the AST, used to label the nodes of a
+        /// [`SyntaxNode`](crate::SyntaxNode) tree.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum NodeKind {
+            $($node),*
+        }
+
+        $(
+            impl AstNode for $node {
+                const KIND: NodeKind = NodeKind::$node;
+            }
+        )*
+
+        impl<'ast, F: FnMut(NodeEvent)> Visit<'ast> for Events<F> {
+            $(
+                fn $visit(&mut self, node: &'ast $node) {
+                    let span = node.span();
+
+                    (self.0)(NodeEvent::Enter(NodeKind::$node, span));
+                    $walk(self, node);
+                    (self.0)(NodeEvent::Exit(span));
+                }
+            )*
+        }
+
+        $(
+            impl Node for $leaf {
+                fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V) {
+                    visitor.$leaf_visit(self)
+                }
+
+                fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+                    visitor.$leaf_visit_mut(self)
+                }
+            }
+        )*
+    };
+}
+
+visitor! {
+    nodes {
+        Program => visit_program, walk_program, visit_program_mut, walk_program_mut;
+        Stmt => visit_stmt, walk_stmt, visit_stmt_mut, walk_stmt_mut;
+        LnStmt => visit_ln_stmt, walk_ln_stmt, visit_ln_stmt_mut, walk_ln_stmt_mut;
+        AssignNewStmt => visit_assign_new_stmt, walk_assign_new_stmt, visit_assign_new_stmt_mut, walk_assign_new_stmt_mut;
+        AssignStmt => visit_assign_stmt, walk_assign_stmt, visit_assign_stmt_mut, walk_assign_stmt_mut;
+        ExprStmt => visit_expr_stmt, walk_expr_stmt, visit_expr_stmt_mut, walk_expr_stmt_mut;
+        Block => visit_block, walk_block, visit_block_mut, walk_block_mut;
+        Argument => visit_argument, walk_argument, visit_argument_mut, walk_argument_mut;
+        ListenerArgument => visit_listener_argument, walk_listener_argument, visit_listener_argument_mut, walk_listener_argument_mut;
+        ListenerArguments => visit_listener_arguments, walk_listener_arguments, visit_listener_arguments_mut, walk_listener_arguments_mut;
+        WhereClause => visit_where_clause, walk_where_clause, visit_where_clause_mut, walk_where_clause_mut;
+        DefaultEvent => visit_default_event, walk_default_event, visit_default_event_mut, walk_default_event_mut;
+        DefaultEvents => visit_default_events, walk_default_events, visit_default_events_mut, walk_default_events_mut;
+        Expr => visit_expr, walk_expr, visit_expr_mut, walk_expr_mut;
+        ParenExpr => visit_paren_expr, walk_paren_expr, visit_paren_expr_mut, walk_paren_expr_mut;
+        ListExpr => visit_list_expr, walk_list_expr, visit_list_expr_mut, walk_list_expr_mut;
+        ClosureExpr => visit_closure_expr, walk_closure_expr, visit_closure_expr_mut, walk_closure_expr_mut;
+        LiteralExpr => visit_literal_expr, walk_literal_expr, visit_literal_expr_mut, walk_literal_expr_mut;
+        InterpolatedExpr => visit_interpolated_expr, walk_interpolated_expr, visit_interpolated_expr_mut, walk_interpolated_expr_mut;
+        Interpolation => visit_interpolation, walk_interpolation, visit_interpolation_mut, walk_interpolation_mut;
+        InterpolationNext => visit_interpolation_next, walk_interpolation_next, visit_interpolation_next_mut, walk_interpolation_next_mut;
+        MemberExpr => visit_member_expr, walk_member_expr, visit_member_expr_mut, walk_member_expr_mut;
+        CallExpr => visit_call_expr, walk_call_expr, visit_call_expr_mut, walk_call_expr_mut;
+        BinOp => visit_bin_op, walk_bin_op, visit_bin_op_mut, walk_bin_op_mut;
+        BinOpExpr => visit_bin_op_expr, walk_bin_op_expr, visit_bin_op_expr_mut, walk_bin_op_expr_mut;
+        Type => visit_type, walk_type, visit_type_mut, walk_type_mut;
+    }
+    leaves {
+        Ident => visit_ident, visit_ident_mut;
+        LitInt => visit_lit_int, visit_lit_int_mut;
+        LitFloat => visit_lit_float, visit_lit_float_mut;
+        LitStr => visit_lit_str, visit_lit_str_mut;
+        LitStrStart => visit_lit_str_start, visit_lit_str_start_mut;
+        LitStrMiddle => visit_lit_str_middle, visit_lit_str_middle_mut;
+        LitStrEnd => visit_lit_str_end, visit_lit_str_end_mut;
+    }
+}
+
+impl<T: Node> Node for Box<T> {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V) {
+        self.as_ref().accept(visitor)
+    }
+
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        self.as_mut().accept_mut(visitor)
+    }
+}
+
+impl<T: Node> Node for Option<T> {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V) {
+        if let Some(node) = self {
+            node.accept(visitor);
+        }
+    }
+
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        if let Some(node) = self {
+            node.accept_mut(visitor);
+        }
+    }
+}
+
+impl<T: Node> Node for Vec<T> {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V) {
+        for node in self {
+            node.accept(visitor);
+        }
+    }
+
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        for node in self {
+            node.accept_mut(visitor);
+        }
+    }
+}
+
+impl<T: Node> Node for SpannedOption<T> {
+    fn accept<'ast, V: Visit<'ast> + ?Sized>(&'ast self, visitor: &mut V) {
+        (**self).accept(visitor)
+    }
+
+    fn accept_mut<V: VisitMut + ?Sized>(&mut self, visitor: &mut V) {
+        (**self).accept_mut(visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Diagnostics, Interner, Parser, SourceMap, Spanned};
+
+    fn parse(src: &str, interner: &Interner) -> Program {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, interner, &mut diagnostics);
+
+        let program = parser.parse().unwrap();
+        assert!(diagnostics.is_empty());
+
+        program
+    }
+
+    #[derive(Default)]
+    struct Idents(Vec<Symbol>);
+
+    impl Visit<'_> for Idents {
+        fn visit_ident(&mut self, node: &Ident) {
+            self.0.push(node.value());
+        }
+    }
+
+    #[test]
+    fn visit_reaches_every_ident() {
+        let interner = Interner::new();
+        let program = parse(
+            "ln log<message_sent>(Message)\nwhere\n\tcontents == 'ping',\n{\n\tchannel.send 'pong', (a + b) * c\n}\n",
+            &interner,
+        );
+
+        let mut idents = Idents::default();
+        idents.visit_program(&program);
+
+        let idents: Vec<_> = (idents.0.into_iter())
+            .map(|symbol| interner.resolve(symbol))
+            .collect();
+
+        assert_eq!(
+            idents,
+            [
+                "log",
+                "message_sent",
+                "Message",
+                "contents",
+                "channel",
+                "send",
+                "a",
+                "b",
+                "c"
+            ]
+        );
+    }
+
+    struct Rename {
+        from: Symbol,
+        to: Symbol,
+    }
+
+    impl VisitMut for Rename {
+        fn visit_expr_mut(&mut self, node: &mut Expr) {
+            if let Expr::Variable(ident) = node {
+                if ident.value() == self.from {
+                    *ident = Ident::new(self.to, ident.span());
+                }
+            }
+
+            walk_expr_mut(self, node);
+        }
+    }
+
+    #[test]
+    fn visit_mut_renames_variables() {
+        let interner = Interner::new();
+        let mut program = parse("x := 1\ny := x + x\n", &interner);
+
+        let mut rename = Rename {
+            from: interner.intern("x"),
+            to: interner.intern("z"),
+        };
+        rename.visit_program_mut(&mut program);
+
+        let mut idents = Idents::default();
+        idents.visit_program(&program);
+
+        let idents: Vec<_> = (idents.0.into_iter())
+            .map(|symbol| interner.resolve(symbol))
+            .collect();
+
+        // the declared name is an `Ident`, not an `Expr::Variable`
+        assert_eq!(idents, ["x", "y", "z", "z"]);
+    }
+
+    #[derive(Default)]
+    struct Symbols(Vec<Symbol>);
+
+    impl Visit<'_> for Symbols {
+        fn visit_symbol(&mut self, symbol: Symbol) {
+            self.0.push(symbol);
+        }
+    }
+
+    #[test]
+    fn visit_symbol_reaches_idents_and_strings() {
+        let interner = Interner::new();
+        let program = parse("x := 'a{y}b{z}c' + 'd'\n", &interner);
+
+        let mut symbols = Symbols::default();
+        symbols.visit_program(&program);
+
+        let symbols: Vec<_> = (symbols.0.into_iter())
+            .map(|symbol| interner.resolve(symbol))
+            .collect();
+
+        assert_eq!(symbols, ["x", "a", "y", "b", "z", "c", "d"]);
+    }
+}
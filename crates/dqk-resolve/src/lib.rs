@@ -0,0 +1,19 @@
+#![forbid(unsafe_code)]
+
+//! Name resolution for dqk programs.
+//!
+//! The resolver walks a [`Program`](dqk_parser::Program) and builds a tree of
+//! [`Scope`]s for the program, every listener and every block. `x := ..`
+//! defines a new variable in the innermost scope, while `x = ..` and every
+//! use of `x` must refer to a definition in an enclosing scope. The arguments
+//! of a listener are defined in its scope, an expanded argument like
+//! `(Message)` defines every field of the entity.
+//!
+//! Every identifier that refers to or defines a name is mapped to its
+//! [`DefId`] in the returned [`Resolution`].
+
+mod resolver;
+mod scope;
+
+pub use resolver::*;
+pub use scope::*;
@@ -0,0 +1,545 @@
+use std::collections::HashMap;
+
+use dqk_parser::{
+    suggest, walk_block, walk_expr, AssignNewStmt, AssignStmt, Block, ClosureExpr, Diagnostics,
+    Error, ErrorCode, Expr, Ident, Interner, Keyword, ListenerArgument, LnStmt, Node, Program,
+    Span, Spanned, Symbol, Type, Visit,
+};
+use dqk_schema::Entity;
+
+use crate::{DefId, DefKind, Definition, Scope, ScopeId, ScopeKind};
+
+/// The scopes and definitions of a program, see [`resolve`].
+#[derive(Clone, Debug)]
+pub struct Resolution {
+    definitions: Vec<Definition>,
+    scopes: Vec<Scope>,
+    /// Definition of every resolved identifier, by its span.
+    idents: HashMap<Span, DefId>,
+}
+
+impl Resolution {
+    fn new() -> Self {
+        Self {
+            definitions: Vec::new(),
+            scopes: vec![Scope::new(ScopeKind::Program, None)],
+            idents: HashMap::new(),
+        }
+    }
+
+    /// The scope of the whole program.
+    pub fn root(&self) -> ScopeId {
+        ScopeId::new(0)
+    }
+
+    pub fn definition(&self, id: DefId) -> &Definition {
+        &self.definitions[id.index()]
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = (DefId, &Definition)> {
+        (self.definitions.iter().enumerate()).map(|(index, def)| (DefId::new(index), def))
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope {
+        &self.scopes[id.index()]
+    }
+
+    /// The definition `ident` refers to, or defines.
+    ///
+    /// Returns `None` for identifiers that aren't names, like the member of a
+    /// member access, and for names that couldn't be resolved.
+    pub fn resolve(&self, ident: &Ident) -> Option<DefId> {
+        self.idents.get(&ident.span()).copied()
+    }
+
+    /// Looks `name` up in `scope` and its parents.
+    pub fn lookup(&self, scope: ScopeId, name: Symbol) -> Option<DefId> {
+        let mut scope = Some(scope);
+
+        while let Some(id) = scope {
+            let current = self.scope(id);
+
+            if let Some(def) = current.get(name) {
+                return Some(def);
+            }
+
+            scope = current.parent;
+        }
+
+        None
+    }
+}
+
+/// Resolves every name in `program`.
+///
+/// Undefined and duplicate names are reported to `diagnostics`, the returned
+/// [`Resolution`] contains everything that could be resolved.
+pub fn resolve(
+    program: &Program,
+    interner: &Interner,
+    diagnostics: &mut Diagnostics,
+) -> Resolution {
+    let mut resolver = Resolver {
+        interner,
+        diagnostics,
+        resolution: Resolution::new(),
+        scope: ScopeId::new(0),
+    };
+
+    program.accept(&mut resolver);
+
+    resolver.resolution
+}
+
+struct Resolver<'a> {
+    interner: &'a Interner,
+    diagnostics: &'a mut Diagnostics,
+    resolution: Resolution,
+    /// The innermost scope.
+    scope: ScopeId,
+}
+
+impl Resolver<'_> {
+    fn scoped(&mut self, kind: ScopeKind, f: impl FnOnce(&mut Self)) {
+        let parent = self.scope;
+
+        self.scope = ScopeId::new(self.resolution.scopes.len());
+        self.resolution.scopes.push(Scope::new(kind, Some(parent)));
+
+        f(self);
+
+        self.scope = parent;
+    }
+
+    /// Defines `name` in the innermost scope, unless it is already defined there.
+    ///
+    /// The fields of an expanded argument shadow the fields of the same name
+    /// of the arguments before it, like the `id` of `Guild` in `(User, Guild)`.
+    fn define(&mut self, name: Symbol, kind: DefKind, span: Span) -> Option<DefId> {
+        let scope = &self.resolution.scopes[self.scope.index()];
+
+        if let Some(first) = scope.get(name) {
+            let first = self.resolution.definition(first);
+
+            match (first.kind, kind) {
+                (DefKind::Field { .. }, DefKind::Field { .. }) => {}
+                (first_kind, _) => {
+                    let error = self.duplicate(name, kind, span, first_kind, first.span);
+                    self.diagnostics.emit(error);
+
+                    return None;
+                }
+            }
+        }
+
+        let id = DefId::new(self.resolution.definitions.len());
+
+        self.resolution.definitions.push(Definition {
+            name,
+            kind,
+            span,
+            scope: self.scope,
+        });
+        self.resolution.scopes[self.scope.index()].insert(name, id);
+
+        Some(id)
+    }
+
+    /// The error for defining `name` again, at `span`, after a `first` definition.
+    fn duplicate(
+        &self,
+        name: Symbol,
+        kind: DefKind,
+        span: Span,
+        first_kind: DefKind,
+        first: Span,
+    ) -> Error {
+        let name = self.interner.resolve(name);
+
+        let hint = |kind, msg: String| match kind {
+            DefKind::Field { entity, .. } => format!("'{}' is a field of {}", name, entity),
+            _ => msg,
+        };
+
+        let error = Error::new(
+            ErrorCode::DuplicateDefinition,
+            format!("'{}' is defined more than once", name),
+        )
+        .with_hint(hint(kind, format!("'{}' redefined here", name)), span)
+        .with_hint(hint(first_kind, "first defined here".to_string()), first);
+
+        match (first_kind, kind) {
+            (DefKind::Field { .. }, _) | (_, DefKind::Field { .. }) => error.with_help(
+                "the fields of an expanded argument can't be renamed, rename the other argument",
+            ),
+            _ => error.with_help(format!("use '{} = ..' to assign a new value", name)),
+        }
+    }
+
+    fn define_ident(&mut self, ident: &Ident, kind: DefKind) {
+        if let Some(id) = self.define(ident.value(), kind, ident.span()) {
+            self.resolution.idents.insert(ident.span(), id);
+        }
+    }
+
+    /// Resolves a use of `ident`, reporting it if it is undefined.
+    fn use_ident(&mut self, ident: &Ident, help: Option<&str>) {
+        if let Some(id) = self.resolution.lookup(self.scope, ident.value()) {
+            self.resolution.idents.insert(ident.span(), id);
+            return;
+        }
+
+        let name = self.interner.resolve(ident.value());
+        let visible = self.visible_names();
+
+        let mut error = Error::new(
+            ErrorCode::UndefinedName,
+            format!("cannot find '{}' in this scope", name),
+        )
+        .with_hint("not found in this scope", ident.span());
+
+        // any other one letter name is a single edit away, that's no hint
+        let suggestion = (name.chars().count() > 1)
+            .then(|| {
+                suggest(name, visible.iter().copied())
+                    .or_else(|| Some(Keyword::suggest(name)?.as_str()))
+            })
+            .flatten();
+
+        if let Some(suggestion) = suggestion {
+            error = error.with_suggestion(suggestion, ident.span());
+        }
+
+        if let Some(help) = help {
+            error = error.with_help(help);
+        }
+
+        self.diagnostics.emit(error);
+    }
+
+    /// Every name visible from the innermost scope.
+    fn visible_names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        let mut scope = Some(self.scope);
+
+        while let Some(id) = scope {
+            let current = self.resolution.scope(id);
+            names.extend(current.names().map(|(name, _)| self.interner.resolve(name)));
+            scope = current.parent;
+        }
+
+        names.sort_unstable();
+        names
+    }
+
+    /// Defines every field of an expanded listener argument like `(Message)`.
+    fn expand(&mut self, ty: &Type) {
+        let Type::Ident(ident) = ty;
+        let name = self.interner.resolve(ident.value());
+
+        let entity = match Entity::from_name(name) {
+            Some(entity) => entity,
+            None => {
+                let mut error = Error::new(
+                    ErrorCode::UnknownType,
+                    format!("cannot find type '{}'", name),
+                )
+                .with_hint("not a Discord type", ty.span());
+
+                if let Some(suggestion) = suggest(name, Entity::ALL.iter().map(Entity::name)) {
+                    error = error.with_suggestion(suggestion, ty.span());
+                }
+
+                self.diagnostics.emit(error);
+                return;
+            }
+        };
+
+        for field in entity.fields() {
+            let kind = DefKind::Field {
+                entity,
+                name: field.name,
+            };
+
+            self.define(self.interner.intern(field.name), kind, ty.span());
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Resolver<'_> {
+    fn visit_ln_stmt(&mut self, node: &'ast LnStmt) {
+        self.define_ident(&node.ident, DefKind::Listener);
+
+        self.scoped(ScopeKind::Listener, |this| {
+            node.args.accept(this);
+            node.where_clause.accept(this);
+            node.block.accept(this);
+        });
+    }
+
+    fn visit_listener_argument(&mut self, node: &'ast ListenerArgument) {
+        match node {
+            ListenerArgument::Expanded(ty) => self.expand(ty),
+            ListenerArgument::Args(arg) => self.define_ident(&arg.ident, DefKind::Argument),
+        }
+    }
+
+    fn visit_closure_expr(&mut self, node: &'ast ClosureExpr) {
+        self.scoped(ScopeKind::Function, |this| {
+            for param in node.params.iter() {
+                this.define_ident(param, DefKind::Argument);
+            }
+
+            node.body.accept(this);
+        });
+    }
+
+    fn visit_block(&mut self, node: &'ast Block) {
+        self.scoped(ScopeKind::Block, |this| walk_block(this, node));
+    }
+
+    fn visit_assign_new_stmt(&mut self, node: &'ast AssignNewStmt) {
+        // the value is resolved first, `x := x + 1` refers to an outer `x`
+        node.expr.accept(self);
+        self.define_ident(&node.ident, DefKind::Variable);
+    }
+
+    fn visit_assign_stmt(&mut self, node: &'ast AssignStmt) {
+        node.expr.accept(self);
+        self.use_ident(&node.ident, Some("use ':=' to declare a new variable"));
+    }
+
+    fn visit_expr(&mut self, node: &'ast Expr) {
+        match node {
+            Expr::Variable(ident) => self.use_ident(ident, None),
+            _ => walk_expr(self, node),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dqk_parser::{Parser, SourceMap, Stmt};
+
+    use super::*;
+
+    fn resolve_source(src: &str) -> (Program, Resolution, Vec<ErrorCode>) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<Program>().unwrap();
+        assert!(diagnostics.is_empty());
+
+        let resolution = resolve(&program, &interner, &mut diagnostics);
+        let codes = diagnostics.iter().map(|error| error.code()).collect();
+
+        (program, resolution, codes)
+    }
+
+    #[test]
+    fn expanded_argument_defines_fields() {
+        let src = include_str!("../../../tests/ping.dqk");
+        let (program, resolution, codes) = resolve_source(src);
+
+        assert_eq!(codes, []);
+
+        let ln = match &program.stmts[0] {
+            Stmt::Ln(ln) => ln,
+            stmt => panic!("expected listener, found {:?}", stmt),
+        };
+
+        let channel = match &ln.block.stmts[0] {
+            Stmt::Expr(stmt) => match &stmt.expr {
+                Expr::Call(call) => match &*call.expr {
+                    Expr::Member(member) => match &*member.expr {
+                        Expr::Variable(ident) => ident,
+                        expr => panic!("expected variable, found {:?}", expr),
+                    },
+                    expr => panic!("expected member, found {:?}", expr),
+                },
+                expr => panic!("expected call, found {:?}", expr),
+            },
+            stmt => panic!("expected expression, found {:?}", stmt),
+        };
+
+        let def = resolution.definition(resolution.resolve(channel).unwrap());
+
+        assert_eq!(
+            def.kind,
+            DefKind::Field {
+                entity: Entity::Message,
+                name: "channel"
+            }
+        );
+        assert_eq!(resolution.scope(def.scope).kind, ScopeKind::Listener);
+    }
+
+    #[test]
+    fn later_expanded_arguments_shadow_fields() {
+        let src = "ln f<member_joined>(User, Guild) {\n\towner.send name\n\tx := is_bot\n}\n";
+        let (program, resolution, codes) = resolve_source(src);
+
+        assert_eq!(codes, []);
+
+        let ln = match &program.stmts[0] {
+            Stmt::Ln(ln) => ln,
+            stmt => panic!("expected listener, found {:?}", stmt),
+        };
+
+        let fields: Vec<_> = (ln.block.stmts.iter())
+            .map(|stmt| match stmt {
+                Stmt::Expr(stmt) => match &stmt.expr {
+                    Expr::Call(call) => call.args.iter().next().unwrap(),
+                    expr => panic!("expected call, found {:?}", expr),
+                },
+                Stmt::AssignNew(stmt) => &stmt.expr,
+                stmt => panic!("expected statement, found {:?}", stmt),
+            })
+            .map(|expr| match expr {
+                Expr::Variable(ident) => {
+                    resolution
+                        .definition(resolution.resolve(ident).unwrap())
+                        .kind
+                }
+                expr => panic!("expected variable, found {:?}", expr),
+            })
+            .collect();
+
+        // `name` is the guild's, `is_bot` is only a field of the user
+        assert_eq!(
+            fields,
+            [
+                DefKind::Field {
+                    entity: Entity::Guild,
+                    name: "name"
+                },
+                DefKind::Field {
+                    entity: Entity::User,
+                    name: "is_bot"
+                },
+            ]
+        );
+
+        // both arguments define their fields, for the values of the event
+        let ids = (resolution.definitions())
+            .filter(|(_, def)| matches!(def.kind, DefKind::Field { name: "id", .. }))
+            .count();
+        assert_eq!(ids, 2);
+    }
+
+    #[test]
+    fn assignment_needs_a_definition() {
+        let (_, _, codes) = resolve_source("x = 1\n");
+        assert_eq!(codes, [ErrorCode::UndefinedName]);
+
+        let (_, _, codes) = resolve_source("x := 1\nx = 2\ny := x + z\n");
+        assert_eq!(codes, [ErrorCode::UndefinedName]);
+    }
+
+    #[test]
+    fn redefinition_in_same_scope() {
+        let (_, _, codes) = resolve_source("x := 1\nx := 2\n");
+        assert_eq!(codes, [ErrorCode::DuplicateDefinition]);
+
+        let (_, _, codes) = resolve_source("x := 1\nln f(Message) {\n\tx := x + 1\n}\n");
+        assert_eq!(codes, []);
+    }
+
+    #[test]
+    fn block_variables_are_local() {
+        let (_, _, codes) = resolve_source("ln f(Message) {\n\tx := 1\n}\ny := x\n");
+        assert_eq!(codes, [ErrorCode::UndefinedName]);
+    }
+
+    #[test]
+    fn closure_parameters() {
+        let (_, resolution, codes) = resolve_source("y := 1\nf := fn(x, y) x + y\nz := x\n");
+        assert_eq!(codes, [ErrorCode::UndefinedName]);
+
+        let scopes: Vec<_> = (resolution.definitions())
+            .map(|(_, def)| resolution.scope(def.scope).kind)
+            .collect();
+        assert_eq!(
+            scopes,
+            [
+                ScopeKind::Program,
+                ScopeKind::Function,
+                ScopeKind::Function,
+                ScopeKind::Program,
+                ScopeKind::Program,
+            ]
+        );
+
+        let (_, _, codes) = resolve_source("f := fn(x, x) x\n");
+        assert_eq!(codes, [ErrorCode::DuplicateDefinition]);
+    }
+
+    #[test]
+    fn misspelled_names_and_keywords_are_suggested() {
+        let suggestions = |src| {
+            let mut source_map = SourceMap::new();
+            let file = source_map.add("test.dqk", src);
+
+            let interner = Interner::new();
+            let mut diagnostics = Diagnostics::new();
+            let program = Parser::new(src, file, &interner, &mut diagnostics)
+                .parse::<Program>()
+                .unwrap();
+
+            resolve(&program, &interner, &mut diagnostics);
+
+            (diagnostics.iter())
+                .flat_map(|error| error.hints())
+                .map(|hint| hint.msg().to_string())
+                .filter(|msg| msg.starts_with("did you mean"))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            suggestions(
+                "count := 1
+x := cuont
+"
+            ),
+            ["did you mean 'count'?"]
+        );
+        assert_eq!(
+            suggestions(
+                "retrun 5
+"
+            ),
+            ["did you mean 'return'?"]
+        );
+        assert_eq!(
+            suggestions(
+                "x := wehre
+"
+            ),
+            ["did you mean 'where'?"]
+        );
+        // a visible name is closer than the keyword
+        assert_eq!(
+            suggestions(
+                "fo := 1
+x := fo1
+"
+            ),
+            ["did you mean 'fo'?"]
+        );
+        assert!(suggestions(
+            "x := unrelated
+"
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn unknown_expanded_type() {
+        let (_, _, codes) = resolve_source("ln f(Mesage) {\n\tchannel.send 'pong'\n}\n");
+        assert_eq!(codes, [ErrorCode::UnknownType, ErrorCode::UndefinedName]);
+    }
+}
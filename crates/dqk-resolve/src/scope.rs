@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use dqk_parser::{Span, Symbol};
+use dqk_schema::Entity;
+
+/// Index of a [`Definition`] in a [`Resolution`](crate::Resolution).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DefId(u32);
+
+impl DefId {
+    pub(crate) fn new(index: usize) -> Self {
+        Self(index as u32)
+    }
+
+    pub const fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Index of a [`Scope`] in a [`Resolution`](crate::Resolution).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScopeId(u32);
+
+impl ScopeId {
+    pub(crate) fn new(index: usize) -> Self {
+        Self(index as u32)
+    }
+
+    pub const fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefKind {
+    /// A variable declared with `:=`.
+    Variable,
+    /// A listener declared with `ln`.
+    Listener,
+    /// A named argument of a listener or function.
+    Argument,
+    /// A field of an expanded listener argument, like the `channel` of
+    /// `(Message)`.
+    Field { entity: Entity, name: &'static str },
+}
+
+#[derive(Clone, Debug)]
+pub struct Definition {
+    pub name: Symbol,
+    pub kind: DefKind,
+    /// Span of the identifier, or of the type of an expanded argument.
+    pub span: Span,
+    pub scope: ScopeId,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScopeKind {
+    /// The top level of a file.
+    Program,
+    /// The arguments and `where` clause of a listener.
+    Listener,
+    /// The parameters of a function.
+    Function,
+    /// The statements between `{` and `}`.
+    Block,
+}
+
+#[derive(Clone, Debug)]
+pub struct Scope {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    names: HashMap<Symbol, DefId>,
+}
+
+impl Scope {
+    pub(crate) fn new(kind: ScopeKind, parent: Option<ScopeId>) -> Self {
+        Self {
+            kind,
+            parent,
+            names: HashMap::new(),
+        }
+    }
+
+    /// The definition of `name` in this scope, not in its parents.
+    pub fn get(&self, name: Symbol) -> Option<DefId> {
+        self.names.get(&name).copied()
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = (Symbol, DefId)> + '_ {
+        self.names.iter().map(|(name, id)| (*name, *id))
+    }
+
+    pub(crate) fn insert(&mut self, name: Symbol, id: DefId) {
+        self.names.insert(name, id);
+    }
+}
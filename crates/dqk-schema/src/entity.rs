@@ -0,0 +1,169 @@
+use std::fmt;
+
+/// Type of a field of an [`Entity`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldType {
+    Integer,
+    String,
+    Bool,
+    Entity(Entity),
+}
+
+/// A field of an [`Entity`], like the `channel` of a `Message`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    pub ty: FieldType,
+}
+
+/// A method of an [`Entity`], like the `send` of a `Channel`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Method {
+    pub name: &'static str,
+    pub params: &'static [FieldType],
+    /// What the method returns, `None` if nothing.
+    pub ret: Option<FieldType>,
+}
+
+macro_rules! entities {
+    ($($(#[$attr:meta])* $entity:ident { $($field:ident: $ty:ident),* $(,)? })*) => {
+        /// A Discord entity, written as its name in scripts.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Entity {
+            $($(#[$attr])* $entity),*
+        }
+
+        impl Entity {
+            pub const ALL: &'static [Entity] = &[$(Self::$entity),*];
+
+            pub const fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$entity => stringify!($entity)),*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($entity) => Some(Self::$entity),)*
+                    _ => None,
+                }
+            }
+
+            pub const fn fields(&self) -> &'static [Field] {
+                match self {
+                    $(Self::$entity => &[
+                        $(Field {
+                            name: stringify!($field),
+                            ty: field_type!($ty),
+                        }),*
+                    ]),*
+                }
+            }
+        }
+    };
+}
+
+macro_rules! methods {
+    ($($entity:ident { $($method:ident($($param:ident),*) $(-> $ret:ident)?),* $(,)? })*) => {
+        impl Entity {
+            pub const fn methods(&self) -> &'static [Method] {
+                match self {
+                    $(Self::$entity => &[
+                        $(Method {
+                            name: stringify!($method),
+                            params: &[$(field_type!($param)),*],
+                            ret: method_ret!($($ret)?),
+                        }),*
+                    ],)*
+                    #[allow(unreachable_patterns)]
+                    _ => &[],
+                }
+            }
+        }
+    };
+}
+
+macro_rules! method_ret {
+    () => {
+        None
+    };
+    ($ret:ident) => {
+        Some(field_type!($ret))
+    };
+}
+
+macro_rules! field_type {
+    (Integer) => {
+        FieldType::Integer
+    };
+    (String) => {
+        FieldType::String
+    };
+    (Bool) => {
+        FieldType::Bool
+    };
+    ($entity:ident) => {
+        FieldType::Entity(Entity::$entity)
+    };
+}
+
+entities! {
+    /// A server.
+    Guild {
+        id: Integer,
+        name: String,
+        owner: User,
+    }
+    User {
+        id: Integer,
+        name: String,
+        is_bot: Bool,
+    }
+    Role {
+        id: Integer,
+        name: String,
+        guild: Guild,
+    }
+    Channel {
+        id: Integer,
+        name: String,
+        guild: Guild,
+    }
+    Message {
+        id: Integer,
+        contents: String,
+        author: User,
+        channel: Channel,
+        guild: Guild,
+        is_bot: Bool,
+    }
+}
+
+methods! {
+    User {
+        send(String),
+    }
+    Channel {
+        send(String),
+    }
+    Message {
+        reply(String),
+        delete(),
+    }
+}
+
+impl Entity {
+    pub fn field(&self, name: &str) -> Option<&'static Field> {
+        self.fields().iter().find(|field| field.name == name)
+    }
+
+    pub fn method(&self, name: &str) -> Option<&'static Method> {
+        self.methods().iter().find(|method| method.name == name)
+    }
+}
+
+impl fmt::Display for Entity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
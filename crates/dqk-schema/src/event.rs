@@ -0,0 +1,106 @@
+use std::fmt;
+
+use crate::Entity;
+
+macro_rules! events {
+    ($($(#[$attr:meta])* $name:ident => $event:ident($($payload:ident),*),)*) => {
+        /// A Discord gateway event a listener can be run for, written as its
+        /// snake case name in the default events of a listener like
+        /// `ln log_message<message_sent>(Message)`.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        pub enum Event {
+            $($(#[$attr])* $event),*
+        }
+
+        impl Event {
+            pub const ALL: &'static [Event] = &[$(Self::$event),*];
+
+            pub const fn name(&self) -> &'static str {
+                match self {
+                    $(Self::$event => stringify!($name)),*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($name) => Some(Self::$event),)*
+                    _ => None,
+                }
+            }
+
+            /// The entities the event comes with, which the arguments of a
+            /// listener for it can be.
+            pub const fn payload(&self) -> &'static [Entity] {
+                match self {
+                    $(Self::$event => &[$(Entity::$payload),*]),*
+                }
+            }
+        }
+    };
+}
+
+events! {
+    message_sent => MessageSent(Message),
+    message_edited => MessageEdited(Message),
+    message_deleted => MessageDeleted(Message),
+    /// A user reacted to a message.
+    reaction_added => ReactionAdded(Message, User),
+    reaction_removed => ReactionRemoved(Message, User),
+    member_joined => MemberJoined(User, Guild),
+    member_left => MemberLeft(User, Guild),
+    role_created => RoleCreated(Role),
+    role_deleted => RoleDeleted(Role),
+    channel_created => ChannelCreated(Channel),
+    channel_deleted => ChannelDeleted(Channel),
+    guild_joined => GuildJoined(Guild),
+}
+
+impl Event {
+    /// Whether a listener for the event can take `entity` as an argument.
+    pub fn provides(&self, entity: Entity) -> bool {
+        self.payload().contains(&entity)
+    }
+
+    /// Every event that provides `entity`.
+    pub fn providing(entity: Entity) -> impl Iterator<Item = Event> {
+        Self::ALL
+            .iter()
+            .copied()
+            .filter(move |event| event.provides(entity))
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip() {
+        for event in Event::ALL {
+            assert_eq!(Event::from_name(event.name()), Some(*event));
+        }
+
+        assert_eq!(Event::from_name("MessageSent"), None);
+    }
+
+    #[test]
+    fn events_providing_an_entity() {
+        let events: Vec<_> = Event::providing(Entity::User).collect();
+
+        assert_eq!(
+            events,
+            [
+                Event::ReactionAdded,
+                Event::ReactionRemoved,
+                Event::MemberJoined,
+                Event::MemberLeft,
+            ]
+        );
+    }
+}
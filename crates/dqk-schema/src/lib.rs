@@ -0,0 +1,10 @@
+#![forbid(unsafe_code)]
+
+//! The Discord entities scripts work with, like the `Message` a listener
+//! receives, their fields and methods, and the events listeners run for.
+
+mod entity;
+mod event;
+
+pub use entity::*;
+pub use event::*;
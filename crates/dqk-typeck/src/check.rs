@@ -0,0 +1,856 @@
+use std::collections::HashMap;
+
+use dqk_parser::{
+    suggest, BinOp, BinOpExpr, Block, CallExpr, ClosureExpr, DefaultEvent, Diagnostics, Error,
+    ErrorCode, Expr, Interner, InterpolatedExpr, ListExpr, ListenerArgument, LiteralExpr, LnStmt,
+    MemberExpr, Program, Span, Spanned, Stmt, Type,
+};
+use dqk_resolve::{DefId, DefKind, Resolution};
+use dqk_schema::{Entity, Event};
+
+use crate::{Bound, Infer, Scheme, Ty, TyVar};
+
+/// The types of the expressions and definitions of a program, see [`check`].
+///
+/// Types that could not be inferred, like the parameter of `id := fn(x) x`,
+/// are left as [`Ty::Var`].
+#[derive(Clone, Debug, Default)]
+pub struct Types {
+    /// Type of every expression, by its span.
+    exprs: HashMap<Span, Ty>,
+    defs: HashMap<DefId, Ty>,
+}
+
+impl Types {
+    pub fn expr(&self, expr: &Expr) -> Option<&Ty> {
+        self.exprs.get(&expr.span())
+    }
+
+    pub fn def(&self, id: DefId) -> Option<&Ty> {
+        self.defs.get(&id)
+    }
+}
+
+/// Type checks `program`, whose names are resolved by `resolution`.
+///
+/// Type errors are reported to `diagnostics`, expressions with errors get
+/// [`Ty::Error`].
+pub fn check(
+    program: &Program,
+    resolution: &Resolution,
+    interner: &Interner,
+    diagnostics: &mut Diagnostics,
+) -> Types {
+    let mut checker = Checker {
+        resolution,
+        interner,
+        diagnostics,
+        infer: Infer::default(),
+        exprs: HashMap::new(),
+        defs: HashMap::new(),
+        params: HashMap::new(),
+    };
+
+    for stmt in &program.stmts {
+        checker.stmt(stmt);
+    }
+
+    let infer = &checker.infer;
+
+    Types {
+        exprs: (checker.exprs.iter())
+            .map(|(span, ty)| (*span, infer.resolve(ty)))
+            .collect(),
+        defs: (checker.defs.iter().chain(&checker.params))
+            .map(|(id, scheme)| (*id, infer.resolve(&scheme.ty)))
+            .collect(),
+    }
+}
+
+struct Checker<'a> {
+    resolution: &'a Resolution,
+    interner: &'a Interner,
+    diagnostics: &'a mut Diagnostics,
+    infer: Infer,
+    /// Type of every expression, before the variables in it are resolved.
+    exprs: HashMap<Span, Ty>,
+    defs: HashMap<DefId, Scheme>,
+    /// Parameters of the closures that are checked, which are out of scope
+    /// and don't keep their types from being generalized.
+    params: HashMap<DefId, Scheme>,
+}
+
+impl Checker<'_> {
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Ln(ln) => self.listener(ln),
+            Stmt::AssignNew(stmt) => {
+                let ty = self.expr(&stmt.expr);
+
+                // only closures are generic, `xs := []` gets its item type from later uses
+                let scheme = match stmt.expr {
+                    Expr::Closure(_) => self.infer.generalize(&ty, &self.env()),
+                    _ => Scheme::mono(ty),
+                };
+
+                if let Some(id) = self.resolution.resolve(&stmt.ident) {
+                    self.defs.insert(id, scheme);
+                }
+            }
+            Stmt::Assign(stmt) => {
+                let ty = self.expr(&stmt.expr);
+
+                let id = match self.resolution.resolve(&stmt.ident) {
+                    Some(id) => id,
+                    None => return,
+                };
+
+                let expected = self.def(id, stmt.ident.span());
+
+                if self.infer.unify(&expected, &ty, stmt.expr.span()).is_err() {
+                    let def = self.resolution.definition(id);
+                    let name = self.interner.resolve(def.name);
+                    let declared = self.infer.resolve(&expected);
+
+                    let error = self.mismatch(&expected, &ty, stmt.expr.span()).with_hint(
+                        format!("'{}' is declared as {} here", name, declared),
+                        def.span,
+                    );
+
+                    self.diagnostics.emit(error);
+                }
+            }
+            Stmt::Expr(stmt) => {
+                self.expr(&stmt.expr);
+            }
+        }
+    }
+
+    fn listener(&mut self, ln: &LnStmt) {
+        let mut args = Vec::new();
+
+        for arg in ln.args.args.iter() {
+            let ty = match arg {
+                // unknown expanded types are reported by the resolver
+                ListenerArgument::Expanded(Type::Ident(ident)) => {
+                    Ty::from_name(self.interner.resolve(ident.value())).unwrap_or(Ty::Error)
+                }
+                ListenerArgument::Args(arg) => {
+                    let ty = self.ty(&arg.ty);
+
+                    if let Some(id) = self.resolution.resolve(&arg.ident) {
+                        self.defs.insert(id, Scheme::mono(ty.clone()));
+                    }
+
+                    ty
+                }
+            };
+
+            args.push((ty, arg.span()));
+        }
+
+        if let Some(default_events) = &*ln.default_events {
+            for default in default_events.events.iter() {
+                self.default_event(default, &args);
+            }
+        }
+
+        if let Some(where_clause) = &*ln.where_clause {
+            for bound in where_clause.bounds.iter() {
+                let ty = self.expr(bound);
+
+                if self.infer.unify(&Ty::Bool, &ty, bound.span()).is_err() {
+                    let found = self.infer.resolve(&ty);
+
+                    self.diagnostics.emit(
+                        Error::new(ErrorCode::MismatchedTypes, "where bounds must be Bool")
+                            .with_hint(format!("expected Bool, found {}", found), bound.span()),
+                    );
+                }
+            }
+        }
+
+        self.block(&ln.block);
+    }
+
+    /// Checks that a default event exists and comes with the arguments of the
+    /// listener.
+    fn default_event(&mut self, default: &DefaultEvent, args: &[(Ty, Span)]) {
+        let name = self.interner.resolve(default.ident.value());
+
+        let event = match Event::from_name(name) {
+            Some(event) => event,
+            None => {
+                let mut error =
+                    Error::new(ErrorCode::UnknownEvent, format!("unknown event '{}'", name))
+                        .with_hint("not a Discord event", default.span());
+
+                if let Some(suggestion) = suggest(name, Event::ALL.iter().map(Event::name)) {
+                    error = error.with_suggestion(suggestion, default.span());
+                }
+
+                self.diagnostics.emit(error);
+                return;
+            }
+        };
+
+        for (ty, span) in args {
+            let entity = match ty {
+                Ty::Entity(entity) if event.provides(*entity) => continue,
+                Ty::Error => continue,
+                Ty::Entity(entity) => Some(*entity),
+                _ => None,
+            };
+
+            let payload: Vec<_> = event.payload().iter().map(Entity::name).collect();
+
+            let mut error = Error::new(
+                ErrorCode::IncompatibleEvent,
+                format!("'{}' events don't come with {}", event, ty),
+            )
+            .with_hint(format!("this is {}", ty), *span)
+            .with_hint(
+                format!("'{}' comes with {}", event, payload.join(", ")),
+                default.span(),
+            );
+
+            if let Some(entity) = entity {
+                let events: Vec<_> = Event::providing(entity).map(|event| event.name()).collect();
+
+                error = error.with_help(format!(
+                    "listeners taking {} can run for {}",
+                    entity,
+                    events.join(", ")
+                ));
+            }
+
+            self.diagnostics.emit(error);
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        for stmt in block.stmts.iter() {
+            self.stmt(stmt);
+        }
+    }
+
+    /// The type written as `ty`.
+    fn ty(&mut self, ty: &Type) -> Ty {
+        let Type::Ident(ident) = ty;
+        let name = self.interner.resolve(ident.value());
+
+        if let Some(ty) = Ty::from_name(name) {
+            return ty;
+        }
+
+        let mut error = Error::new(
+            ErrorCode::UnknownType,
+            format!("cannot find type '{}'", name),
+        )
+        .with_hint("not a type", ty.span());
+
+        if let Some(suggestion) = suggest(name, Ty::names()) {
+            error = error.with_suggestion(suggestion, ty.span());
+        }
+
+        self.diagnostics.emit(error);
+
+        Ty::Error
+    }
+
+    /// The type of a definition, from its declaration or the schema.
+    /// Type of the definition `id`, used at `span`.
+    fn def(&mut self, id: DefId, span: Span) -> Ty {
+        if let Some(scheme) = self.defs.get(&id) {
+            let scheme = scheme.clone();
+            return self.infer.instantiate(&scheme);
+        }
+
+        let def = self.resolution.definition(id);
+
+        match def.kind {
+            DefKind::Field { entity, name } => entity
+                .field(name)
+                .map_or(Ty::Error, |field| field.ty.into()),
+            DefKind::Listener => {
+                let name = self.interner.resolve(def.name);
+
+                self.diagnostics.emit(
+                    Error::new(ErrorCode::NotAValue, "listeners are not values")
+                        .with_hint(format!("'{}' is a listener", name), span)
+                        .with_hint("it is declared here", def.span),
+                );
+
+                Ty::Error
+            }
+            _ => Ty::Error,
+        }
+    }
+
+    /// Variables the types of the definitions depend on, which must not be
+    /// generalized.
+    fn env(&self) -> Vec<TyVar> {
+        let mut vars = Vec::new();
+
+        for scheme in self.defs.values().filter(|scheme| scheme.vars.is_empty()) {
+            self.infer.free_vars(&scheme.ty, &mut vars);
+        }
+
+        vars
+    }
+
+    /// A type mismatch at `span`, pointing at where `expected` was inferred.
+    fn mismatch(&self, expected: &Ty, found: &Ty, span: Span) -> Error {
+        let found = self.infer.resolve(found);
+        let error = Error::new(ErrorCode::MismatchedTypes, "mismatched types");
+
+        // a generic parameter that an operator was applied to
+        if let Some((bound, origin)) = self.infer.bound(expected) {
+            return error
+                .with_hint(format!("expected {}, found {}", bound, found), span)
+                .with_hint(format!("{} is required by this", bound), origin);
+        }
+
+        let resolved = self.infer.resolve(expected);
+        let mut error = error.with_hint(format!("expected {}, found {}", resolved, found), span);
+
+        if let Some(origin) = self.infer.origin(expected).filter(|origin| *origin != span) {
+            error = error.with_hint(format!("{} was inferred from this", resolved), origin);
+        }
+
+        error
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Ty {
+        let ty = match expr {
+            Expr::Paren(paren) => self.expr(&paren.expr),
+            Expr::Literal(LiteralExpr::Integer(_)) => Ty::Integer,
+            Expr::Literal(LiteralExpr::Float(_)) => Ty::Float,
+            Expr::Literal(LiteralExpr::String(_)) => Ty::String,
+            Expr::Interpolated(interpolated) => self.interpolated(interpolated),
+            Expr::List(list) => self.list(list),
+            Expr::Closure(closure) => self.closure(closure),
+            Expr::Variable(ident) => match self.resolution.resolve(ident) {
+                Some(id) => self.def(id, ident.span()),
+                None => Ty::Error,
+            },
+            Expr::Member(member) => self.member(member),
+            Expr::Call(call) => self.call(call),
+            Expr::BinOp(bin_op) => self.bin_op(bin_op),
+        };
+
+        self.exprs.insert(expr.span(), ty.clone());
+
+        ty
+    }
+
+    /// Values of any type can be interpolated, they are converted to strings.
+    fn interpolated(&mut self, interpolated: &InterpolatedExpr) -> Ty {
+        for part in interpolated.parts.iter() {
+            self.expr(&part.expr);
+        }
+
+        Ty::String
+    }
+
+    /// Every item must have the type of the first.
+    fn list(&mut self, list: &ListExpr) -> Ty {
+        let item = self.infer.fresh();
+
+        for expr in list.items.iter() {
+            let ty = self.expr(expr);
+
+            if self.infer.unify(&item, &ty, expr.span()).is_err() {
+                let error = self.mismatch(&item, &ty, expr.span());
+                self.diagnostics.emit(error);
+            }
+        }
+
+        Ty::List(Box::new(item))
+    }
+
+    /// The parameters get their types from how the body uses them.
+    fn closure(&mut self, closure: &ClosureExpr) -> Ty {
+        let params: Vec<_> = (closure.params.iter())
+            .map(|param| {
+                let ty = self.infer.fresh();
+
+                if let Some(id) = self.resolution.resolve(param) {
+                    self.defs.insert(id, Scheme::mono(ty.clone()));
+                }
+
+                ty
+            })
+            .collect();
+
+        let ret = self.expr(&closure.body);
+
+        for param in closure.params.iter() {
+            if let Some(id) = self.resolution.resolve(param) {
+                let scheme = self.defs.remove(&id).unwrap();
+                self.params.insert(id, scheme);
+            }
+        }
+
+        Ty::Function {
+            params,
+            ret: Box::new(ret),
+        }
+    }
+
+    /// A field, or a method of a Discord type. Methods without parameters are
+    /// called right away, like `message.delete`.
+    fn member(&mut self, member: &MemberExpr) -> Ty {
+        let ty = self.expr(&member.expr);
+        let ty = self.infer.shallow(&ty);
+        let name = self.interner.resolve(member.ident.value());
+
+        let entity = match ty {
+            Ty::Entity(entity) => entity,
+            Ty::Error => return Ty::Error,
+            Ty::Var(_) => {
+                self.diagnostics.emit(
+                    Error::new(ErrorCode::CannotInfer, "cannot infer type").with_hint(
+                        format!("the type of this must be known to access '{}'", name),
+                        member.expr.span(),
+                    ),
+                );
+
+                return Ty::Error;
+            }
+            ty => {
+                let ty = self.infer.resolve(&ty);
+
+                self.diagnostics.emit(
+                    Error::new(
+                        ErrorCode::UnknownMember,
+                        format!("no member '{}' on type {}", name, ty),
+                    )
+                    .with_hint(
+                        format!("this is {}, which has no members", ty),
+                        member.expr.span(),
+                    ),
+                );
+
+                return Ty::Error;
+            }
+        };
+
+        if let Some(field) = entity.field(name) {
+            return field.ty.into();
+        }
+
+        if let Some(method) = entity.method(name) {
+            return match Ty::from(method) {
+                Ty::Function { params, ret } if params.is_empty() => *ret,
+                ty => ty,
+            };
+        }
+
+        let members = (entity.fields().iter().map(|field| field.name))
+            .chain(entity.methods().iter().map(|method| method.name));
+
+        let mut error = Error::new(
+            ErrorCode::UnknownMember,
+            format!("no member '{}' on type {}", name, entity),
+        )
+        .with_hint(format!("unknown member of {}", entity), member.ident.span());
+
+        if let Some(suggestion) = suggest(name, members) {
+            error = error.with_suggestion(suggestion, member.ident.span());
+        }
+
+        self.diagnostics.emit(error);
+
+        Ty::Error
+    }
+
+    fn call(&mut self, call: &CallExpr) -> Ty {
+        let callee = self.expr(&call.expr);
+        let args: Vec<_> = (call.args.iter())
+            .map(|arg| (self.expr(arg), arg.span()))
+            .collect();
+
+        let (params, ret) = match self.infer.shallow(&callee) {
+            Ty::Function { params, ret } => (params, ret),
+            Ty::Error => return Ty::Error,
+            // calling an argument of a closure makes it a function
+            Ty::Var(_) => {
+                let params: Vec<_> = args.iter().map(|_| self.infer.fresh()).collect();
+                let ret = Box::new(self.infer.fresh());
+
+                let ty = Ty::Function {
+                    params: params.clone(),
+                    ret: ret.clone(),
+                };
+                let _ = self.infer.unify(&callee, &ty, call.expr.span());
+
+                (params, ret)
+            }
+            ty => {
+                let ty = self.infer.resolve(&ty);
+
+                self.diagnostics.emit(
+                    Error::new(ErrorCode::InvalidCall, format!("{} is not callable", ty))
+                        .with_hint(format!("this is {}, not a function", ty), call.expr.span()),
+                );
+
+                return Ty::Error;
+            }
+        };
+
+        if params.len() != args.len() {
+            let s = if params.len() == 1 { "" } else { "s" };
+            let ty = self.infer.resolve(&Ty::Function {
+                params: params.clone(),
+                ret: ret.clone(),
+            });
+
+            self.diagnostics.emit(
+                Error::new(
+                    ErrorCode::InvalidCall,
+                    format!(
+                        "expected {} argument{}, found {}",
+                        params.len(),
+                        s,
+                        args.len()
+                    ),
+                )
+                .with_hint(format!("this takes {}", ty), call.expr.span()),
+            );
+
+            return *ret;
+        }
+
+        for (param, (arg, span)) in params.iter().zip(args) {
+            if self.infer.unify(param, &arg, span).is_err() {
+                let error = self.mismatch(param, &arg, span);
+                self.diagnostics.emit(error);
+            }
+        }
+
+        *ret
+    }
+
+    fn bin_op(&mut self, expr: &BinOpExpr) -> Ty {
+        let lhs = self.expr(&expr.lhs);
+        let rhs = self.expr(&expr.rhs);
+
+        let mut lhs = self.infer.shallow(&lhs);
+        let mut rhs = self.infer.shallow(&rhs);
+
+        // an operand that isn't known yet gets the type of the other one
+        let unknown = matches!(lhs, Ty::Var(_)) || matches!(rhs, Ty::Var(_));
+
+        if unknown && self.infer.unify(&lhs, &rhs, expr.span()).is_ok() {
+            lhs = self.infer.shallow(&lhs);
+            rhs = self.infer.shallow(&rhs);
+        }
+
+        if lhs == Ty::Error || rhs == Ty::Error {
+            return match expr.op {
+                BinOp::Eq(_) => Ty::Bool,
+                _ => Ty::Error,
+            };
+        }
+
+        let ty = match (&expr.op, &lhs, &rhs) {
+            (BinOp::Eq(_), lhs, rhs) if lhs.is_numeric() && rhs.is_numeric() => Some(Ty::Bool),
+            // lists are compared item by item, so their items must unify too
+            (BinOp::Eq(_), lhs, rhs) => self
+                .infer
+                .unify(lhs, rhs, expr.span())
+                .ok()
+                .map(|()| Ty::Bool),
+            // both are the same variable, like the `x + x` of `fn(x) x + x`,
+            // which can only become a type the operator allows
+            (op, Ty::Var(var), Ty::Var(other)) if var == other => {
+                let bound = match op {
+                    BinOp::Add(_) => Bound::Add,
+                    _ => Bound::Numeric,
+                };
+
+                self.infer.constrain(*var, bound, expr.span());
+                Some(lhs.clone())
+            }
+            (BinOp::Add(_), Ty::String, Ty::String) => Some(Ty::String),
+            (_, Ty::Integer, Ty::Integer) => Some(Ty::Integer),
+            (_, lhs, rhs) if lhs.is_numeric() && rhs.is_numeric() => Some(Ty::Float),
+            _ => None,
+        };
+
+        ty.unwrap_or_else(|| {
+            let op = match expr.op {
+                BinOp::Add(_) => "+",
+                BinOp::Sub(_) => "-",
+                BinOp::Mul(_) => "*",
+                BinOp::Div(_) => "/",
+                BinOp::Eq(_) => "==",
+            };
+
+            let lhs = self.infer.resolve(&lhs);
+            let rhs = self.infer.resolve(&rhs);
+
+            self.diagnostics.emit(
+                Error::new(
+                    ErrorCode::MismatchedTypes,
+                    format!("cannot apply '{}' to {} and {}", op, lhs, rhs),
+                )
+                .with_hint(format!("this is {}", lhs), expr.lhs.span())
+                .with_hint(format!("this is {}", rhs), expr.rhs.span()),
+            );
+
+            Ty::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dqk_parser::{Parser, SourceMap};
+
+    use super::*;
+
+    fn check_source(src: &str) -> (Program, Types, Vec<ErrorCode>) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<Program>().unwrap();
+        let resolution = dqk_resolve::resolve(&program, &interner, &mut diagnostics);
+        assert!(diagnostics.is_empty());
+
+        let types = check(&program, &resolution, &interner, &mut diagnostics);
+        let codes = diagnostics.iter().map(|error| error.code()).collect();
+
+        (program, types, codes)
+    }
+
+    fn codes(src: &str) -> Vec<ErrorCode> {
+        check_source(src).2
+    }
+
+    #[test]
+    fn ping_checks() {
+        assert_eq!(codes(include_str!("../../../tests/ping.dqk")), []);
+    }
+
+    #[test]
+    fn declarations_have_the_type_of_their_value() {
+        let (program, types, codes) = check_source("x := 1 + 2.5\ny := x == 3\n");
+
+        assert_eq!(codes, []);
+
+        let ty = |index: usize| match &program.stmts[index] {
+            Stmt::AssignNew(stmt) => types.expr(&stmt.expr).unwrap().clone(),
+            stmt => panic!("expected declaration, found {:?}", stmt),
+        };
+
+        assert_eq!(ty(0), Ty::Float);
+        assert_eq!(ty(1), Ty::Bool);
+    }
+
+    #[test]
+    fn interpolations_are_strings() {
+        let (program, types, codes) = check_source("n := 2\nx := 'n is {n + 1}' + '!'\n");
+
+        assert_eq!(codes, []);
+
+        match &program.stmts[1] {
+            Stmt::AssignNew(stmt) => assert_eq!(types.expr(&stmt.expr), Some(&Ty::String)),
+            stmt => panic!("expected declaration, found {:?}", stmt),
+        }
+
+        assert_eq!(
+            check_source("x := '{1 - 'a'}'\n").2,
+            [ErrorCode::MismatchedTypes]
+        );
+    }
+
+    #[test]
+    fn operands_and_assignments() {
+        assert_eq!(codes("x := 'a' - 1\n"), [ErrorCode::MismatchedTypes]);
+        assert_eq!(
+            codes("x := 'a' + 'b'\nx = 2\n"),
+            [ErrorCode::MismatchedTypes]
+        );
+        // the error is only reported once, not for every use of its result
+        assert_eq!(
+            codes("x := 'a' * 2\ny := x + 1\n"),
+            [ErrorCode::MismatchedTypes]
+        );
+    }
+
+    #[test]
+    fn equality_unifies_the_operands() {
+        assert_eq!(
+            codes("x := [1] == [1]\ny := [] == [[2.5]]\nz := 1 == 2.5\n"),
+            []
+        );
+        assert_eq!(codes("x := [1] == ['a']\n"), [ErrorCode::MismatchedTypes]);
+    }
+
+    #[test]
+    fn where_bounds_are_bool() {
+        let src = "ln f<message_sent>(Message)\nwhere\n\tcontents,\n\tis_bot,\n{\n}\n";
+        assert_eq!(codes(src), [ErrorCode::MismatchedTypes]);
+    }
+
+    #[test]
+    fn members_and_calls() {
+        let src = "ln f<message_sent>(Message) {\n\tauthor.nmae\n\tchannel.send 1\n\tchannel.send 'a', 'b'\n\tid.send\n}\n";
+
+        assert_eq!(
+            codes(src),
+            [
+                ErrorCode::UnknownMember,
+                ErrorCode::MismatchedTypes,
+                ErrorCode::InvalidCall,
+                ErrorCode::UnknownMember,
+            ]
+        );
+    }
+
+    fn def_types(src: &str) -> Vec<String> {
+        let (program, types, codes) = check_source(src);
+        assert_eq!(codes, []);
+
+        (program.stmts.iter())
+            .map(|stmt| match stmt {
+                Stmt::AssignNew(stmt) => types.expr(&stmt.expr).unwrap().to_string(),
+                stmt => panic!("expected declaration, found {:?}", stmt),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn lists_infer_their_item_type() {
+        assert_eq!(
+            def_types("xs := [1, 2]\nys := []\nzs := [[1.5], []]\n"),
+            ["[Integer]", "[T1]", "[[Float]]"]
+        );
+        assert_eq!(codes("xs := [1, 'a']\n"), [ErrorCode::MismatchedTypes]);
+    }
+
+    #[test]
+    fn closures_are_generic() {
+        let types = def_types(
+            "add := fn(a, b) a + b\nx := add 1, 2\ny := add 'a', 'b'\napply := fn(f, x) f x\nz := apply (fn(a) a * 2), 3\n",
+        );
+
+        assert_eq!(types[1..3], ["Integer", "String"]);
+        assert_eq!(types[4], "Integer");
+    }
+
+    #[test]
+    fn generic_operands_are_bounded() {
+        assert_eq!(
+            codes("add := fn(a, b) a + b\nx := add [1], [2]\n"),
+            [ErrorCode::MismatchedTypes, ErrorCode::MismatchedTypes]
+        );
+        assert_eq!(
+            codes("neg := fn(a) 0 - a\nsub := fn(a) a - a\nx := sub 'a'\n"),
+            [ErrorCode::MismatchedTypes]
+        );
+        // the bounds of both operands are joined, `-` only allows numbers
+        assert_eq!(
+            codes("f := fn(a, b) (a + b) - a\nx := f 'a', 'b'\ny := f 1.5, 2.5\n"),
+            [ErrorCode::MismatchedTypes, ErrorCode::MismatchedTypes]
+        );
+
+        let types = def_types("twice := fn(a) a + a\nx := twice 'a'\ny := twice 2\n");
+        assert_eq!(types[1..], ["String", "Integer"]);
+    }
+
+    #[test]
+    fn mismatch_points_at_the_inference() {
+        let src = "inc := fn(a) a + 1\nx := inc 'one'\n";
+
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<Program>().unwrap();
+        let resolution = dqk_resolve::resolve(&program, &interner, &mut diagnostics);
+        check(&program, &resolution, &interner, &mut diagnostics);
+
+        let error = diagnostics.iter().next().unwrap();
+        let hints: Vec<_> = (error.hints().iter())
+            .map(|hint| (hint.msg(), source_map.snippet(hint.span())))
+            .collect();
+
+        assert_eq!(
+            hints,
+            [
+                ("expected Integer, found String", "'one'"),
+                ("Integer was inferred from this", "a + 1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn members_need_a_known_type() {
+        assert_eq!(
+            codes("author_of := fn(message) message.author\n"),
+            [ErrorCode::CannotInfer]
+        );
+        assert_eq!(codes("f := fn(x) x x\n"), [ErrorCode::MismatchedTypes]);
+    }
+
+    #[test]
+    fn listeners_are_not_values() {
+        let src = "ln f(Message) {\n}\nx := f\nf 1\nf = 2\n";
+
+        let mut source_map = SourceMap::new();
+        let file = source_map.add("test.dqk", src);
+
+        let interner = Interner::new();
+        let mut diagnostics = Diagnostics::new();
+        let mut parser = Parser::new(src, file, &interner, &mut diagnostics);
+
+        let program = parser.parse::<Program>().unwrap();
+        let resolution = dqk_resolve::resolve(&program, &interner, &mut diagnostics);
+        check(&program, &resolution, &interner, &mut diagnostics);
+
+        let errors: Vec<_> = (diagnostics.iter())
+            .map(|error| {
+                let hints: Vec<_> = (error.hints().iter())
+                    .map(|hint| source_map.line_col(hint.span()).line)
+                    .collect();
+
+                (error.code(), hints)
+            })
+            .collect();
+
+        assert_eq!(
+            errors,
+            [
+                (ErrorCode::NotAValue, vec![3, 1]),
+                (ErrorCode::NotAValue, vec![4, 1]),
+                (ErrorCode::NotAValue, vec![5, 1]),
+            ]
+        );
+    }
+
+    #[test]
+    fn default_events() {
+        assert_eq!(
+            codes("ln f<message_sent, message_edited>(Message) {\n}\n"),
+            []
+        );
+        assert_eq!(
+            codes("ln f<mesage_sent>(Message) {\n}\n"),
+            [ErrorCode::UnknownEvent]
+        );
+        assert_eq!(
+            codes("ln f<member_joined, message_sent>(User) {\n}\n"),
+            [ErrorCode::IncompatibleEvent]
+        );
+    }
+}
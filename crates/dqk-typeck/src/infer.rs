@@ -0,0 +1,278 @@
+use std::{collections::HashMap, fmt};
+
+use dqk_parser::Span;
+
+use crate::{Ty, TyVar};
+
+/// A type variable bound to a type, and the span that caused it.
+#[derive(Clone, Debug)]
+struct Binding {
+    ty: Ty,
+    origin: Span,
+}
+
+/// What the operators applied to an unbound variable allow it to become.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Bound {
+    /// `+`, numbers and strings.
+    Add,
+    /// `-`, `*` and `/`, numbers.
+    Numeric,
+}
+
+impl Bound {
+    fn allows(self, ty: &Ty) -> bool {
+        match self {
+            Self::Add => ty.is_numeric() || *ty == Ty::String,
+            Self::Numeric => ty.is_numeric(),
+        }
+    }
+
+    /// The bound of a variable that has both bounds.
+    fn meet(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Add, Self::Add) => Self::Add,
+            _ => Self::Numeric,
+        }
+    }
+}
+
+impl fmt::Display for Bound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Add => f.write_str("a number or String"),
+            Self::Numeric => f.write_str("a number"),
+        }
+    }
+}
+
+/// The type of a definition, generic over `vars`.
+///
+/// Closures declared with `:=` are generic over the type variables that
+/// nothing else constrains, so `id := fn(x) x` can be called with any type.
+#[derive(Clone, Debug)]
+pub(crate) struct Scheme {
+    pub(crate) vars: Vec<TyVar>,
+    pub(crate) ty: Ty,
+}
+
+impl Scheme {
+    /// A type that is not generic.
+    pub(crate) fn mono(ty: Ty) -> Self {
+        Self {
+            vars: Vec::new(),
+            ty,
+        }
+    }
+}
+
+/// Type variables and the types they are bound to, solved by unification.
+#[derive(Default)]
+pub(crate) struct Infer {
+    vars: Vec<Option<Binding>>,
+    /// Bounds of the unbound variables, and the operator that caused them.
+    bounds: HashMap<TyVar, (Bound, Span)>,
+}
+
+impl Infer {
+    pub(crate) fn fresh(&mut self) -> Ty {
+        self.vars.push(None);
+        Ty::Var(TyVar::new(self.vars.len() - 1))
+    }
+
+    /// Follows the bindings of `ty` until it is a type or an unbound variable.
+    pub(crate) fn shallow(&self, ty: &Ty) -> Ty {
+        match ty {
+            Ty::Var(var) => match &self.vars[var.index()] {
+                Some(binding) => self.shallow(&binding.ty),
+                None => ty.clone(),
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    /// `ty` with every bound variable replaced by its type.
+    pub(crate) fn resolve(&self, ty: &Ty) -> Ty {
+        match self.shallow(ty) {
+            Ty::List(item) => Ty::List(Box::new(self.resolve(&item))),
+            Ty::Function { params, ret } => Ty::Function {
+                params: params.iter().map(|param| self.resolve(param)).collect(),
+                ret: Box::new(self.resolve(&ret)),
+            },
+            ty => ty,
+        }
+    }
+
+    /// The span whose type bound `ty`, if `ty` is a bound variable.
+    pub(crate) fn origin(&self, ty: &Ty) -> Option<Span> {
+        match ty {
+            Ty::Var(var) => self.vars[var.index()]
+                .as_ref()
+                .map(|binding| binding.origin),
+            _ => None,
+        }
+    }
+
+    /// The bound of `ty` and the span that caused it, if `ty` is an unbound
+    /// variable with one.
+    pub(crate) fn bound(&self, ty: &Ty) -> Option<(Bound, Span)> {
+        match self.shallow(ty) {
+            Ty::Var(var) => self.bounds.get(&var).copied(),
+            _ => None,
+        }
+    }
+
+    /// Restricts the unbound variable `var` to the types `bound` allows.
+    pub(crate) fn constrain(&mut self, var: TyVar, bound: Bound, origin: Span) {
+        let bound = match self.bounds.get(&var) {
+            Some(&(other, origin)) => (bound.meet(other), origin),
+            None => (bound, origin),
+        };
+
+        self.bounds.insert(var, bound);
+    }
+
+    /// Makes `expected` and `found` the same type, binding variables to the
+    /// span `origin`. Fails if they are different types.
+    pub(crate) fn unify(&mut self, expected: &Ty, found: &Ty, origin: Span) -> Result<(), ()> {
+        let expected = self.shallow(expected);
+        let found = self.shallow(found);
+
+        match (expected, found) {
+            (Ty::Error, _) | (_, Ty::Error) => Ok(()),
+            (Ty::Var(a), Ty::Var(b)) if a == b => Ok(()),
+            (Ty::Var(var), ty) | (ty, Ty::Var(var)) => {
+                // `x := fn(f) f f` would have an infinite type
+                if self.occurs(var, &ty) {
+                    return Err(());
+                }
+
+                if let Some((bound, origin)) = self.bounds.remove(&var) {
+                    match ty {
+                        Ty::Var(other) => self.constrain(other, bound, origin),
+                        ref ty if !bound.allows(ty) => {
+                            self.bounds.insert(var, (bound, origin));
+                            return Err(());
+                        }
+                        _ => {}
+                    }
+                }
+
+                self.vars[var.index()] = Some(Binding { ty, origin });
+                Ok(())
+            }
+            (Ty::List(expected), Ty::List(found)) => self.unify(&expected, &found, origin),
+            (
+                Ty::Function {
+                    params: expected_params,
+                    ret: expected_ret,
+                },
+                Ty::Function {
+                    params: found_params,
+                    ret: found_ret,
+                },
+            ) => {
+                if expected_params.len() != found_params.len() {
+                    return Err(());
+                }
+
+                for (expected, found) in expected_params.iter().zip(&found_params) {
+                    self.unify(expected, found, origin)?;
+                }
+
+                self.unify(&expected_ret, &found_ret, origin)
+            }
+            (expected, found) if expected == found => Ok(()),
+            _ => Err(()),
+        }
+    }
+
+    fn occurs(&self, var: TyVar, ty: &Ty) -> bool {
+        match self.shallow(ty) {
+            Ty::Var(other) => other == var,
+            Ty::List(item) => self.occurs(var, &item),
+            Ty::Function { params, ret } => {
+                params.iter().any(|param| self.occurs(var, param)) || self.occurs(var, &ret)
+            }
+            _ => false,
+        }
+    }
+
+    /// The unbound variables in `ty`.
+    pub(crate) fn free_vars(&self, ty: &Ty, vars: &mut Vec<TyVar>) {
+        match self.shallow(ty) {
+            Ty::Var(var) if !vars.contains(&var) => vars.push(var),
+            Ty::List(item) => self.free_vars(&item, vars),
+            Ty::Function { params, ret } => {
+                for param in &params {
+                    self.free_vars(param, vars);
+                }
+
+                self.free_vars(&ret, vars);
+            }
+            _ => {}
+        }
+    }
+
+    /// A scheme generic over the free variables of `ty` that aren't in `env`.
+    pub(crate) fn generalize(&self, ty: &Ty, env: &[TyVar]) -> Scheme {
+        let mut vars = Vec::new();
+        self.free_vars(ty, &mut vars);
+        vars.retain(|var| !env.contains(var));
+
+        Scheme {
+            vars,
+            ty: ty.clone(),
+        }
+    }
+
+    /// The type of `scheme` with fresh variables for its generic ones.
+    pub(crate) fn instantiate(&mut self, scheme: &Scheme) -> Ty {
+        if scheme.vars.is_empty() {
+            return scheme.ty.clone();
+        }
+
+        let mut fresh = HashMap::new();
+
+        for &var in &scheme.vars {
+            let ty = self.fresh();
+
+            if let (Ty::Var(new), Some(&(bound, origin))) = (&ty, self.bounds.get(&var)) {
+                self.bounds.insert(*new, (bound, origin));
+            }
+
+            fresh.insert(var, ty);
+        }
+
+        self.substitute(&scheme.ty, &fresh)
+    }
+
+    /// Replaces the variables in `vars`. Other bound variables are kept where
+    /// possible, so errors can still point at their origin.
+    fn substitute(&self, ty: &Ty, vars: &HashMap<TyVar, Ty>) -> Ty {
+        match ty {
+            Ty::Var(var) => match (vars.get(var), &self.vars[var.index()]) {
+                (Some(fresh), _) => fresh.clone(),
+                (None, Some(binding)) if self.mentions(&binding.ty, vars) => {
+                    self.substitute(&binding.ty, vars)
+                }
+                (None, _) => ty.clone(),
+            },
+            Ty::List(item) => Ty::List(Box::new(self.substitute(item, vars))),
+            Ty::Function { params, ret } => Ty::Function {
+                params: (params.iter())
+                    .map(|param| self.substitute(param, vars))
+                    .collect(),
+                ret: Box::new(self.substitute(ret, vars)),
+            },
+            ty => ty.clone(),
+        }
+    }
+
+    fn mentions(&self, ty: &Ty, vars: &HashMap<TyVar, Ty>) -> bool {
+        let mut free = Vec::new();
+        self.free_vars(ty, &mut free);
+
+        free.iter().any(|var| vars.contains_key(var))
+    }
+}
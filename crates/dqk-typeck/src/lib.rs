@@ -0,0 +1,20 @@
+#![forbid(unsafe_code)]
+
+//! Type checking of resolved dqk programs.
+//!
+//! Every expression gets a [`Ty`]. Variables declared with `:=` have the type
+//! of their value, the fields of an expanded listener argument and the
+//! members of Discord types get theirs from the schema. Types that aren't
+//! written anywhere, like the item type of a list or the parameters of a
+//! closure, are inferred by unification, and closures declared with `:=` are
+//! generic over what their body doesn't constrain. The checker reports
+//! operators applied to the wrong types, `where` bounds that aren't `Bool`,
+//! unknown members and calls with the wrong arguments.
+
+mod check;
+mod infer;
+mod ty;
+
+pub use check::*;
+pub(crate) use infer::*;
+pub use ty::*;
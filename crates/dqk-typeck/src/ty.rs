@@ -0,0 +1,117 @@
+use std::fmt;
+
+use dqk_schema::{Entity, FieldType, Method};
+
+/// A type that is not known yet, solved by inference.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TyVar(u32);
+
+impl TyVar {
+    pub(crate) fn new(index: usize) -> Self {
+        Self(index as u32)
+    }
+
+    pub const fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The type of an expression or variable.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Ty {
+    Integer,
+    Float,
+    String,
+    Bool,
+    /// No value, what calling a method like `channel.send` results in.
+    Unit,
+    Entity(Entity),
+    List(Box<Ty>),
+    Function {
+        params: Vec<Ty>,
+        ret: Box<Ty>,
+    },
+    /// A type that is not inferred yet, or a generic one.
+    Var(TyVar),
+    /// The type of an expression with an error. It is compatible with every
+    /// type, so an error is only reported once.
+    Error,
+}
+
+impl Ty {
+    /// The type written as `name`, like `Integer` or `Message`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Integer" => Some(Self::Integer),
+            "Float" => Some(Self::Float),
+            "String" => Some(Self::String),
+            "Bool" => Some(Self::Bool),
+            _ => Entity::from_name(name).map(Self::Entity),
+        }
+    }
+
+    /// Names of every type that can be written.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        ["Integer", "Float", "String", "Bool"]
+            .into_iter()
+            .chain(Entity::ALL.iter().map(Entity::name))
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, Self::Integer | Self::Float)
+    }
+}
+
+impl From<FieldType> for Ty {
+    fn from(ty: FieldType) -> Self {
+        match ty {
+            FieldType::Integer => Self::Integer,
+            FieldType::String => Self::String,
+            FieldType::Bool => Self::Bool,
+            FieldType::Entity(entity) => Self::Entity(entity),
+        }
+    }
+}
+
+impl From<&Method> for Ty {
+    fn from(method: &Method) -> Self {
+        Self::Function {
+            params: method.params.iter().map(|&param| param.into()).collect(),
+            ret: Box::new(method.ret.map_or(Ty::Unit, Ty::from)),
+        }
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Integer => f.write_str("Integer"),
+            Self::Float => f.write_str("Float"),
+            Self::String => f.write_str("String"),
+            Self::Bool => f.write_str("Bool"),
+            Self::Unit => f.write_str("()"),
+            Self::Entity(entity) => write!(f, "{}", entity),
+            Self::List(item) => write!(f, "[{}]", item),
+            Self::Function { params, ret } => {
+                f.write_str("fn(")?;
+
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    write!(f, "{}", param)?;
+                }
+
+                f.write_str(")")?;
+
+                match **ret {
+                    Ty::Unit => Ok(()),
+                    ref ret => write!(f, " -> {}", ret),
+                }
+            }
+            Self::Var(var) => write!(f, "T{}", var.index()),
+            Self::Error => f.write_str("{error}"),
+        }
+    }
+}
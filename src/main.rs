@@ -1,23 +1,394 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
 
 use clap::StructOpt;
-use dqk_parser::{Parser, Stmt, StringAllocator};
+use dqk_interp::{EventData, Interpreter, Vm};
+use dqk_parser::{
+    Diagnostics, ErrorCode, FileId, Interner, Lexer, Program, Severity, SourceMap, Spanned,
+    AST_SCHEMA_VERSION,
+};
+use dqk_resolve::Resolution;
+use serde::Serialize;
 
 #[derive(clap::Parser)]
 #[clap(author, version, about, long_about = None)]
 struct Args {
-    /// Path to source
-    source: PathBuf,
+    /// Turn every diagnostic of the group into an error
+    #[clap(long, arg_enum, global = true)]
+    deny: Vec<LintGroup>,
+    /// Silence every diagnostic of the group
+    #[clap(long, arg_enum, global = true)]
+    allow: Vec<LintGroup>,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, clap::ArgEnum)]
+enum LintGroup {
+    Warnings,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Parse a source file and print the syntax tree
+    Parse {
+        /// Path to a source file or a directory of them
+        source: PathBuf,
+        /// Print the syntax tree as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Check source files for errors without running them
+    Check {
+        /// Path to a source file or a directory of them
+        source: PathBuf,
+    },
+    /// Run a source file, and its listeners for an event
+    Run {
+        /// Path to a source file
+        source: PathBuf,
+        /// Path to a JSON file with an event to run the listeners for
+        #[clap(long)]
+        event: Option<PathBuf>,
+        /// Compile the source file to bytecode and run it in a VM
+        #[clap(long)]
+        vm: bool,
+    },
+    /// Compile a source file to bytecode and print its instructions
+    Disasm {
+        /// Path to a source file
+        source: PathBuf,
+    },
+    /// Print the tokens of a source file
+    Lex {
+        /// Path to a source file
+        source: PathBuf,
+    },
+    /// Format source files in place
+    Fmt {
+        /// Path to a source file or a directory of them
+        source: PathBuf,
+        /// Don't write the files, exit with 1 if any of them isn't formatted
+        #[clap(long)]
+        check: bool,
+    },
+    /// Print a detailed explanation of an error code
+    Explain {
+        /// Error code, e.g. E0004
+        code: String,
+    },
+}
+
+fn report(diagnostics: &Diagnostics, source_map: &SourceMap) {
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.display(source_map));
+    }
+
+    let errors = diagnostics.count(Severity::Error);
+    let warnings = diagnostics.count(Severity::Warning);
+
+    if warnings > 0 {
+        eprintln!("warning: {} warning(s) emitted", warnings);
+    }
+
+    if errors > 0 {
+        eprintln!("error: aborting due to {} error(s)", errors);
+        process::exit(1);
+    }
+}
+
+/// Loads `source`, or every source file in it if it is a directory.
+fn load(source: &Path, source_map: &mut SourceMap) -> io::Result<Vec<FileId>> {
+    if source.is_dir() {
+        (dqk_parser::find_files(source)?.into_iter())
+            .map(|path| source_map.load(path))
+            .collect()
+    } else {
+        Ok(vec![source_map.load(source)?])
+    }
+}
+
+/// Output of `dqk parse --json`.
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    version: u32,
+    /// Every interned string, indexed by the symbols in the programs.
+    symbols: Vec<&'a str>,
+    files: Vec<JsonFile<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    path: &'a Path,
+    program: Program,
+}
+
+fn parse(source: PathBuf, json: bool, diagnostics: &mut Diagnostics) {
+    let mut source_map = SourceMap::new();
+
+    let files = match load(&source, &mut source_map) {
+        Ok(files) => files,
+        Err(error) => {
+            eprintln!("error: cannot read '{}': {}", source.display(), error);
+            process::exit(1);
+        }
+    };
+
+    let interner = Interner::new();
+
+    let programs = dqk_parser::parse_sources(&files, &source_map, &interner, diagnostics);
+
+    if json {
+        let output = JsonOutput {
+            version: AST_SCHEMA_VERSION,
+            symbols: interner.iter().map(|(_, string)| string).collect(),
+            files: (programs.into_iter())
+                .map(|program| JsonFile {
+                    path: source_map.file_name(program.span()),
+                    program,
+                })
+                .collect(),
+        };
+
+        println!("{}", serde_json::to_string_pretty(&output).unwrap());
+    } else {
+        for program in programs {
+            println!("{:#?}", program);
+        }
+    }
+
+    report(diagnostics, &source_map);
+}
+
+fn check(source: PathBuf, diagnostics: &mut Diagnostics) {
+    let mut source_map = SourceMap::new();
+
+    let files = match load(&source, &mut source_map) {
+        Ok(files) => files,
+        Err(error) => {
+            eprintln!("error: cannot read '{}': {}", source.display(), error);
+            process::exit(1);
+        }
+    };
+
+    let interner = Interner::new();
+
+    for program in dqk_parser::parse_sources(&files, &source_map, &interner, diagnostics) {
+        let resolution = dqk_resolve::resolve(&program, &interner, diagnostics);
+        dqk_typeck::check(&program, &resolution, &interner, diagnostics);
+    }
+
+    report(diagnostics, &source_map);
+}
+
+/// Reads the event fixture of `dqk run --event`.
+fn load_event(path: &Path) -> EventData {
+    let json = fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|error| error.to_string()))
+        .and_then(|json| EventData::from_json(&json).map_err(|error| error.to_string()));
+
+    match json {
+        Ok(event) => event,
+        Err(error) => {
+            eprintln!("error: cannot read event '{}': {}", path.display(), error);
+            process::exit(1);
+        }
+    }
+}
+
+/// Loads, checks and lowers the single source file `source`, exiting if it
+/// has errors.
+fn lower(
+    source: &Path,
+    diagnostics: &mut Diagnostics,
+) -> (SourceMap, Interner, Resolution, dqk_hir::Program) {
+    let mut source_map = SourceMap::new();
+
+    let file = match source_map.load(source) {
+        Ok(file) => file,
+        Err(error) => {
+            eprintln!("error: cannot read '{}': {}", source.display(), error);
+            process::exit(1);
+        }
+    };
+
+    let interner = Interner::new();
+
+    let program = dqk_parser::parse_sources(&[file], &source_map, &interner, diagnostics).pop();
+    let program = match program {
+        Some(program) if !diagnostics.has_errors() => program,
+        _ => {
+            report(diagnostics, &source_map);
+            process::exit(1);
+        }
+    };
+
+    let resolution = dqk_resolve::resolve(&program, &interner, diagnostics);
+    let types = dqk_typeck::check(&program, &resolution, &interner, diagnostics);
+
+    if diagnostics.has_errors() {
+        report(diagnostics, &source_map);
+    }
+
+    let program = dqk_hir::lower(&program, &resolution, &types, &interner);
+
+    (source_map, interner, resolution, program)
+}
+
+fn run(source: PathBuf, event: Option<PathBuf>, vm: bool, diagnostics: &mut Diagnostics) {
+    let event = event.as_deref().map(load_event);
+    let (source_map, interner, resolution, program) = lower(&source, diagnostics);
+
+    let (result, actions) = if vm {
+        match dqk_interp::compile(&program, &resolution, &interner) {
+            Ok(module) => {
+                let mut vm = Vm::new(&module);
+
+                let result = vm.run().and_then(|()| match &event {
+                    Some(event) => vm.dispatch(event).map(|_| ()),
+                    None => Ok(()),
+                });
+
+                (result, vm.actions().to_vec())
+            }
+            Err(error) => (Err(error), Vec::new()),
+        }
+    } else {
+        let mut interpreter = Interpreter::new(&resolution, &interner);
+
+        let result = interpreter.run(&program).and_then(|()| match &event {
+            Some(event) => interpreter.dispatch(event).map(|_| ()),
+            None => Ok(()),
+        });
+
+        (result, interpreter.actions().to_vec())
+    };
+
+    for action in actions {
+        println!("{}", action);
+    }
+
+    if let Err(error) = result {
+        diagnostics.emit(error);
+    }
+
+    report(diagnostics, &source_map);
+}
+
+fn disasm(source: PathBuf, diagnostics: &mut Diagnostics) {
+    let (source_map, interner, resolution, program) = lower(&source, diagnostics);
+    match dqk_interp::compile(&program, &resolution, &interner) {
+        Ok(module) => print!("{}", module.disassemble(&source_map)),
+        Err(error) => diagnostics.emit(error),
+    }
+
+    report(diagnostics, &source_map);
+}
+
+fn lex(source: PathBuf, diagnostics: &mut Diagnostics) {
+    let mut source_map = SourceMap::new();
+
+    let file = match source_map.load(&source) {
+        Ok(file) => file,
+        Err(error) => {
+            eprintln!("error: cannot read '{}': {}", source.display(), error);
+            process::exit(1);
+        }
+    };
+
+    let interner = Interner::new();
+
+    println!("{:<10} {:<12} KIND", "POSITION", "SPAN");
+
+    for token in Lexer::new(source_map.source(file), file, &interner) {
+        match token {
+            Ok(token) => {
+                let span = token.span();
+
+                println!(
+                    "{:<10} {:<12} {}",
+                    source_map.line_col(span).to_string(),
+                    format!("{}..{}", span.start(), span.end()),
+                    token.kind().describe(&interner),
+                );
+            }
+            Err(error) => diagnostics.emit(error),
+        }
+    }
+
+    report(diagnostics, &source_map);
+}
+
+fn fmt(source: PathBuf, check: bool, diagnostics: &mut Diagnostics) {
+    let mut source_map = SourceMap::new();
+
+    let files = match load(&source, &mut source_map) {
+        Ok(files) => files,
+        Err(error) => {
+            eprintln!("error: cannot read '{}': {}", source.display(), error);
+            process::exit(1);
+        }
+    };
+
+    let mut unformatted = 0;
+
+    for file in files {
+        let src = source_map.source(file);
+        let path = source_map.file(file).name();
+
+        let formatted = match dqk_fmt::format_source(src, file, diagnostics) {
+            Some(formatted) if formatted != src => formatted,
+            _ => continue,
+        };
+
+        if check {
+            println!("{} is not formatted", path.display());
+            unformatted += 1;
+        } else if let Err(error) = fs::write(path, formatted) {
+            eprintln!("error: cannot write '{}': {}", path.display(), error);
+            process::exit(1);
+        }
+    }
+
+    report(diagnostics, &source_map);
+
+    if unformatted > 0 {
+        process::exit(1);
+    }
+}
+
+fn explain(code: String) {
+    match code.parse::<ErrorCode>() {
+        Ok(code) => print!("{}", code.explain()),
+        Err(_) => {
+            eprintln!("error: '{}' is not a valid error code", code);
+            process::exit(1);
+        }
+    }
 }
 
 fn main() {
     let args = Args::parse();
 
-    let source = fs::read_to_string(&args.source).unwrap();
-
-    let mut string_allocator = StringAllocator::new();
+    let mut diagnostics = Diagnostics::new();
 
-    let mut parser = Parser::new(&source, None, &mut string_allocator);
+    if args.deny.contains(&LintGroup::Warnings) {
+        diagnostics.deny_warnings();
+    } else if args.allow.contains(&LintGroup::Warnings) {
+        diagnostics.allow_warnings();
+    }
 
-    println!("{:#?}", parser.parse::<Stmt>());
+    match args.command {
+        Command::Parse { source, json } => parse(source, json, &mut diagnostics),
+        Command::Check { source } => check(source, &mut diagnostics),
+        Command::Run { source, event, vm } => run(source, event, vm, &mut diagnostics),
+        Command::Disasm { source } => disasm(source, &mut diagnostics),
+        Command::Lex { source } => lex(source, &mut diagnostics),
+        Command::Fmt { source, check } => fmt(source, check, &mut diagnostics),
+        Command::Explain { code } => explain(code),
+    }
 }